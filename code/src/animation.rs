@@ -0,0 +1,182 @@
+//! LED animations for the PowerUp / PowerDown phases
+//!
+//! The phase logic only moves the power stage (see [crate::ramp]); an [Animation] translates the stage
+//! into the duties of the LED channels. Alternative effects (wipe, center-out, twinkle on addressable strips)
+//! can be added as further implementations without touching the phase logic.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use crate::config::Config;
+use crate::LED_MAX_POWER_STAGE;
+
+pub trait Animation {
+    fn name(&self) -> &'static str;
+
+    /// Called with the max duty of the LED driver and the number of LED channels - at startup and on configuration changes
+    fn init(&mut self, config: &Config, max_duty: u32, channels: usize);
+
+    /// Duties per channel for the power stage (range [0..LED_MAX_POWER_STAGE])
+    fn tick(&mut self, stage: u32) -> Vec<u32>;
+}
+
+/// Mapping of the power stage to the relative LED power
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DimmingCurve {
+    /// `ln(x / 50 + 1)` - the original curve; steppy at the bottom end of high resolution PWM
+    Log,
+    /// CIE 1931 lightness - perceptually linear
+    Cie1931,
+    /// gamma 2.2
+    Gamma22,
+}
+
+impl DimmingCurve {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            DimmingCurve::Log => 0,
+            DimmingCurve::Cie1931 => 1,
+            DimmingCurve::Gamma22 => 2,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(DimmingCurve::Log),
+            1 => Some(DimmingCurve::Cie1931),
+            2 => Some(DimmingCurve::Gamma22),
+            _ => None
+        }
+    }
+
+    /// relative power [0..=1] for the power stage
+    fn power(self, stage: u32) -> f32 {
+        let x = stage as f32 / LED_MAX_POWER_STAGE as f32;
+        match self {
+            DimmingCurve::Log => f32::ln(stage as f32 / 50.0 + 1.0) / f32::ln(LED_MAX_POWER_STAGE as f32 / 50.0 + 1.0),
+            DimmingCurve::Cie1931 => {
+                let lightness = x * 100.0;
+                if lightness <= 8.0 {
+                    lightness / 903.3
+                } else {
+                    ((lightness + 16.0) / 116.0).powi(3)
+                }
+            }
+            DimmingCurve::Gamma22 => x.powf(2.2),
+        }
+    }
+}
+
+impl Display for DimmingCurve {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DimmingCurve::Log => "log",
+            DimmingCurve::Cie1931 => "cie1931",
+            DimmingCurve::Gamma22 => "gamma2.2",
+        })
+    }
+}
+
+impl FromStr for DimmingCurve {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "log" => Ok(DimmingCurve::Log),
+            "cie1931" => Ok(DimmingCurve::Cie1931),
+            "gamma2.2" => Ok(DimmingCurve::Gamma22),
+            _ => Err(anyhow!("unknown dimming curve '{}' (expected log, cie1931 or gamma2.2)", s))
+        }
+    }
+}
+
+/// Treatment of duties below the minimum duty (`min_duty_pct`)
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum MinDutyPolicy {
+    /// raise to the minimum duty
+    Clamp,
+    /// switch off
+    Snap,
+}
+
+impl MinDutyPolicy {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            MinDutyPolicy::Clamp => 0,
+            MinDutyPolicy::Snap => 1,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(MinDutyPolicy::Clamp),
+            1 => Some(MinDutyPolicy::Snap),
+            _ => None
+        }
+    }
+
+    /// The duty with the policy applied for the minimum duty - zero stays zero
+    pub fn apply(self, duty: u32, min_duty: u32) -> u32 {
+        match self {
+            _ if duty == 0 || duty >= min_duty => duty,
+            MinDutyPolicy::Clamp => min_duty,
+            MinDutyPolicy::Snap => 0,
+        }
+    }
+}
+
+impl Display for MinDutyPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MinDutyPolicy::Clamp => "clamp",
+            MinDutyPolicy::Snap => "snap",
+        })
+    }
+}
+
+impl FromStr for MinDutyPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clamp" => Ok(MinDutyPolicy::Clamp),
+            "snap" => Ok(MinDutyPolicy::Snap),
+            _ => Err(anyhow!("unknown min duty policy '{}' (expected clamp or snap)", s))
+        }
+    }
+}
+
+/// Default animation: all channels follow the configured [DimmingCurve]
+#[derive(Debug, Default)]
+pub struct CurveRamp {
+    /// duty per power stage, precomputed at init
+    table: Vec<u32>,
+    channels: usize,
+}
+
+impl CurveRamp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Animation for CurveRamp {
+    fn name(&self) -> &'static str {
+        "curve"
+    }
+
+    fn init(&mut self, config: &Config, max_duty: u32, channels: usize) {
+        self.table = (0..=LED_MAX_POWER_STAGE)
+            .map(|stage| (config.dimming_curve.power(stage) * max_duty as f32).round() as u32)
+            .collect();
+        self.channels = channels;
+        log::info!("LED dimming curve: {}, max duty: {}", config.dimming_curve, max_duty);
+    }
+
+    fn tick(&mut self, stage: u32) -> Vec<u32> {
+        let duty = self.table.get(stage as usize).copied().unwrap_or(0);
+        vec![duty; self.channels]
+    }
+}
@@ -0,0 +1,104 @@
+//! BLE GATT configuration service (opt-in)
+//!
+//! Lets a phone read and set the lux threshold and the max. brightness and read (or subscribe to) the live status
+//! without a serial connection - e.g. with a generic BLE app like nRF Connect. All characteristics need an
+//! authenticated pairing with the PIN `ble_pin`; the service is only started with a PIN.
+//! Values are UTF-8 text; written values are handed over to the main loop like `set` on the console.
+//! There are no Wi-Fi credentials - the ESP32-H2 has no Wi-Fi.
+//! The bonds of the paired phones are stored in NVS (wiped by a factory reset).
+//! With `local_only_analytics` the status leaves out the light phase, the presence and the duty (see [ExportedStatus]).
+
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use esp32_nimble::enums::{AuthReq, SecurityIOCap};
+use esp32_nimble::utilities::mutex::Mutex as BleMutex;
+use esp32_nimble::{uuid128, BLEAdvertisementData, BLECharacteristic, BLEDevice, NimbleProperties};
+
+use crate::config::{Config, ConfigWatch, Param};
+use crate::console::Command;
+use crate::event::ExportedStatus;
+
+/// NVS namespace of the bonds and keys, which the NimBLE host stores (`CONFIG_BT_NIMBLE_NVS_PERSIST`)
+pub const BOND_NVS_NAMESPACE: &str = "nimble_bond";
+const STATUS_UPDATE_PERIOD: Duration = Duration::from_secs(1);
+
+pub struct BleConfigService {
+    receiver: Receiver<Command>,
+    lux_threshold: Arc<BleMutex<BLECharacteristic>>,
+    max_brightness: Arc<BleMutex<BLECharacteristic>>,
+    status: Arc<BleMutex<BLECharacteristic>>,
+    last_update: Option<Instant>,
+    config_watch: ConfigWatch,
+}
+
+impl BleConfigService {
+    pub fn start(config: &Config) -> Result<Self> {
+        let device = BLEDevice::take();
+        BLEDevice::set_device_name(&config.hostname).map_err(|e| anyhow!("BLE device name: {:?}", e))?;
+        device.security()
+            .set_auth(AuthReq::all())
+            .set_passkey(config.ble_pin)
+            .set_io_cap(SecurityIOCap::DisplayOnly)
+            .resolve_rpa();
+
+        let service_uuid = uuid128!("6f1c0001-5d3c-4c56-9a3e-2b8c1f4e7a10");
+        let service = device.get_server().create_service(service_uuid);
+        let (sender, receiver) = mpsc::channel();
+        let writable = NimbleProperties::READ | NimbleProperties::READ_ENC | NimbleProperties::READ_AUTHEN
+            | NimbleProperties::WRITE | NimbleProperties::WRITE_ENC | NimbleProperties::WRITE_AUTHEN;
+
+        let lux_threshold = service.lock().create_characteristic(uuid128!("6f1c0002-5d3c-4c56-9a3e-2b8c1f4e7a10"), writable);
+        let lux_sender = sender.clone();
+        lux_threshold.lock().on_write(move |args| {
+            let value = String::from_utf8_lossy(args.recv_data()).trim().to_string();
+            let _ = lux_sender.send(Command::Set(Param::LuxThreshold, value));
+        });
+
+        let max_brightness = service.lock().create_characteristic(uuid128!("6f1c0003-5d3c-4c56-9a3e-2b8c1f4e7a10"), writable);
+        max_brightness.lock().on_write(move |args| {
+            let value = String::from_utf8_lossy(args.recv_data()).trim().to_string();
+            let _ = sender.send(Command::Set(Param::MaxBrightnessPct, value));
+        });
+
+        let status = service.lock().create_characteristic(
+            uuid128!("6f1c0004-5d3c-4c56-9a3e-2b8c1f4e7a10"),
+            NimbleProperties::READ | NimbleProperties::READ_ENC | NimbleProperties::READ_AUTHEN | NimbleProperties::NOTIFY,
+        );
+
+        device.get_advertising().lock()
+            .set_data(BLEAdvertisementData::new().name(&config.hostname).add_service_uuid(service_uuid))
+            .map_err(|e| anyhow!("BLE advertising data: {:?}", e))?;
+        device.get_advertising().lock().start().map_err(|e| anyhow!("BLE advertising: {:?}", e))?;
+        log::info!("BLE configuration service started");
+
+        Ok(Self { receiver, lux_threshold, max_brightness, status, last_update: None, config_watch: ConfigWatch::new() })
+    }
+
+    /// Returns the next written value as command (non-blocking)
+    pub fn poll(&self) -> Option<Command> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Refreshes the characteristic values - the settings on a change of the configuration - and notifies the status
+    pub fn update(&mut self, config: &Config, status: &ExportedStatus, now: Instant) {
+        if self.config_watch.changed(config) {
+            self.lux_threshold.lock().set_value(config.lux_threshold.to_string().as_bytes());
+            self.max_brightness.lock().set_value(config.max_brightness_pct.to_string().as_bytes());
+        }
+        if self.last_update.is_some_and(|t| now.duration_since(t) < STATUS_UPDATE_PERIOD) {
+            return;
+        }
+        self.last_update = Some(now);
+        let lux = status.lux.map_or("-".to_string(), |lux| format!("{:.1}", lux));
+        let text = match &status.occupancy {
+            Some(occupancy) => format!("mode: {}, phase: {:?}, lux: {}, presence: {}, duty: {}/{}",
+                                       status.mode, occupancy.phase, lux, occupancy.presence, occupancy.duty, occupancy.max_duty),
+            None => format!("mode: {}, lux: {}", status.mode, lux)
+        };
+        self.status.lock().set_value(text.as_bytes()).notify();
+    }
+}
@@ -0,0 +1,87 @@
+//! Startup bring-up of the components
+//!
+//! Each component is initialized on its own - transient failures (bus, flash) with a few attempts.
+//! The firmware boots with whatever succeeded and logs a per-component summary,
+//! instead of aborting on the first failure. Only if an essential component is missing, we give up.
+
+use anyhow::Result;
+use esp_idf_hal::delay::FreeRtos;
+
+const INIT_ATTEMPTS: u32 = 3;
+const RETRY_DELAY_MS: u32 = 100;
+
+struct ComponentResult {
+    component: &'static str,
+    attempts: u32,
+    /// `None` on success
+    error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Bringup {
+    results: Vec<ComponentResult>,
+}
+
+impl Bringup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Initializes a component with a single attempt (e.g. consuming peripherals)
+    pub fn init<T>(&mut self, component: &'static str, init: impl FnOnce() -> Result<T>) -> Option<T> {
+        let result = init();
+        self.record(component, 1, result)
+    }
+
+    /// Initializes a component with up to [INIT_ATTEMPTS] attempts
+    pub fn init_with_retries<T>(&mut self, component: &'static str, mut init: impl FnMut() -> Result<T>) -> Option<T> {
+        let mut attempts = 1;
+        loop {
+            match init() {
+                Err(e) if attempts < INIT_ATTEMPTS => {
+                    log::warn!("bring-up of {} failed (attempt {}): {} - retrying", component, attempts, e);
+                    FreeRtos::delay_ms(RETRY_DELAY_MS);
+                    attempts += 1;
+                }
+                result => return self.record(component, attempts, result)
+            }
+        }
+    }
+
+    /// Records the outcome of a component, which handles its initialization on its own
+    pub fn report(&mut self, component: &'static str, available: bool) {
+        self.results.push(ComponentResult {
+            component,
+            attempts: 1,
+            error: (!available).then(|| "not available".to_string()),
+        });
+    }
+
+    fn record<T>(&mut self, component: &'static str, attempts: u32, result: Result<T>) -> Option<T> {
+        let (value, error) = match result {
+            Ok(v) => (Some(v), None),
+            Err(e) => (None, Some(e.to_string()))
+        };
+        self.results.push(ComponentResult { component, attempts, error });
+        value
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.results.iter().filter(|r| r.error.is_some()).count()
+    }
+
+    /// Components which failed
+    pub fn failed_components(&self) -> impl Iterator<Item=&'static str> + '_ {
+        self.results.iter().filter(|r| r.error.is_some()).map(|r| r.component)
+    }
+
+    pub fn log_summary(&self) {
+        log::info!("bring-up summary: {} of {} components up", self.results.len() - self.failed_count(), self.results.len());
+        for r in &self.results {
+            match &r.error {
+                None => log::info!("  {}: ok", r.component),
+                Some(e) => log::error!("  {}: FAILED after {} attempt(s): {}", r.component, r.attempts, e)
+            }
+        }
+    }
+}
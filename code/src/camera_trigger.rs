@@ -0,0 +1,68 @@
+//! Camera trigger output on GPIO10 (opt-in)
+//!
+//! Fires a short pulse on the trigger pin at the start of a presence session (see [crate::presence_session]).
+//! Intended to trigger a stairwell camera or a snapshot automation. The timing is configurable: `camera_pre_delay_ms`,
+//! `camera_pulse_ms` and `camera_hold_s` (the quiet period of a session).
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use esp_idf_hal::gpio::{AnyOutputPin, Output, PinDriver};
+
+use crate::config::Config;
+use crate::event::{Event, EventQueue};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum TriggerState {
+    Idle,
+    Armed { fire_at: Instant },
+    Pulse { until: Instant },
+}
+
+pub struct CameraTrigger {
+    pin: PinDriver<'static, AnyOutputPin, Output>,
+    state: TriggerState,
+}
+
+impl CameraTrigger {
+    pub fn new(pin: PinDriver<'static, AnyOutputPin, Output>) -> Self {
+        Self {
+            pin,
+            state: TriggerState::Idle,
+        }
+    }
+
+    /// `session_started`: a presence session started (see [crate::presence_session::PresenceSession::update])
+    pub fn update(&mut self, session_started: bool, config: &Config, now: Instant, events: &mut EventQueue) -> Result<()> {
+        if session_started && self.state == TriggerState::Idle {
+            self.state = TriggerState::Armed { fire_at: now + Duration::from_millis(config.camera_pre_delay_ms as u64) };
+        }
+
+        match self.state {
+            TriggerState::Idle => (),
+            TriggerState::Armed { fire_at } => {
+                if now >= fire_at {
+                    self.pin.set_high()?;
+                    self.state = TriggerState::Pulse { until: now + Duration::from_millis(config.camera_pulse_ms as u64) };
+                    events.push(Event::CameraTriggered);
+                }
+            }
+            TriggerState::Pulse { until } => {
+                if now >= until {
+                    self.pin.set_low()?;
+                    self.state = TriggerState::Idle;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Time until the trigger needs the next update call to keep its timing
+    pub fn time_to_next_action(&self, now: Instant) -> Option<Duration> {
+        match self.state {
+            TriggerState::Idle => None,
+            TriggerState::Armed { fire_at } => Some(fire_at.saturating_duration_since(now)),
+            TriggerState::Pulse { until } => Some(until.saturating_duration_since(now)),
+        }
+    }
+}
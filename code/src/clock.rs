@@ -0,0 +1,69 @@
+//! Wall clock
+//!
+//! The local time of day for the scheduled features (fallback dark window, night light, ...).
+//! The system time is set by SNTP (feature `sntp`, needs a network connection); until then the local time is unknown
+//! and these features behave as documented for a missing wall clock.
+//! The time zone is a POSIX TZ string (`timezone`), e.g. `CET-1CEST,M3.5.0,M10.5.0/3`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use esp_idf_svc::sys;
+
+use crate::schedule::TimeOfDay;
+
+/// A system time before this (2024-01-01) is the unset clock, counting from 1970 since the boot
+const MIN_VALID_EPOCH_S: u64 = 1_704_067_200;
+
+pub struct Clock {
+    timezone: String,
+    #[cfg(feature = "sntp")]
+    _sntp: Option<esp_idf_svc::sntp::EspSntp<'static>>,
+}
+
+impl Clock {
+    pub fn new(timezone: &str) -> Self {
+        let mut clock = Self {
+            timezone: String::new(),
+            #[cfg(feature = "sntp")]
+            _sntp: start_sntp(),
+        };
+        clock.set_timezone(timezone);
+        clock
+    }
+
+    pub fn set_timezone(&mut self, timezone: &str) {
+        if timezone == self.timezone {
+            return;
+        }
+        self.timezone = timezone.to_string();
+        std::env::set_var("TZ", timezone);
+        // Safety: re-reads the TZ variable for the C time functions
+        unsafe { sys::tzset() };
+    }
+
+    /// Local time of day - `None` while the system time is not set
+    pub fn local_time(&self) -> Option<TimeOfDay> {
+        let now = unix_time_s()? as sys::time_t;
+        // Safety: plain C struct, completely written by localtime_r
+        let mut local: sys::tm = unsafe { std::mem::zeroed() };
+        unsafe { sys::localtime_r(&now, &mut local) };
+        TimeOfDay::from_minutes((local.tm_hour * 60 + local.tm_min) as u16)
+    }
+}
+
+fn unix_time_s() -> Option<u64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    (now >= MIN_VALID_EPOCH_S).then_some(now)
+}
+
+/// SNTP keeps the system time synchronized in the background (`pool.ntp.org`)
+#[cfg(feature = "sntp")]
+fn start_sntp() -> Option<esp_idf_svc::sntp::EspSntp<'static>> {
+    match esp_idf_svc::sntp::EspSntp::new_default() {
+        Ok(sntp) => Some(sntp),
+        Err(e) => {
+            log::warn!("SNTP not started: {}", e);
+            None
+        }
+    }
+}
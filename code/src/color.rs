@@ -0,0 +1,118 @@
+//! Color model of the LED output
+//!
+//! - Tunable white (CCT): a warm and a cold white channel are mixed to the requested color temperature.
+//! - RGBW: a color (HSV hue + saturation or a color temperature) is translated into the shares of the four channels.
+//!   The brightness (value) comes from the power stage of the ramp.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+
+/// Light color of an RGBW strip
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LedColor {
+    /// hue [0..360) in degrees, saturation [0..=100] in percent
+    Hsv { hue: u16, saturation: u8 },
+    /// color temperature [K]
+    Cct(u32),
+}
+
+impl Display for LedColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LedColor::Hsv { hue, saturation } => write!(f, "hsv:{},{}", hue, saturation),
+            LedColor::Cct(kelvin) => write!(f, "cct:{}", kelvin),
+        }
+    }
+}
+
+impl FromStr for LedColor {
+    type Err = anyhow::Error;
+
+    /// Format: `hsv:<hue>,<saturation>` or `cct:<kelvin>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (model, value) = s.split_once(':')
+            .ok_or_else(|| anyhow!("invalid color '{}' (expected hsv:<hue>,<saturation> or cct:<kelvin>)", s))?;
+        match model {
+            "hsv" => {
+                let (hue, saturation) = value.split_once(',')
+                    .ok_or_else(|| anyhow!("invalid color '{}' (expected hsv:<hue>,<saturation>)", s))?;
+                let hue: u16 = hue.parse()?;
+                let saturation: u8 = saturation.parse()?;
+                if hue >= 360 || saturation > 100 {
+                    bail!("invalid color '{}' (hue 0..360, saturation 0..=100)", s);
+                }
+                Ok(LedColor::Hsv { hue, saturation })
+            }
+            "cct" => {
+                let kelvin: u32 = value.parse()?;
+                if !(1000..=10000).contains(&kelvin) {
+                    bail!("invalid color '{}' (color temperature 1000..=10000)", s);
+                }
+                Ok(LedColor::Cct(kelvin))
+            }
+            _ => Err(anyhow!("unknown color model '{}' (expected hsv or cct)", model))
+        }
+    }
+}
+
+/// Shares `[warm, cold]` of the two white channels for the color temperature `cct_k`.
+/// Mixed linearly in mired (reciprocal color temperature), which is closer to the perceived color than kelvin.
+/// The shares sum up to 1, so the brightness stays about the same across color temperatures.
+/// The color temperature is limited to the range of the two channels.
+pub fn cct_shares(cct_k: u32, warm_k: u32, cold_k: u32) -> [f32; 2] {
+    if warm_k >= cold_k {
+        return [1.0, 0.0];
+    }
+    let mired = |kelvin: u32| 1_000_000.0 / kelvin as f32;
+    let cct_k = cct_k.clamp(warm_k, cold_k);
+    let cold = (mired(warm_k) - mired(cct_k)) / (mired(warm_k) - mired(cold_k));
+    [1.0 - cold, cold]
+}
+
+/// Shares `[red, green, blue, white]` of the channels for the color at full brightness.
+/// The part common to red, green and blue is moved to the white channel.
+pub fn rgbw_shares(color: LedColor) -> [f32; 4] {
+    let [r, g, b] = match color {
+        LedColor::Hsv { hue, saturation } => hsv_to_rgb(hue, saturation as f32 / 100.0),
+        LedColor::Cct(kelvin) => blackbody_rgb(kelvin),
+    };
+    let w = r.min(g).min(b);
+    [r - w, g - w, b - w, w]
+}
+
+/// RGB [0..=1] of a fully bright color
+fn hsv_to_rgb(hue: u16, saturation: f32) -> [f32; 3] {
+    let sector = hue as f32 / 60.0;
+    let x = 1.0 - (sector % 2.0 - 1.0).abs();
+    let [r, g, b] = match hue / 60 {
+        0 => [1.0, x, 0.0],
+        1 => [x, 1.0, 0.0],
+        2 => [0.0, 1.0, x],
+        3 => [0.0, x, 1.0],
+        4 => [x, 0.0, 1.0],
+        _ => [1.0, 0.0, x],
+    };
+    // desaturate towards white
+    [r, g, b].map(|c| 1.0 - saturation * (1.0 - c))
+}
+
+/// RGB [0..=1] of a black body at the color temperature - approximation by Tanner Helland, good within 1000..40000 K
+fn blackbody_rgb(kelvin: u32) -> [f32; 3] {
+    let t = kelvin as f32 / 100.0;
+    let r = if t <= 66.0 { 255.0 } else { 329.69873 * (t - 60.0).powf(-0.13320476) };
+    let g = if t <= 66.0 {
+        99.4708 * t.ln() - 161.11957
+    } else {
+        288.12217 * (t - 60.0).powf(-0.075514849)
+    };
+    let b = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.51773 * (t - 10.0).ln() - 305.04479
+    };
+    [r, g, b].map(|c| c.clamp(0.0, 255.0) / 255.0)
+}
@@ -0,0 +1,1950 @@
+//! Runtime configuration, persisted in NVS
+//!
+//! The stored values carry the version of their layout ([CONFIG_VERSION]). Parts of the firmware, which keep a copy
+//! of the configuration or derive state from it, follow the changes with a [ConfigWatch].
+
+use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::{anyhow, bail, Result};
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+
+use crate::LED_MAX_POWER_STAGE;
+use crate::animation::{DimmingCurve, MinDutyPolicy};
+use crate::color::LedColor;
+use crate::crosstalk::CrosstalkCurve;
+use crate::group::GroupRole;
+use crate::led_strip::StripEffect;
+use crate::light_sensor::{LuxCombine, SaturationPolicy, VemlGain, VEML_INTEGRATION_TIMES_MS};
+use crate::lux_filter::LuxFilterKind;
+use crate::peripheral::LED_PWM_CLOCK_HZ;
+use crate::profile;
+use crate::ramp::RampShape;
+use crate::schedule::TimeOfDay;
+
+pub const NVS_NAMESPACE: &str = "sensor-light";
+/// Version of the layout of the stored values - to be raised with an incompatible change of a stored value
+/// (key, encoding or meaning)
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Shown instead of a set credential (see [Config::get_redacted])
+pub const REDACTED: &str = "<redacted>";
+
+/// Source of the configuration revisions - unique across all copies (see [ConfigWatch])
+static NEXT_REVISION: AtomicU32 = AtomicU32::new(1);
+
+/// max. length of string values (incl. terminating zero)
+const MAX_STRING_LEN: usize = 512;
+
+// NVS keys are limited to 15 characters
+const KEY_CONFIG_VERSION: &str = "cfg_version";
+const KEY_LUX_THRESHOLD: &str = "lux_threshold";
+const KEY_LUX_FILTER: &str = "lux_filter";
+const KEY_LUX_WINDOW: &str = "lux_window";
+const KEY_RETRIGGER_GUARD_MS: &str = "retrig_guard";
+const KEY_RETRIGGER_CONFIRM_MS: &str = "retrig_confirm";
+const KEY_SAMPLING_GAP_INTERVAL_S: &str = "gap_interval";
+const KEY_SAMPLING_GAP_STAGE: &str = "gap_stage";
+const KEY_LED_LUX_CONTRIBUTION: &str = "led_lux";
+const KEY_FADE_UP_S: &str = "fade_up_s";
+const KEY_FADE_DOWN_S: &str = "fade_down_s";
+const KEY_ZONE_TRAIL_S: &str = "zone_trail";
+const KEY_LUX_CALIBRATION: &str = "lux_calib";
+const KEY_DARK_LATCH_MIN: &str = "dark_latch";
+const KEY_DARK_RELEASE_LUX: &str = "dark_rel_lux";
+const KEY_DARK_RELEASE_MIN: &str = "dark_rel_min";
+const KEY_DIAG_OPT_IN: &str = "diag_opt_in";
+const KEY_DIAG_URL: &str = "diag_url";
+const KEY_DAY_SLEEP_AFTER_MIN: &str = "day_sleep_after";
+const KEY_DAY_SLEEP_LUX: &str = "day_sleep_lux";
+const KEY_DAY_SLEEP_WAKE_MIN: &str = "day_sleep_wake";
+const KEY_VEML_GAIN: &str = "veml_gain";
+const KEY_VEML_IT_MS: &str = "veml_it";
+const KEY_VEML_PSM: &str = "veml_psm";
+const KEY_SATURATION_POLICY: &str = "saturation";
+const KEY_LUX_STATS_PERSIST: &str = "lux_stats_nvs";
+const KEY_LUX_HALF_LIFE_MS: &str = "lux_half_life";
+const KEY_SECOND_LIGHT_SENSOR: &str = "light_sensor_2";
+const KEY_LUX_COMBINE: &str = "lux_combine";
+const KEY_FALLBACK_DARK_FROM: &str = "fb_dark_from";
+const KEY_FALLBACK_DARK_UNTIL: &str = "fb_dark_until";
+const KEY_LATENCY_BUDGET_MS: &str = "latency_budget";
+const KEY_LED_CROSSTALK: &str = "led_crosstalk";
+const KEY_CROSSTALK_COMPENSATION: &str = "crosstalk_comp";
+const KEY_MAX_BRIGHTNESS_PCT: &str = "max_bright_pct";
+const KEY_ADAPTIVE_LUX_FACTOR: &str = "adaptive_lux";
+const KEY_DIMMING_CURVE: &str = "dimming_curve";
+const KEY_LED_HW_FADE: &str = "led_hw_fade";
+const KEY_SECOND_LED: &str = "second_led";
+const KEY_SECOND_LED_DELAY_MS: &str = "second_led_dly";
+const KEY_SECOND_LED_PCT: &str = "second_led_pct";
+const KEY_TUNABLE_WHITE: &str = "tunable_white";
+const KEY_CCT_WARM_K: &str = "cct_warm_k";
+const KEY_CCT_COLD_K: &str = "cct_cold_k";
+const KEY_CCT_K: &str = "cct_k";
+const KEY_CCT_NIGHT_K: &str = "cct_night_k";
+const KEY_CCT_NIGHT_FROM: &str = "cct_night_from";
+const KEY_CCT_NIGHT_UNTIL: &str = "cct_night_until";
+const KEY_LOCAL_ONLY_ANALYTICS: &str = "local_only";
+const KEY_RGBW: &str = "rgbw";
+const KEY_LED_COLOR: &str = "led_color";
+const KEY_LED_STRIP_PIXELS: &str = "led_strip_px";
+const KEY_LED_STRIP_RGBW: &str = "led_strip_rgbw";
+const KEY_STRIP_EFFECT: &str = "strip_effect";
+const KEY_CHASE_REVERSE: &str = "chase_reverse";
+const KEY_NIGHT_LIGHT_PCT: &str = "night_light_pct";
+const KEY_NIGHT_LIGHT_FROM: &str = "night_from";
+const KEY_NIGHT_LIGHT_UNTIL: &str = "night_until";
+const KEY_AMBIENT_BRIGHTNESS_PCT: &str = "ambient_bright";
+const KEY_PWM_FREQ_HZ: &str = "pwm_freq_hz";
+const KEY_PWM_RESOLUTION_BITS: &str = "pwm_res_bits";
+const KEY_INVERT_PWM: &str = "invert_pwm";
+const KEY_MIN_DUTY_PCT: &str = "min_duty_pct";
+const KEY_MIN_DUTY_POLICY: &str = "min_duty_pol";
+const KEY_MAX_ON_TIME_MIN: &str = "max_on_time";
+const KEY_PRESENCE_STAGE_PCT: &str = "presence_stage";
+const KEY_FULL_ON_AFTER_MS: &str = "full_on_after";
+const KEY_RAMP_SHAPE: &str = "ramp_shape";
+const KEY_LED_SELF_TEST: &str = "led_self_test";
+const KEY_STANDBY_GLOW_PCT: &str = "standby_glow";
+const KEY_TEMPERATURE_SENSOR: &str = "temp_sensor";
+const KEY_DERATE_FROM_C: &str = "derate_from";
+const KEY_DERATE_TO_C: &str = "derate_to";
+const KEY_DERATE_MIN_PCT: &str = "derate_min_pct";
+const KEY_LED_DITHER: &str = "led_dither";
+const KEY_LED_FULL_WATTS: &str = "led_full_watts";
+const KEY_FADE_TAKEOVER_MS: &str = "fade_takeover";
+const KEY_MQTT_URL: &str = "mqtt_url";
+const KEY_MQTT_TOPIC: &str = "mqtt_topic";
+const KEY_MQTT_HEARTBEAT_S: &str = "mqtt_heartbeat";
+const KEY_HA_DISCOVERY: &str = "ha_discovery";
+const KEY_API_TOKEN: &str = "api_token";
+const KEY_HOSTNAME: &str = "hostname";
+const KEY_TIMEZONE: &str = "timezone";
+const KEY_BLE_PIN: &str = "ble_pin";
+const KEY_SYSLOG_TARGET: &str = "syslog_target";
+const KEY_WEBHOOK_URLS: &str = "webhook_urls";
+const KEY_INFLUX_URL: &str = "influx_url";
+const KEY_INFLUX_TOKEN: &str = "influx_token";
+const KEY_INFLUX_INTERVAL_S: &str = "influx_int_s";
+const KEY_GROUP_ROLE: &str = "group_role";
+const KEY_GROUP_TOPIC: &str = "group_topic";
+const KEY_PROFILE: &str = "profile";
+/// names of the stored profiles (comma separated) and the key prefix of their values
+const KEY_PROFILE_NAMES: &str = "profiles";
+const KEY_PROFILE_PREFIX: &str = "prof_";
+/// the always-on mode (`persist_always_on`)
+const KEY_ALWAYS_ON: &str = "always_on";
+const KEY_PERSIST_ALWAYS_ON: &str = "persist_on";
+const KEY_CAMERA_PRE_DELAY_MS: &str = "cam_pre_delay";
+const KEY_CAMERA_PULSE_MS: &str = "cam_pulse";
+const KEY_CAMERA_HOLD_S: &str = "cam_hold";
+const KEY_THREAD_DATASET: &str = "thread_dataset";
+const KEY_HUE_USER: &str = "hue_user";
+
+/// Ambient light level [lux] at or below which we consider it dark enough for operation
+const DEFAULT_LUX_THRESHOLD: f32 = 30.0;
+
+/// Number of ambient light readings the lux filter works on
+const DEFAULT_LUX_WINDOW: u32 = 10;
+
+/// Refractory period [ms] after a completed power-down, in which presence triggers are ignored or need confirmation
+const DEFAULT_RETRIGGER_GUARD_MS: u32 = 3000;
+
+/// Time [ms] a presence needs to be continuously detected within the guard period to trigger a power-up
+/// (0 = presence triggers are ignored within the guard period)
+const DEFAULT_RETRIGGER_CONFIRM_MS: u32 = 1000;
+
+/// Interval [s] of lux sampling gaps while the light is on (0 = disabled)
+const DEFAULT_SAMPLING_GAP_INTERVAL_S: u32 = 0;
+
+/// LED power stage the light is dimmed to during a sampling gap
+const DEFAULT_SAMPLING_GAP_STAGE: u32 = 20;
+
+/// Calibrated lux the LED itself contributes to a reading at the sampling gap stage
+const DEFAULT_LED_LUX_CONTRIBUTION: f32 = 0.0;
+
+/// Duration [s] of a fade from off to full power
+const DEFAULT_FADE_UP_S: f32 = 5.0;
+
+/// Duration [s] of a fade from full power to off
+const DEFAULT_FADE_DOWN_S: f32 = 10.0;
+
+/// Time [s] after the last presence in a zone, until the zone brightness has faded out linearly (0 = disabled: power down right away)
+const DEFAULT_ZONE_TRAIL_S: u32 = 0;
+
+/// Multiplicative calibration factor applied to all lux readings (e.g. to compensate a diffusor)
+const DEFAULT_LUX_CALIBRATION: f32 = 1.0;
+
+/// Minutes of continuous darkness after which the dark state is latched (0 = disabled)
+const DEFAULT_DARK_LATCH_MIN: u32 = 0;
+
+/// Lux level, which needs to be exceeded to release a latched dark state
+const DEFAULT_DARK_RELEASE_LUX: f32 = 100.0;
+
+/// Minutes the lux level needs to stay above the release level to release a latched dark state
+const DEFAULT_DARK_RELEASE_MIN: u32 = 5;
+
+/// Minutes of continuous daylight (above the daylight sleep level) after which we go to deep sleep (0 = disabled)
+const DEFAULT_DAY_SLEEP_AFTER_MIN: u32 = 0;
+
+/// Lux level above which we consider it broad daylight (for the daylight deep sleep)
+const DEFAULT_DAY_SLEEP_LUX: f32 = 1000.0;
+
+/// Deep sleep duration [minutes] until the next daylight re-check
+const DEFAULT_DAY_SLEEP_WAKE_MIN: u32 = 5;
+
+/// VEML7700 integration time [ms]
+const DEFAULT_VEML_IT_MS: u32 = 100;
+
+/// VEML7700 power saving mode (0 = off, 1..=4)
+const DEFAULT_VEML_PSM: u32 = 0;
+
+/// Age [ms] after which a sample has half its weight in the time-weighted median lux filter
+const DEFAULT_LUX_HALF_LIFE_MS: u32 = 2000;
+
+/// Max. time [ms] from the presence signal edge to the first visible duty increase before we raise an alert (0 = disabled)
+const DEFAULT_LATENCY_BUDGET_MS: u32 = 150;
+
+/// Maximum LED power level [%] of the full driver duty
+const DEFAULT_MAX_BRIGHTNESS_PCT: u32 = 100;
+
+/// Delay [ms] of the second LED segment following the first one
+const DEFAULT_SECOND_LED_DELAY_MS: u32 = 1000;
+
+/// Color temperature [K] of the warm white channel
+const DEFAULT_CCT_WARM_K: u32 = 2700;
+
+/// Color temperature [K] of the cold white channel
+const DEFAULT_CCT_COLD_K: u32 = 6500;
+
+/// Color temperature [K] of the light
+const DEFAULT_CCT_K: u32 = 4000;
+
+/// Color temperature [K] of the light within the night window
+const DEFAULT_CCT_NIGHT_K: u32 = 2700;
+
+/// Warm white
+const DEFAULT_LED_COLOR: LedColor = LedColor::Cct(3000);
+
+/// Keeps the RMT signal of a strip update in the range of a few milliseconds (30 us per pixel)
+const MAX_LED_STRIP_PIXELS: u32 = 300;
+
+/// Brightness [%] within the night-light window
+const DEFAULT_NIGHT_LIGHT_PCT: u32 = 20;
+
+/// LEDC PWM frequency [Hz]
+const DEFAULT_PWM_FREQ_HZ: u32 = 5000;
+
+/// LEDC PWM resolution [bit]
+const DEFAULT_PWM_RESOLUTION_BITS: u32 = 12;
+
+const MIN_PWM_FREQ_HZ: u32 = 100;
+const MAX_PWM_FREQ_HZ: u32 = 40_000;
+const MIN_PWM_RESOLUTION_BITS: u32 = 8;
+const MAX_PWM_RESOLUTION_BITS: u32 = 14;
+const MAX_MIN_DUTY_PCT: f32 = 20.0;
+const MAX_STANDBY_GLOW_PCT: f32 = 10.0;
+
+const DEFAULT_MQTT_TOPIC: &str = "sensor-light";
+const DEFAULT_GROUP_TOPIC: &str = "sensor-light-group";
+const DEFAULT_HOSTNAME: &str = "sensor-light";
+const DEFAULT_TIMEZONE: &str = "UTC0";
+/// Period of the MQTT state heartbeat [s]
+const DEFAULT_MQTT_HEARTBEAT_S: u32 = 60;
+
+/// Interval [s] of the InfluxDB metrics push
+const DEFAULT_INFLUX_INTERVAL_S: u32 = 60;
+
+/// Delay between the first presence detection and the camera trigger pulse [ms]
+const DEFAULT_CAMERA_PRE_DELAY_MS: u32 = 0;
+/// Length of the camera trigger pulse [ms]
+const DEFAULT_CAMERA_PULSE_MS: u32 = 200;
+/// Time without presence, after which the next presence triggers the camera again [s]
+const DEFAULT_CAMERA_HOLD_S: u32 = 30;
+const MAX_CAMERA_PRE_DELAY_MS: u32 = 10_000;
+const MAX_CAMERA_PULSE_MS: u32 = 5000;
+const MAX_CAMERA_HOLD_S: u32 = 3600;
+/// An operational dataset has at most 254 bytes of TLVs - as hex string
+const MAX_THREAD_DATASET_LEN: usize = 2 * 254;
+/// Real bridges hand out user names of 40 characters
+const MAX_HUE_USER_LEN: usize = 40;
+
+/// Configuration parameters accessible by name (e.g. via the console)
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Param {
+    LuxThreshold,
+    LuxFilter,
+    LuxWindow,
+    RetriggerGuardMs,
+    RetriggerConfirmMs,
+    SamplingGapIntervalS,
+    SamplingGapStage,
+    LedLuxContribution,
+    FadeUpS,
+    FadeDownS,
+    ZoneTrailS,
+    LuxCalibration,
+    DarkLatchMin,
+    DarkReleaseLux,
+    DarkReleaseMin,
+    DiagOptIn,
+    DiagUrl,
+    DaySleepAfterMin,
+    DaySleepLux,
+    DaySleepWakeMin,
+    VemlGain,
+    VemlItMs,
+    VemlPsm,
+    SaturationPolicy,
+    LuxStatsPersist,
+    LuxHalfLifeMs,
+    SecondLightSensor,
+    LuxCombine,
+    FallbackDarkFrom,
+    FallbackDarkUntil,
+    LatencyBudgetMs,
+    LedCrosstalk,
+    CrosstalkCompensation,
+    MaxBrightnessPct,
+    AdaptiveLuxFactor,
+    DimmingCurve,
+    LedHwFade,
+    SecondLed,
+    SecondLedDelayMs,
+    SecondLedPct,
+    TunableWhite,
+    CctWarmK,
+    CctColdK,
+    CctK,
+    CctNightK,
+    CctNightFrom,
+    CctNightUntil,
+    LocalOnlyAnalytics,
+    Rgbw,
+    LedColor,
+    LedStripPixels,
+    LedStripRgbw,
+    StripEffect,
+    ChaseReverse,
+    NightLightPct,
+    NightLightFrom,
+    NightLightUntil,
+    AmbientBrightnessPct,
+    PwmFreqHz,
+    PwmResolutionBits,
+    InvertPwm,
+    MinDutyPct,
+    MinDutyPolicy,
+    MaxOnTimeMin,
+    PresenceStagePct,
+    FullOnAfterMs,
+    RampShape,
+    LedSelfTest,
+    StandbyGlowPct,
+    TemperatureSensor,
+    DerateFromC,
+    DerateToC,
+    DerateMinPct,
+    LedDither,
+    LedFullWatts,
+    FadeTakeoverMs,
+    MqttUrl,
+    MqttTopic,
+    MqttHeartbeatS,
+    HaDiscovery,
+    ApiToken,
+    Hostname,
+    Timezone,
+    BlePin,
+    SyslogTarget,
+    WebhookUrls,
+    InfluxUrl,
+    InfluxToken,
+    InfluxIntervalS,
+    GroupRole,
+    GroupTopic,
+    Profile,
+    PersistAlwaysOn,
+    CameraPreDelayMs,
+    CameraPulseMs,
+    CameraHoldS,
+    ThreadDataset,
+    HueUser,
+}
+
+impl Param {
+    /// All parameters
+    pub const ALL: &'static [Param] = &[
+        Param::LuxThreshold,
+        Param::LuxFilter,
+        Param::LuxWindow,
+        Param::RetriggerGuardMs,
+        Param::RetriggerConfirmMs,
+        Param::SamplingGapIntervalS,
+        Param::SamplingGapStage,
+        Param::LedLuxContribution,
+        Param::FadeUpS,
+        Param::FadeDownS,
+        Param::ZoneTrailS,
+        Param::LuxCalibration,
+        Param::DarkLatchMin,
+        Param::DarkReleaseLux,
+        Param::DarkReleaseMin,
+        Param::DiagOptIn,
+        Param::DiagUrl,
+        Param::DaySleepAfterMin,
+        Param::DaySleepLux,
+        Param::DaySleepWakeMin,
+        Param::VemlGain,
+        Param::VemlItMs,
+        Param::VemlPsm,
+        Param::SaturationPolicy,
+        Param::LuxStatsPersist,
+        Param::LuxHalfLifeMs,
+        Param::SecondLightSensor,
+        Param::LuxCombine,
+        Param::FallbackDarkFrom,
+        Param::FallbackDarkUntil,
+        Param::LatencyBudgetMs,
+        Param::LedCrosstalk,
+        Param::CrosstalkCompensation,
+        Param::MaxBrightnessPct,
+        Param::AdaptiveLuxFactor,
+        Param::DimmingCurve,
+        Param::LedHwFade,
+        Param::SecondLed,
+        Param::SecondLedDelayMs,
+        Param::SecondLedPct,
+        Param::TunableWhite,
+        Param::CctWarmK,
+        Param::CctColdK,
+        Param::CctK,
+        Param::CctNightK,
+        Param::CctNightFrom,
+        Param::CctNightUntil,
+        Param::LocalOnlyAnalytics,
+        Param::Rgbw,
+        Param::LedColor,
+        Param::LedStripPixels,
+        Param::LedStripRgbw,
+        Param::StripEffect,
+        Param::ChaseReverse,
+        Param::NightLightPct,
+        Param::NightLightFrom,
+        Param::NightLightUntil,
+        Param::AmbientBrightnessPct,
+        Param::PwmFreqHz,
+        Param::PwmResolutionBits,
+        Param::InvertPwm,
+        Param::MinDutyPct,
+        Param::MinDutyPolicy,
+        Param::MaxOnTimeMin,
+        Param::PresenceStagePct,
+        Param::FullOnAfterMs,
+        Param::RampShape,
+        Param::LedSelfTest,
+        Param::StandbyGlowPct,
+        Param::TemperatureSensor,
+        Param::DerateFromC,
+        Param::DerateToC,
+        Param::DerateMinPct,
+        Param::LedDither,
+        Param::LedFullWatts,
+        Param::FadeTakeoverMs,
+        Param::MqttUrl,
+        Param::MqttTopic,
+        Param::MqttHeartbeatS,
+        Param::HaDiscovery,
+        Param::ApiToken,
+        Param::Hostname,
+        Param::Timezone,
+        Param::BlePin,
+        Param::SyslogTarget,
+        Param::WebhookUrls,
+        Param::InfluxUrl,
+        Param::InfluxToken,
+        Param::InfluxIntervalS,
+        Param::GroupRole,
+        Param::GroupTopic,
+        Param::Profile,
+        Param::PersistAlwaysOn,
+        Param::CameraPreDelayMs,
+        Param::CameraPulseMs,
+        Param::CameraHoldS,
+        Param::ThreadDataset,
+        Param::HueUser,
+    ];
+
+    /// Whether the value may contain credentials
+    pub fn is_credential(self) -> bool {
+        matches!(self, Param::ApiToken | Param::MqttUrl | Param::WebhookUrls | Param::InfluxUrl | Param::InfluxToken
+            | Param::ThreadDataset | Param::HueUser)
+    }
+
+    /// Whether the value is a setting of the ambient light sensor hardware (applied by reconfiguring the sensors)
+    pub fn configures_light_sensor(self) -> bool {
+        matches!(self, Param::VemlGain | Param::VemlItMs | Param::VemlPsm | Param::SaturationPolicy)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Param::LuxThreshold => "lux_threshold",
+            Param::LuxFilter => "lux_filter",
+            Param::LuxWindow => "lux_window",
+            Param::RetriggerGuardMs => "retrigger_guard_ms",
+            Param::RetriggerConfirmMs => "retrigger_confirm_ms",
+            Param::SamplingGapIntervalS => "sampling_gap_interval_s",
+            Param::SamplingGapStage => "sampling_gap_stage",
+            Param::LedLuxContribution => "led_lux_contribution",
+            Param::FadeUpS => "fade_up_s",
+            Param::FadeDownS => "fade_down_s",
+            Param::ZoneTrailS => "zone_trail_s",
+            Param::LuxCalibration => "lux_calibration",
+            Param::DarkLatchMin => "dark_latch_min",
+            Param::DarkReleaseLux => "dark_release_lux",
+            Param::DarkReleaseMin => "dark_release_min",
+            Param::DiagOptIn => "diag_opt_in",
+            Param::DiagUrl => "diag_url",
+            Param::DaySleepAfterMin => "day_sleep_after_min",
+            Param::DaySleepLux => "day_sleep_lux",
+            Param::DaySleepWakeMin => "day_sleep_wake_min",
+            Param::VemlGain => "veml_gain",
+            Param::VemlItMs => "veml_it_ms",
+            Param::VemlPsm => "veml_psm",
+            Param::SaturationPolicy => "saturation_policy",
+            Param::LuxStatsPersist => "lux_stats_persist",
+            Param::LuxHalfLifeMs => "lux_half_life_ms",
+            Param::SecondLightSensor => "second_light_sensor",
+            Param::LuxCombine => "lux_combine",
+            Param::FallbackDarkFrom => "fallback_dark_from",
+            Param::FallbackDarkUntil => "fallback_dark_until",
+            Param::LatencyBudgetMs => "latency_budget_ms",
+            Param::LedCrosstalk => "led_crosstalk",
+            Param::CrosstalkCompensation => "crosstalk_compensation",
+            Param::MaxBrightnessPct => "max_brightness_pct",
+            Param::AdaptiveLuxFactor => "adaptive_lux_factor",
+            Param::DimmingCurve => "dimming_curve",
+            Param::LedHwFade => "led_hw_fade",
+            Param::SecondLed => "second_led",
+            Param::SecondLedDelayMs => "second_led_delay_ms",
+            Param::SecondLedPct => "second_led_pct",
+            Param::TunableWhite => "tunable_white",
+            Param::CctWarmK => "cct_warm_k",
+            Param::CctColdK => "cct_cold_k",
+            Param::CctK => "cct_k",
+            Param::CctNightK => "cct_night_k",
+            Param::CctNightFrom => "cct_night_from",
+            Param::CctNightUntil => "cct_night_until",
+            Param::LocalOnlyAnalytics => "local_only_analytics",
+            Param::Rgbw => "rgbw",
+            Param::LedColor => "led_color",
+            Param::LedStripPixels => "led_strip_pixels",
+            Param::LedStripRgbw => "led_strip_rgbw",
+            Param::StripEffect => "strip_effect",
+            Param::ChaseReverse => "chase_reverse",
+            Param::NightLightPct => "night_light_pct",
+            Param::NightLightFrom => "night_light_from",
+            Param::NightLightUntil => "night_light_until",
+            Param::AmbientBrightnessPct => "ambient_brightness_pct",
+            Param::PwmFreqHz => "pwm_freq_hz",
+            Param::PwmResolutionBits => "pwm_resolution_bits",
+            Param::InvertPwm => "invert_pwm",
+            Param::MinDutyPct => "min_duty_pct",
+            Param::MinDutyPolicy => "min_duty_policy",
+            Param::MaxOnTimeMin => "max_on_time_min",
+            Param::PresenceStagePct => "presence_stage_pct",
+            Param::FullOnAfterMs => "full_on_after_ms",
+            Param::RampShape => "ramp_shape",
+            Param::LedSelfTest => "led_self_test",
+            Param::StandbyGlowPct => "standby_glow_pct",
+            Param::TemperatureSensor => "temperature_sensor",
+            Param::DerateFromC => "derate_from_c",
+            Param::DerateToC => "derate_to_c",
+            Param::DerateMinPct => "derate_min_pct",
+            Param::LedDither => "led_dither",
+            Param::LedFullWatts => "led_full_watts",
+            Param::FadeTakeoverMs => "fade_takeover_ms",
+            Param::MqttUrl => "mqtt_url",
+            Param::MqttTopic => "mqtt_topic",
+            Param::MqttHeartbeatS => "mqtt_heartbeat_s",
+            Param::HaDiscovery => "ha_discovery",
+            Param::ApiToken => "api_token",
+            Param::Hostname => "hostname",
+            Param::Timezone => "timezone",
+            Param::BlePin => "ble_pin",
+            Param::SyslogTarget => "syslog_target",
+            Param::WebhookUrls => "webhook_urls",
+            Param::InfluxUrl => "influx_url",
+            Param::InfluxToken => "influx_token",
+            Param::InfluxIntervalS => "influx_interval_s",
+            Param::GroupRole => "group_role",
+            Param::GroupTopic => "group_topic",
+            Param::Profile => "profile",
+            Param::PersistAlwaysOn => "persist_always_on",
+            Param::CameraPreDelayMs => "camera_pre_delay_ms",
+            Param::CameraPulseMs => "camera_pulse_ms",
+            Param::CameraHoldS => "camera_hold_s",
+            Param::ThreadDataset => "thread_dataset",
+            Param::HueUser => "hue_user",
+        }
+    }
+}
+
+impl Display for Param {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Param {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lux_threshold" => Ok(Param::LuxThreshold),
+            "lux_filter" => Ok(Param::LuxFilter),
+            "lux_window" => Ok(Param::LuxWindow),
+            "retrigger_guard_ms" => Ok(Param::RetriggerGuardMs),
+            "retrigger_confirm_ms" => Ok(Param::RetriggerConfirmMs),
+            "sampling_gap_interval_s" => Ok(Param::SamplingGapIntervalS),
+            "sampling_gap_stage" => Ok(Param::SamplingGapStage),
+            "led_lux_contribution" => Ok(Param::LedLuxContribution),
+            "fade_up_s" => Ok(Param::FadeUpS),
+            "fade_down_s" => Ok(Param::FadeDownS),
+            "zone_trail_s" => Ok(Param::ZoneTrailS),
+            "lux_calibration" => Ok(Param::LuxCalibration),
+            "dark_latch_min" => Ok(Param::DarkLatchMin),
+            "dark_release_lux" => Ok(Param::DarkReleaseLux),
+            "dark_release_min" => Ok(Param::DarkReleaseMin),
+            "diag_opt_in" => Ok(Param::DiagOptIn),
+            "diag_url" => Ok(Param::DiagUrl),
+            "day_sleep_after_min" => Ok(Param::DaySleepAfterMin),
+            "day_sleep_lux" => Ok(Param::DaySleepLux),
+            "day_sleep_wake_min" => Ok(Param::DaySleepWakeMin),
+            "veml_gain" => Ok(Param::VemlGain),
+            "veml_it_ms" => Ok(Param::VemlItMs),
+            "veml_psm" => Ok(Param::VemlPsm),
+            "saturation_policy" => Ok(Param::SaturationPolicy),
+            "lux_stats_persist" => Ok(Param::LuxStatsPersist),
+            "lux_half_life_ms" => Ok(Param::LuxHalfLifeMs),
+            "second_light_sensor" => Ok(Param::SecondLightSensor),
+            "lux_combine" => Ok(Param::LuxCombine),
+            "fallback_dark_from" => Ok(Param::FallbackDarkFrom),
+            "fallback_dark_until" => Ok(Param::FallbackDarkUntil),
+            "latency_budget_ms" => Ok(Param::LatencyBudgetMs),
+            "led_crosstalk" => Ok(Param::LedCrosstalk),
+            "crosstalk_compensation" => Ok(Param::CrosstalkCompensation),
+            "max_brightness_pct" => Ok(Param::MaxBrightnessPct),
+            "adaptive_lux_factor" => Ok(Param::AdaptiveLuxFactor),
+            "dimming_curve" => Ok(Param::DimmingCurve),
+            "led_hw_fade" => Ok(Param::LedHwFade),
+            "second_led" => Ok(Param::SecondLed),
+            "second_led_delay_ms" => Ok(Param::SecondLedDelayMs),
+            "second_led_pct" => Ok(Param::SecondLedPct),
+            "tunable_white" => Ok(Param::TunableWhite),
+            "cct_warm_k" => Ok(Param::CctWarmK),
+            "cct_cold_k" => Ok(Param::CctColdK),
+            "cct_k" => Ok(Param::CctK),
+            "cct_night_k" => Ok(Param::CctNightK),
+            "cct_night_from" => Ok(Param::CctNightFrom),
+            "cct_night_until" => Ok(Param::CctNightUntil),
+            "local_only_analytics" => Ok(Param::LocalOnlyAnalytics),
+            "rgbw" => Ok(Param::Rgbw),
+            "led_color" => Ok(Param::LedColor),
+            "led_strip_pixels" => Ok(Param::LedStripPixels),
+            "led_strip_rgbw" => Ok(Param::LedStripRgbw),
+            "strip_effect" => Ok(Param::StripEffect),
+            "chase_reverse" => Ok(Param::ChaseReverse),
+            "night_light_pct" => Ok(Param::NightLightPct),
+            "night_light_from" => Ok(Param::NightLightFrom),
+            "night_light_until" => Ok(Param::NightLightUntil),
+            "ambient_brightness_pct" => Ok(Param::AmbientBrightnessPct),
+            "pwm_freq_hz" => Ok(Param::PwmFreqHz),
+            "pwm_resolution_bits" => Ok(Param::PwmResolutionBits),
+            "invert_pwm" => Ok(Param::InvertPwm),
+            "min_duty_pct" => Ok(Param::MinDutyPct),
+            "min_duty_policy" => Ok(Param::MinDutyPolicy),
+            "max_on_time_min" => Ok(Param::MaxOnTimeMin),
+            "presence_stage_pct" => Ok(Param::PresenceStagePct),
+            "full_on_after_ms" => Ok(Param::FullOnAfterMs),
+            "ramp_shape" => Ok(Param::RampShape),
+            "led_self_test" => Ok(Param::LedSelfTest),
+            "standby_glow_pct" => Ok(Param::StandbyGlowPct),
+            "temperature_sensor" => Ok(Param::TemperatureSensor),
+            "derate_from_c" => Ok(Param::DerateFromC),
+            "derate_to_c" => Ok(Param::DerateToC),
+            "derate_min_pct" => Ok(Param::DerateMinPct),
+            "led_dither" => Ok(Param::LedDither),
+            "led_full_watts" => Ok(Param::LedFullWatts),
+            "fade_takeover_ms" => Ok(Param::FadeTakeoverMs),
+            "mqtt_url" => Ok(Param::MqttUrl),
+            "mqtt_topic" => Ok(Param::MqttTopic),
+            "mqtt_heartbeat_s" => Ok(Param::MqttHeartbeatS),
+            "ha_discovery" => Ok(Param::HaDiscovery),
+            "api_token" => Ok(Param::ApiToken),
+            "hostname" => Ok(Param::Hostname),
+            "timezone" => Ok(Param::Timezone),
+            "ble_pin" => Ok(Param::BlePin),
+            "syslog_target" => Ok(Param::SyslogTarget),
+            "webhook_urls" => Ok(Param::WebhookUrls),
+            "influx_url" => Ok(Param::InfluxUrl),
+            "influx_token" => Ok(Param::InfluxToken),
+            "influx_interval_s" => Ok(Param::InfluxIntervalS),
+            "group_role" => Ok(Param::GroupRole),
+            "group_topic" => Ok(Param::GroupTopic),
+            "profile" => Ok(Param::Profile),
+            "persist_always_on" => Ok(Param::PersistAlwaysOn),
+            "camera_pre_delay_ms" => Ok(Param::CameraPreDelayMs),
+            "camera_pulse_ms" => Ok(Param::CameraPulseMs),
+            "camera_hold_s" => Ok(Param::CameraHoldS),
+            "thread_dataset" => Ok(Param::ThreadDataset),
+            "hue_user" => Ok(Param::HueUser),
+            _ => Err(anyhow!("unknown parameter '{}'", s))
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Config {
+    /// changes with every change of a value (see [ConfigWatch]) - not part of the value itself
+    revision: u32,
+    pub lux_threshold: f32,
+    pub lux_filter: LuxFilterKind,
+    pub lux_window: u32,
+    pub retrigger_guard_ms: u32,
+    pub retrigger_confirm_ms: u32,
+    pub sampling_gap_interval_s: u32,
+    pub sampling_gap_stage: u32,
+    pub led_lux_contribution: f32,
+    /// fade durations [s] for the full power range - partial fades take proportionally less time
+    pub fade_up_s: f32,
+    pub fade_down_s: f32,
+    pub zone_trail_s: u32,
+    pub lux_calibration: f32,
+    pub dark_latch_min: u32,
+    pub dark_release_lux: f32,
+    pub dark_release_min: u32,
+    /// explicit opt-in for uploading diagnostic reports
+    pub diag_opt_in: bool,
+    /// HTTP(S) endpoint diagnostic reports are POSTed to
+    pub diag_url: String,
+    pub day_sleep_after_min: u32,
+    pub day_sleep_lux: f32,
+    pub day_sleep_wake_min: u32,
+    pub veml_gain: VemlGain,
+    pub veml_it_ms: u32,
+    pub veml_psm: u32,
+    pub saturation_policy: SaturationPolicy,
+    pub lux_stats_persist: bool,
+    pub lux_half_life_ms: u32,
+    pub second_light_sensor: bool,
+    pub lux_combine: LuxCombine,
+    pub fallback_dark_from: TimeOfDay,
+    pub fallback_dark_until: TimeOfDay,
+    pub latency_budget_ms: u32,
+    /// measured LED lux contribution (see `calibrate crosstalk`)
+    pub led_crosstalk: Option<CrosstalkCurve>,
+    pub crosstalk_compensation: bool,
+    /// maximum LED power level [%] (1..=100)
+    pub max_brightness_pct: u32,
+    /// darkness threshold relative to the nightly lux baseline (0 = disabled)
+    pub adaptive_lux_factor: f32,
+    pub dimming_curve: DimmingCurve,
+    /// LEDC hardware fading between the ramp segments instead of software stepping
+    pub led_hw_fade: bool,
+    /// second LED segment on its own PWM channel (applied after a restart)
+    pub second_led: bool,
+    pub second_led_delay_ms: u32,
+    /// brightness of the second LED segment [%] relative to the first one
+    pub second_led_pct: u32,
+    /// the two LED outputs drive the warm and cold white channels of a tunable white strip (instead of two segments)
+    pub tunable_white: bool,
+    pub cct_warm_k: u32,
+    pub cct_cold_k: u32,
+    /// color temperature [K] of the light (tunable white)
+    pub cct_k: u32,
+    /// color temperature [K] within the night window (tunable white)
+    pub cct_night_k: u32,
+    pub cct_night_from: TimeOfDay,
+    pub cct_night_until: TimeOfDay,
+    /// privacy: occupancy events stay on the device - telemetry gets aggregate counts only
+    pub local_only_analytics: bool,
+    /// four LED outputs drive the red, green, blue and white channel of an RGBW strip
+    pub rgbw: bool,
+    /// color of the presence light (RGBW)
+    pub led_color: LedColor,
+    /// number of pixels of an addressable LED strip (0 = PWM LED outputs)
+    pub led_strip_pixels: u32,
+    /// the addressable strip has a white channel (SK6812 RGBW)
+    pub led_strip_rgbw: bool,
+    /// how an addressable strip follows the power stage
+    pub strip_effect: StripEffect,
+    /// the chase effect starts at the far end of the strip
+    pub chase_reverse: bool,
+    /// brightness [%] of the power curve within the night-light window
+    pub night_light_pct: u32,
+    /// start of the night-light window; equal start and end = disabled
+    pub night_light_from: TimeOfDay,
+    pub night_light_until: TimeOfDay,
+    /// brightness [%] at 0 lux, rising linearly to 100 % at the lux threshold (100 = not adaptive)
+    pub ambient_brightness_pct: u32,
+    /// PWM frequency of the LED outputs [Hz] - applied after a restart
+    pub pwm_freq_hz: u32,
+    /// duty resolution of the LED outputs [bit] - applied after a restart
+    pub pwm_resolution_bits: u32,
+    /// inverted PWM signal (NPN gate driver stage) - applied after a restart
+    pub invert_pwm: bool,
+    /// minimum duty [% of the full duty] of a lit LED channel (0 = none)
+    pub min_duty_pct: f32,
+    /// treatment of duties below `min_duty_pct`
+    pub min_duty_policy: MinDutyPolicy,
+    /// safety cutoff: max. continuous on-time [min] in auto mode (0 = none)
+    pub max_on_time_min: u32,
+    /// two-stage brightness: power stage [%] of a fresh presence (100 = full brightness at once)
+    pub presence_stage_pct: u32,
+    /// two-stage brightness: presence duration [ms] for full brightness
+    pub full_on_after_ms: u32,
+    /// progress of a fade over its time
+    pub ramp_shape: RampShape,
+    /// LED self-test at startup
+    pub led_self_test: bool,
+    /// faint standby light [% of the max. duty] while off in the dark (0 = none)
+    pub standby_glow_pct: f32,
+    /// LM75 / TMP102 temperature sensor on the second I2C bus for the thermal derating (applied after a restart)
+    pub temperature_sensor: bool,
+    /// thermal derating: temperature [°C] above which the max. duty is reduced
+    pub derate_from_c: f32,
+    /// thermal derating: temperature [°C] of the full reduction
+    pub derate_to_c: f32,
+    /// thermal derating: max. duty [%] at `derate_to_c` and above (also without a valid temperature reading)
+    pub derate_min_pct: u32,
+    /// temporal dithering of the PWM outputs for finer duties (applied after a restart)
+    pub led_dither: bool,
+    /// power [W] of the LEDs at full duty - for the energy estimate (0 = no estimate)
+    pub led_full_watts: f32,
+    /// velocity controlled fades: time [ms] to reach the fade rate from standstill (0 = constant rate)
+    pub fade_takeover_ms: u32,
+    /// MQTT broker the state is published to (e.g. `mqtt://broker:1883`) - empty = disabled
+    pub mqtt_url: String,
+    /// base topic of the published state
+    pub mqtt_topic: String,
+    /// period [s] the state is published also without a transition (0 = transitions only)
+    pub mqtt_heartbeat_s: u32,
+    /// publish Home Assistant MQTT discovery messages
+    pub ha_discovery: bool,
+    /// bearer token the REST API and the TCP console expect - empty = `POST` requests and firmware updates refused, no TCP console
+    pub api_token: String,
+    /// mDNS host name (`<hostname>.local`)
+    pub hostname: String,
+    /// POSIX TZ string of the local time
+    pub timezone: String,
+    /// pairing PIN of the BLE configuration service (0 = service disabled)
+    pub ble_pin: u32,
+    /// syslog host (`<host>[:<port>]`) the log is mirrored to; empty = disabled
+    pub syslog_target: String,
+    /// HTTP(S) endpoints events are POSTed to (comma separated)
+    pub webhook_urls: String,
+    /// InfluxDB write endpoint the metrics are pushed to (empty = disabled)
+    pub influx_url: String,
+    /// InfluxDB API token (empty = no authentication)
+    pub influx_token: String,
+    /// interval [s] of the InfluxDB metrics push
+    pub influx_interval_s: u32,
+    /// role in a group of lights acting as one
+    pub group_role: GroupRole,
+    /// MQTT base topic of the group
+    pub group_topic: String,
+    /// selected configuration profile (empty = none)
+    pub profile: String,
+    /// the always-on mode survives a restart
+    pub persist_always_on: bool,
+    /// delay between the first presence detection and the start of the camera trigger pulse [ms]
+    pub camera_pre_delay_ms: u32,
+    /// length of the camera trigger pulse [ms]
+    pub camera_pulse_ms: u32,
+    /// time without any presence, after which the next presence counts as a first detection again [s]
+    pub camera_hold_s: u32,
+    /// active operational dataset of the Thread network (TLVs as hex string) - empty = not joined
+    pub thread_dataset: String,
+    /// user name of the paired Hue clients (generated by `hue pair`) - empty = not paired
+    pub hue_user: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            revision: 0,
+            lux_threshold: DEFAULT_LUX_THRESHOLD,
+            lux_filter: LuxFilterKind::TimeWeightedMedian,
+            lux_window: DEFAULT_LUX_WINDOW,
+            retrigger_guard_ms: DEFAULT_RETRIGGER_GUARD_MS,
+            retrigger_confirm_ms: DEFAULT_RETRIGGER_CONFIRM_MS,
+            sampling_gap_interval_s: DEFAULT_SAMPLING_GAP_INTERVAL_S,
+            sampling_gap_stage: DEFAULT_SAMPLING_GAP_STAGE,
+            led_lux_contribution: DEFAULT_LED_LUX_CONTRIBUTION,
+            fade_up_s: DEFAULT_FADE_UP_S,
+            fade_down_s: DEFAULT_FADE_DOWN_S,
+            zone_trail_s: DEFAULT_ZONE_TRAIL_S,
+            lux_calibration: DEFAULT_LUX_CALIBRATION,
+            dark_latch_min: DEFAULT_DARK_LATCH_MIN,
+            dark_release_lux: DEFAULT_DARK_RELEASE_LUX,
+            dark_release_min: DEFAULT_DARK_RELEASE_MIN,
+            diag_opt_in: false,
+            diag_url: String::new(),
+            day_sleep_after_min: DEFAULT_DAY_SLEEP_AFTER_MIN,
+            day_sleep_lux: DEFAULT_DAY_SLEEP_LUX,
+            day_sleep_wake_min: DEFAULT_DAY_SLEEP_WAKE_MIN,
+            veml_gain: VemlGain::One,
+            veml_it_ms: DEFAULT_VEML_IT_MS,
+            veml_psm: DEFAULT_VEML_PSM,
+            saturation_policy: SaturationPolicy::Clamp,
+            lux_stats_persist: false,
+            lux_half_life_ms: DEFAULT_LUX_HALF_LIFE_MS,
+            second_light_sensor: false,
+            lux_combine: LuxCombine::Min,
+            fallback_dark_from: TimeOfDay::MIDNIGHT,
+            fallback_dark_until: TimeOfDay::MIDNIGHT,
+            latency_budget_ms: DEFAULT_LATENCY_BUDGET_MS,
+            led_crosstalk: None,
+            crosstalk_compensation: false,
+            max_brightness_pct: DEFAULT_MAX_BRIGHTNESS_PCT,
+            adaptive_lux_factor: 0.0,
+            dimming_curve: DimmingCurve::Cie1931,
+            led_hw_fade: false,
+            second_led: false,
+            second_led_delay_ms: DEFAULT_SECOND_LED_DELAY_MS,
+            second_led_pct: 100,
+            tunable_white: false,
+            cct_warm_k: DEFAULT_CCT_WARM_K,
+            cct_cold_k: DEFAULT_CCT_COLD_K,
+            cct_k: DEFAULT_CCT_K,
+            cct_night_k: DEFAULT_CCT_NIGHT_K,
+            cct_night_from: TimeOfDay::MIDNIGHT,
+            cct_night_until: TimeOfDay::from_hour(6),
+            local_only_analytics: false,
+            rgbw: false,
+            led_color: DEFAULT_LED_COLOR,
+            led_strip_pixels: 0,
+            led_strip_rgbw: false,
+            strip_effect: StripEffect::Uniform,
+            chase_reverse: false,
+            night_light_pct: DEFAULT_NIGHT_LIGHT_PCT,
+            night_light_from: TimeOfDay::MIDNIGHT,
+            night_light_until: TimeOfDay::MIDNIGHT,
+            ambient_brightness_pct: 100,
+            pwm_freq_hz: DEFAULT_PWM_FREQ_HZ,
+            pwm_resolution_bits: DEFAULT_PWM_RESOLUTION_BITS,
+            invert_pwm: true,
+            min_duty_pct: 0.0,
+            min_duty_policy: MinDutyPolicy::Clamp,
+            max_on_time_min: 0,
+            presence_stage_pct: 100,
+            full_on_after_ms: 3000,
+            ramp_shape: RampShape::Linear,
+            led_self_test: true,
+            standby_glow_pct: 0.0,
+            temperature_sensor: false,
+            derate_from_c: 60.0,
+            derate_to_c: 80.0,
+            derate_min_pct: 30,
+            led_dither: false,
+            led_full_watts: 0.0,
+            fade_takeover_ms: 0,
+            mqtt_url: String::new(),
+            mqtt_topic: DEFAULT_MQTT_TOPIC.to_string(),
+            mqtt_heartbeat_s: DEFAULT_MQTT_HEARTBEAT_S,
+            ha_discovery: true,
+            api_token: String::new(),
+            hostname: DEFAULT_HOSTNAME.to_string(),
+            timezone: DEFAULT_TIMEZONE.to_string(),
+            ble_pin: 0,
+            syslog_target: String::new(),
+            webhook_urls: String::new(),
+            influx_url: String::new(),
+            influx_token: String::new(),
+            influx_interval_s: DEFAULT_INFLUX_INTERVAL_S,
+            group_role: GroupRole::Standalone,
+            group_topic: DEFAULT_GROUP_TOPIC.to_string(),
+            profile: String::new(),
+            persist_always_on: false,
+            camera_pre_delay_ms: DEFAULT_CAMERA_PRE_DELAY_MS,
+            camera_pulse_ms: DEFAULT_CAMERA_PULSE_MS,
+            camera_hold_s: DEFAULT_CAMERA_HOLD_S,
+            thread_dataset: String::new(),
+            hue_user: String::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn get(&self, param: Param) -> String {
+        match param {
+            Param::LuxThreshold => self.lux_threshold.to_string(),
+            Param::LuxFilter => self.lux_filter.to_string(),
+            Param::LuxWindow => self.lux_window.to_string(),
+            Param::RetriggerGuardMs => self.retrigger_guard_ms.to_string(),
+            Param::RetriggerConfirmMs => self.retrigger_confirm_ms.to_string(),
+            Param::SamplingGapIntervalS => self.sampling_gap_interval_s.to_string(),
+            Param::SamplingGapStage => self.sampling_gap_stage.to_string(),
+            Param::LedLuxContribution => self.led_lux_contribution.to_string(),
+            Param::FadeUpS => self.fade_up_s.to_string(),
+            Param::FadeDownS => self.fade_down_s.to_string(),
+            Param::ZoneTrailS => self.zone_trail_s.to_string(),
+            Param::LuxCalibration => self.lux_calibration.to_string(),
+            Param::DarkLatchMin => self.dark_latch_min.to_string(),
+            Param::DarkReleaseLux => self.dark_release_lux.to_string(),
+            Param::DarkReleaseMin => self.dark_release_min.to_string(),
+            Param::DiagOptIn => self.diag_opt_in.to_string(),
+            Param::DiagUrl => self.diag_url.clone(),
+            Param::DaySleepAfterMin => self.day_sleep_after_min.to_string(),
+            Param::DaySleepLux => self.day_sleep_lux.to_string(),
+            Param::DaySleepWakeMin => self.day_sleep_wake_min.to_string(),
+            Param::VemlGain => self.veml_gain.to_string(),
+            Param::VemlItMs => self.veml_it_ms.to_string(),
+            Param::VemlPsm => self.veml_psm.to_string(),
+            Param::SaturationPolicy => self.saturation_policy.to_string(),
+            Param::LuxStatsPersist => self.lux_stats_persist.to_string(),
+            Param::LuxHalfLifeMs => self.lux_half_life_ms.to_string(),
+            Param::SecondLightSensor => self.second_light_sensor.to_string(),
+            Param::LuxCombine => self.lux_combine.to_string(),
+            Param::FallbackDarkFrom => self.fallback_dark_from.to_string(),
+            Param::FallbackDarkUntil => self.fallback_dark_until.to_string(),
+            Param::LatencyBudgetMs => self.latency_budget_ms.to_string(),
+            Param::LedCrosstalk => self.led_crosstalk.map_or("none".to_string(), |c| c.to_string()),
+            Param::CrosstalkCompensation => self.crosstalk_compensation.to_string(),
+            Param::MaxBrightnessPct => self.max_brightness_pct.to_string(),
+            Param::AdaptiveLuxFactor => self.adaptive_lux_factor.to_string(),
+            Param::DimmingCurve => self.dimming_curve.to_string(),
+            Param::LedHwFade => self.led_hw_fade.to_string(),
+            Param::SecondLed => self.second_led.to_string(),
+            Param::SecondLedDelayMs => self.second_led_delay_ms.to_string(),
+            Param::SecondLedPct => self.second_led_pct.to_string(),
+            Param::TunableWhite => self.tunable_white.to_string(),
+            Param::CctWarmK => self.cct_warm_k.to_string(),
+            Param::CctColdK => self.cct_cold_k.to_string(),
+            Param::CctK => self.cct_k.to_string(),
+            Param::CctNightK => self.cct_night_k.to_string(),
+            Param::CctNightFrom => self.cct_night_from.to_string(),
+            Param::CctNightUntil => self.cct_night_until.to_string(),
+            Param::LocalOnlyAnalytics => self.local_only_analytics.to_string(),
+            Param::Rgbw => self.rgbw.to_string(),
+            Param::LedColor => self.led_color.to_string(),
+            Param::LedStripPixels => self.led_strip_pixels.to_string(),
+            Param::LedStripRgbw => self.led_strip_rgbw.to_string(),
+            Param::StripEffect => self.strip_effect.to_string(),
+            Param::ChaseReverse => self.chase_reverse.to_string(),
+            Param::NightLightPct => self.night_light_pct.to_string(),
+            Param::NightLightFrom => self.night_light_from.to_string(),
+            Param::NightLightUntil => self.night_light_until.to_string(),
+            Param::AmbientBrightnessPct => self.ambient_brightness_pct.to_string(),
+            Param::PwmFreqHz => self.pwm_freq_hz.to_string(),
+            Param::PwmResolutionBits => self.pwm_resolution_bits.to_string(),
+            Param::InvertPwm => self.invert_pwm.to_string(),
+            Param::MinDutyPct => self.min_duty_pct.to_string(),
+            Param::MinDutyPolicy => self.min_duty_policy.to_string(),
+            Param::MaxOnTimeMin => self.max_on_time_min.to_string(),
+            Param::PresenceStagePct => self.presence_stage_pct.to_string(),
+            Param::FullOnAfterMs => self.full_on_after_ms.to_string(),
+            Param::RampShape => self.ramp_shape.to_string(),
+            Param::LedSelfTest => self.led_self_test.to_string(),
+            Param::StandbyGlowPct => self.standby_glow_pct.to_string(),
+            Param::TemperatureSensor => self.temperature_sensor.to_string(),
+            Param::DerateFromC => self.derate_from_c.to_string(),
+            Param::DerateToC => self.derate_to_c.to_string(),
+            Param::DerateMinPct => self.derate_min_pct.to_string(),
+            Param::LedDither => self.led_dither.to_string(),
+            Param::LedFullWatts => self.led_full_watts.to_string(),
+            Param::FadeTakeoverMs => self.fade_takeover_ms.to_string(),
+            Param::MqttUrl => self.mqtt_url.clone(),
+            Param::MqttTopic => self.mqtt_topic.clone(),
+            Param::MqttHeartbeatS => self.mqtt_heartbeat_s.to_string(),
+            Param::HaDiscovery => self.ha_discovery.to_string(),
+            Param::ApiToken => self.api_token.clone(),
+            Param::Hostname => self.hostname.clone(),
+            Param::Timezone => self.timezone.clone(),
+            Param::BlePin => self.ble_pin.to_string(),
+            Param::SyslogTarget => self.syslog_target.clone(),
+            Param::WebhookUrls => self.webhook_urls.clone(),
+            Param::InfluxUrl => self.influx_url.clone(),
+            Param::InfluxToken => self.influx_token.clone(),
+            Param::InfluxIntervalS => self.influx_interval_s.to_string(),
+            Param::GroupRole => self.group_role.to_string(),
+            Param::GroupTopic => self.group_topic.clone(),
+            Param::Profile => self.profile.clone(),
+            Param::PersistAlwaysOn => self.persist_always_on.to_string(),
+            Param::CameraPreDelayMs => self.camera_pre_delay_ms.to_string(),
+            Param::CameraPulseMs => self.camera_pulse_ms.to_string(),
+            Param::CameraHoldS => self.camera_hold_s.to_string(),
+            Param::ThreadDataset => self.thread_dataset.clone(),
+            Param::HueUser => self.hue_user.clone(),
+        }
+    }
+
+    /// Like [Config::get] - but a set credential as [REDACTED], for output which may reach the network
+    /// (the log is mirrored to the TCP console and the syslog server)
+    pub fn get_redacted(&self, param: Param) -> String {
+        let value = self.get(param);
+        if param.is_credential() && !value.is_empty() {
+            REDACTED.to_string()
+        } else {
+            value
+        }
+    }
+
+    /// Parses and validates `value` and sets it on success
+    pub fn set(&mut self, param: Param, value: &str) -> Result<()> {
+        match param {
+            Param::LuxThreshold => self.lux_threshold = parse_non_negative(param, value)?,
+            Param::LuxFilter => self.lux_filter = value.parse()?,
+            Param::LuxWindow => {
+                let window: u32 = value.parse()?;
+                if window == 0 {
+                    bail!("{} must be at least 1", param);
+                }
+                self.lux_window = window;
+            }
+            Param::RetriggerGuardMs => self.retrigger_guard_ms = value.parse()?,
+            Param::RetriggerConfirmMs => self.retrigger_confirm_ms = value.parse()?,
+            Param::SamplingGapIntervalS => self.sampling_gap_interval_s = value.parse()?,
+            Param::SamplingGapStage => {
+                let stage: u32 = value.parse()?;
+                if stage > LED_MAX_POWER_STAGE {
+                    bail!("{} must not exceed {}", param, LED_MAX_POWER_STAGE);
+                }
+                self.sampling_gap_stage = stage;
+            }
+            Param::LedLuxContribution => self.led_lux_contribution = parse_non_negative(param, value)?,
+            Param::FadeUpS | Param::FadeDownS => {
+                let seconds: f32 = value.parse()?;
+                if !(0.0..=600.0).contains(&seconds) {
+                    bail!("{} must be in the range 0..=600", param);
+                }
+                match param {
+                    Param::FadeUpS => self.fade_up_s = seconds,
+                    _ => self.fade_down_s = seconds
+                }
+            }
+            Param::ZoneTrailS => self.zone_trail_s = value.parse()?,
+            Param::LuxCalibration => {
+                let factor: f32 = value.parse()?;
+                if !(factor > 0.0) {
+                    bail!("{} must be greater than 0", param);
+                }
+                self.lux_calibration = factor;
+            }
+            Param::DarkLatchMin => self.dark_latch_min = value.parse()?,
+            Param::DarkReleaseLux => self.dark_release_lux = parse_non_negative(param, value)?,
+            Param::DarkReleaseMin => self.dark_release_min = value.parse()?,
+            Param::DiagOptIn => self.diag_opt_in = value.parse()?,
+            Param::DiagUrl => self.diag_url = value.to_string(),
+            Param::DaySleepAfterMin => self.day_sleep_after_min = value.parse()?,
+            Param::DaySleepLux => self.day_sleep_lux = parse_non_negative(param, value)?,
+            Param::DaySleepWakeMin => {
+                let minutes: u32 = value.parse()?;
+                if minutes == 0 {
+                    bail!("{} must be at least 1", param);
+                }
+                self.day_sleep_wake_min = minutes;
+            }
+            Param::VemlGain => self.veml_gain = value.parse()?,
+            Param::VemlItMs => {
+                let it_ms: u32 = value.parse()?;
+                if !VEML_INTEGRATION_TIMES_MS.contains(&it_ms) {
+                    bail!("{} must be one of {:?}", param, VEML_INTEGRATION_TIMES_MS);
+                }
+                self.veml_it_ms = it_ms;
+            }
+            Param::VemlPsm => {
+                let psm: u32 = value.parse()?;
+                if psm > 4 {
+                    bail!("{} must be in range 0..=4", param);
+                }
+                self.veml_psm = psm;
+            }
+            Param::SaturationPolicy => self.saturation_policy = value.parse()?,
+            Param::LuxStatsPersist => self.lux_stats_persist = value.parse()?,
+            Param::LuxHalfLifeMs => self.lux_half_life_ms = value.parse()?,
+            Param::SecondLightSensor => self.second_light_sensor = value.parse()?,
+            Param::LuxCombine => self.lux_combine = value.parse()?,
+            Param::FallbackDarkFrom => self.fallback_dark_from = value.parse()?,
+            Param::FallbackDarkUntil => self.fallback_dark_until = value.parse()?,
+            Param::LatencyBudgetMs => self.latency_budget_ms = value.parse()?,
+            Param::LedCrosstalk => self.led_crosstalk = match value {
+                "none" => None,
+                _ => Some(value.parse()?)
+            },
+            Param::CrosstalkCompensation => self.crosstalk_compensation = value.parse()?,
+            Param::MaxBrightnessPct => {
+                let percent: u32 = value.parse()?;
+                if !(1..=100).contains(&percent) {
+                    bail!("{} must be in the range 1..=100", param);
+                }
+                self.max_brightness_pct = percent;
+            }
+            Param::AdaptiveLuxFactor => self.adaptive_lux_factor = parse_non_negative(param, value)?,
+            Param::DimmingCurve => self.dimming_curve = value.parse()?,
+            Param::LedHwFade => self.led_hw_fade = value.parse()?,
+            Param::SecondLed => self.second_led = value.parse()?,
+            Param::SecondLedDelayMs => self.second_led_delay_ms = value.parse()?,
+            Param::SecondLedPct => {
+                let percent: u32 = value.parse()?;
+                if percent > 100 {
+                    bail!("{} must not exceed 100", param);
+                }
+                self.second_led_pct = percent;
+            }
+            Param::TunableWhite => self.tunable_white = value.parse()?,
+            Param::CctWarmK => self.cct_warm_k = parse_cct(param, value)?,
+            Param::CctColdK => self.cct_cold_k = parse_cct(param, value)?,
+            Param::CctK => self.cct_k = parse_cct(param, value)?,
+            Param::CctNightK => self.cct_night_k = parse_cct(param, value)?,
+            Param::CctNightFrom => self.cct_night_from = value.parse()?,
+            Param::CctNightUntil => self.cct_night_until = value.parse()?,
+            Param::LocalOnlyAnalytics => self.local_only_analytics = value.parse()?,
+            Param::Rgbw => self.rgbw = value.parse()?,
+            Param::LedColor => self.led_color = value.parse()?,
+            Param::LedStripPixels => {
+                let pixels: u32 = value.parse()?;
+                if pixels > MAX_LED_STRIP_PIXELS {
+                    bail!("{} must not exceed {}", param, MAX_LED_STRIP_PIXELS);
+                }
+                self.led_strip_pixels = pixels;
+            }
+            Param::LedStripRgbw => self.led_strip_rgbw = value.parse()?,
+            Param::StripEffect => self.strip_effect = value.parse()?,
+            Param::ChaseReverse => self.chase_reverse = value.parse()?,
+            Param::NightLightPct => {
+                let percent: u32 = value.parse()?;
+                if !(1..=100).contains(&percent) {
+                    bail!("{} must be in the range 1..=100", param);
+                }
+                self.night_light_pct = percent;
+            }
+            Param::NightLightFrom => self.night_light_from = value.parse()?,
+            Param::NightLightUntil => self.night_light_until = value.parse()?,
+            Param::AmbientBrightnessPct => {
+                let percent: u32 = value.parse()?;
+                if !(1..=100).contains(&percent) {
+                    bail!("{} must be in the range 1..=100", param);
+                }
+                self.ambient_brightness_pct = percent;
+            }
+            Param::PwmFreqHz => {
+                let freq_hz = value.parse()?;
+                check_pwm(freq_hz, self.pwm_resolution_bits)?;
+                self.pwm_freq_hz = freq_hz;
+            }
+            Param::PwmResolutionBits => {
+                let resolution_bits = value.parse()?;
+                check_pwm(self.pwm_freq_hz, resolution_bits)?;
+                self.pwm_resolution_bits = resolution_bits;
+            }
+            Param::InvertPwm => self.invert_pwm = value.parse()?,
+            Param::MinDutyPct => {
+                let percent: f32 = value.parse()?;
+                if !(0.0..=MAX_MIN_DUTY_PCT).contains(&percent) {
+                    bail!("{} must be in the range 0..={}", param, MAX_MIN_DUTY_PCT);
+                }
+                self.min_duty_pct = percent;
+            }
+            Param::MinDutyPolicy => self.min_duty_policy = value.parse()?,
+            Param::MaxOnTimeMin => self.max_on_time_min = value.parse()?,
+            Param::PresenceStagePct => {
+                let percent: u32 = value.parse()?;
+                if !(1..=100).contains(&percent) {
+                    bail!("{} must be in the range 1..=100", param);
+                }
+                self.presence_stage_pct = percent;
+            }
+            Param::FullOnAfterMs => self.full_on_after_ms = value.parse()?,
+            Param::RampShape => self.ramp_shape = value.parse()?,
+            Param::LedSelfTest => self.led_self_test = value.parse()?,
+            Param::StandbyGlowPct => {
+                let percent: f32 = value.parse()?;
+                if !(0.0..=MAX_STANDBY_GLOW_PCT).contains(&percent) {
+                    bail!("{} must be in the range 0..={}", param, MAX_STANDBY_GLOW_PCT);
+                }
+                self.standby_glow_pct = percent;
+            }
+            Param::TemperatureSensor => self.temperature_sensor = value.parse()?,
+            Param::DerateFromC => {
+                let celsius = value.parse()?;
+                check_derating(celsius, self.derate_to_c)?;
+                self.derate_from_c = celsius;
+            }
+            Param::DerateToC => {
+                let celsius = value.parse()?;
+                check_derating(self.derate_from_c, celsius)?;
+                self.derate_to_c = celsius;
+            }
+            Param::DerateMinPct => {
+                let percent: u32 = value.parse()?;
+                if !(1..=100).contains(&percent) {
+                    bail!("{} must be in the range 1..=100", param);
+                }
+                self.derate_min_pct = percent;
+            }
+            Param::LedDither => self.led_dither = value.parse()?,
+            Param::LedFullWatts => self.led_full_watts = parse_non_negative(param, value)?,
+            Param::FadeTakeoverMs => self.fade_takeover_ms = value.parse()?,
+            Param::MqttUrl => self.mqtt_url = value.to_string(),
+            Param::MqttTopic => {
+                if value.is_empty() || value.contains(['#', '+']) {
+                    bail!("{} must be a non-empty topic without wildcards", param);
+                }
+                self.mqtt_topic = value.to_string()
+            }
+            Param::MqttHeartbeatS => self.mqtt_heartbeat_s = value.parse()?,
+            Param::HaDiscovery => self.ha_discovery = value.parse()?,
+            Param::ApiToken => self.api_token = value.to_string(),
+            Param::Hostname => {
+                if value.is_empty() || value.len() > 63 || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                    bail!("{} must have 1 to 63 letters, digits or '-'", param);
+                }
+                self.hostname = value.to_string()
+            }
+            Param::Timezone => {
+                if value.is_empty() {
+                    bail!("{} must not be empty", param);
+                }
+                self.timezone = value.to_string()
+            }
+            Param::BlePin => {
+                let pin: u32 = value.parse()?;
+                if pin > 999_999 {
+                    bail!("{} must be in the range 0..=999999", param);
+                }
+                self.ble_pin = pin;
+            }
+            Param::SyslogTarget => self.syslog_target = value.to_string(),
+            Param::WebhookUrls => {
+                if !value.is_empty() && value.split(',').any(|url| !url.starts_with("http://") && !url.starts_with("https://")) {
+                    bail!("{} must be a comma separated list of http(s) URLs", param);
+                }
+                self.webhook_urls = value.to_string()
+            }
+            Param::InfluxUrl => self.influx_url = value.to_string(),
+            Param::InfluxToken => self.influx_token = value.to_string(),
+            Param::InfluxIntervalS => {
+                let interval: u32 = value.parse()?;
+                if interval < 10 {
+                    bail!("{} must be at least 10", param);
+                }
+                self.influx_interval_s = interval;
+            }
+            Param::GroupRole => self.group_role = value.parse()?,
+            Param::GroupTopic => {
+                if value.is_empty() || value.contains(['#', '+']) {
+                    bail!("{} must be a non-empty topic without wildcards", param);
+                }
+                self.group_topic = value.to_string()
+            }
+            Param::Profile => {
+                if !value.is_empty() {
+                    profile::check_name(value)?;
+                }
+                self.profile = value.to_string()
+            }
+            Param::PersistAlwaysOn => self.persist_always_on = value.parse()?,
+            Param::CameraPreDelayMs => {
+                let ms: u32 = value.parse()?;
+                if ms > MAX_CAMERA_PRE_DELAY_MS {
+                    bail!("{} must be in the range 0..={}", param, MAX_CAMERA_PRE_DELAY_MS);
+                }
+                self.camera_pre_delay_ms = ms;
+            }
+            Param::CameraPulseMs => {
+                let ms: u32 = value.parse()?;
+                if !(1..=MAX_CAMERA_PULSE_MS).contains(&ms) {
+                    bail!("{} must be in the range 1..={}", param, MAX_CAMERA_PULSE_MS);
+                }
+                self.camera_pulse_ms = ms;
+            }
+            Param::CameraHoldS => {
+                let seconds: u32 = value.parse()?;
+                if !(1..=MAX_CAMERA_HOLD_S).contains(&seconds) {
+                    bail!("{} must be in the range 1..={}", param, MAX_CAMERA_HOLD_S);
+                }
+                self.camera_hold_s = seconds;
+            }
+            Param::ThreadDataset => {
+                if value.len() > MAX_THREAD_DATASET_LEN || value.len() % 2 != 0 || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+                    bail!("{} must be a hex string of at most {} characters", param, MAX_THREAD_DATASET_LEN);
+                }
+                self.thread_dataset = value.to_string();
+            }
+            Param::HueUser => {
+                if value.len() > MAX_HUE_USER_LEN || !value.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    bail!("{} must consist of at most {} letters and digits", param, MAX_HUE_USER_LEN);
+                }
+                self.hue_user = value.to_string();
+            }
+        }
+        self.touch();
+        Ok(())
+    }
+
+    /// Marks the configuration as changed - after a change of values without [Config::set]
+    pub fn touch(&mut self) {
+        self.revision = NEXT_REVISION.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+}
+
+/// Finite and not negative - `f32::from_str` also accepts "NaN" and "inf"
+fn parse_non_negative(param: Param, value: &str) -> Result<f32> {
+    let v: f32 = value.parse()?;
+    if !v.is_finite() || v < 0.0 {
+        bail!("{} must be a finite number, not negative", param);
+    }
+    Ok(v)
+}
+
+/// Color temperature [K] within the range of common white LEDs
+fn parse_cct(param: Param, value: &str) -> Result<u32> {
+    let kelvin: u32 = value.parse()?;
+    if !(1000..=10000).contains(&kelvin) {
+        bail!("{} must be in the range 1000..=10000", param);
+    }
+    Ok(kelvin)
+}
+
+/// The PWM counter runs at frequency × 2^resolution, which the LEDC clock has to provide
+fn check_pwm(freq_hz: u32, resolution_bits: u32) -> Result<()> {
+    if !(MIN_PWM_FREQ_HZ..=MAX_PWM_FREQ_HZ).contains(&freq_hz) {
+        bail!("{} must be in the range {}..={}", Param::PwmFreqHz, MIN_PWM_FREQ_HZ, MAX_PWM_FREQ_HZ);
+    }
+    if !(MIN_PWM_RESOLUTION_BITS..=MAX_PWM_RESOLUTION_BITS).contains(&resolution_bits) {
+        bail!("{} must be in the range {}..={}", Param::PwmResolutionBits, MIN_PWM_RESOLUTION_BITS, MAX_PWM_RESOLUTION_BITS);
+    }
+    if freq_hz as u64 * (1 << resolution_bits) > LED_PWM_CLOCK_HZ as u64 {
+        bail!("{} Hz at {} bit exceed the PWM clock of {} Hz - lower {} or {}",
+            freq_hz, resolution_bits, LED_PWM_CLOCK_HZ, Param::PwmFreqHz, Param::PwmResolutionBits);
+    }
+    Ok(())
+}
+
+/// The derating interpolates between the two temperatures, so they have to span a range
+fn check_derating(from_c: f32, to_c: f32) -> Result<()> {
+    if !from_c.is_finite() || !to_c.is_finite() || from_c >= to_c {
+        bail!("{} and {} must be finite with {} < {}", Param::DerateFromC, Param::DerateToC, Param::DerateFromC, Param::DerateToC);
+    }
+    Ok(())
+}
+
+/// All parameters like `get` - the credentials redacted, as the configuration is logged
+impl Debug for Config {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(Param::ALL.iter().map(|param| (param.name(), self.get_redacted(*param))))
+            .finish()
+    }
+}
+
+/// The values of all parameters - regardless of the revision
+impl PartialEq for Config {
+    fn eq(&self, other: &Self) -> bool {
+        Param::ALL.iter().all(|param| self.get(*param) == other.get(*param))
+    }
+}
+
+/// Tells a holder of a configuration copy about changes - every part of the firmware, which follows the
+/// configuration, keeps its own watch. Compares the revision only, so it is cheap to ask every loop iteration.
+#[derive(Debug, Default)]
+pub struct ConfigWatch {
+    seen: Option<u32>,
+}
+
+impl ConfigWatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the configuration changed since the last call (true on the first call)
+    pub fn changed(&mut self, config: &Config) -> bool {
+        if self.seen == Some(config.revision) {
+            return false;
+        }
+        self.seen = Some(config.revision);
+        true
+    }
+}
+
+pub struct ConfigStore {
+    nvs: EspNvs<NvsDefault>,
+}
+
+impl ConfigStore {
+    pub fn new(partition: EspDefaultNvsPartition) -> Result<Self> {
+        Ok(Self {
+            nvs: EspNvs::new(partition, NVS_NAMESPACE, true)?
+        })
+    }
+
+    fn get_string(&self, key: &str) -> Result<Option<String>> {
+        let mut buf = [0_u8; MAX_STRING_LEN];
+        Ok(self.nvs.get_str(key, &mut buf)?.map(|s| s.to_string()))
+    }
+
+    /// Loads the stored configuration; values not (yet) stored are taken from the defaults.
+    /// Values stored by a firmware with a newer layout are not used - they might be misread.
+    pub fn load(&self) -> Result<Config> {
+        let mut config = Config::default();
+        // stored without a version: the first layout
+        let version = self.nvs.get_u32(KEY_CONFIG_VERSION)?.unwrap_or(1);
+        if version > CONFIG_VERSION {
+            log::warn!("configuration stored with layout version {} (supported: {}) - using the defaults", version, CONFIG_VERSION);
+            return Ok(config);
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_LUX_THRESHOLD)? {
+            config.lux_threshold = f32::from_bits(v);
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_LUX_FILTER)?.and_then(LuxFilterKind::from_u8) {
+            config.lux_filter = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_LUX_WINDOW)? {
+            config.lux_window = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_RETRIGGER_GUARD_MS)? {
+            config.retrigger_guard_ms = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_RETRIGGER_CONFIRM_MS)? {
+            config.retrigger_confirm_ms = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_SAMPLING_GAP_INTERVAL_S)? {
+            config.sampling_gap_interval_s = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_SAMPLING_GAP_STAGE)? {
+            config.sampling_gap_stage = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_LED_LUX_CONTRIBUTION)? {
+            config.led_lux_contribution = f32::from_bits(v);
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_FADE_UP_S)? {
+            config.fade_up_s = f32::from_bits(v);
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_FADE_DOWN_S)? {
+            config.fade_down_s = f32::from_bits(v);
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_ZONE_TRAIL_S)? {
+            config.zone_trail_s = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_LUX_CALIBRATION)? {
+            config.lux_calibration = f32::from_bits(v);
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_DARK_LATCH_MIN)? {
+            config.dark_latch_min = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_DARK_RELEASE_LUX)? {
+            config.dark_release_lux = f32::from_bits(v);
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_DARK_RELEASE_MIN)? {
+            config.dark_release_min = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_DIAG_OPT_IN)? {
+            config.diag_opt_in = v != 0;
+        }
+        if let Some(v) = self.get_string(KEY_DIAG_URL)? {
+            config.diag_url = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_DAY_SLEEP_AFTER_MIN)? {
+            config.day_sleep_after_min = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_DAY_SLEEP_LUX)? {
+            config.day_sleep_lux = f32::from_bits(v);
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_DAY_SLEEP_WAKE_MIN)? {
+            config.day_sleep_wake_min = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_VEML_GAIN)?.and_then(VemlGain::from_u8) {
+            config.veml_gain = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_VEML_IT_MS)? {
+            config.veml_it_ms = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_VEML_PSM)? {
+            config.veml_psm = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_SATURATION_POLICY)?.and_then(SaturationPolicy::from_u8) {
+            config.saturation_policy = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_LUX_STATS_PERSIST)? {
+            config.lux_stats_persist = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_LUX_HALF_LIFE_MS)? {
+            config.lux_half_life_ms = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_SECOND_LIGHT_SENSOR)? {
+            config.second_light_sensor = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_LUX_COMBINE)?.and_then(LuxCombine::from_u8) {
+            config.lux_combine = v;
+        }
+        if let Some(v) = self.nvs.get_u16(KEY_FALLBACK_DARK_FROM)?.and_then(TimeOfDay::from_minutes) {
+            config.fallback_dark_from = v;
+        }
+        if let Some(v) = self.nvs.get_u16(KEY_FALLBACK_DARK_UNTIL)?.and_then(TimeOfDay::from_minutes) {
+            config.fallback_dark_until = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_LATENCY_BUDGET_MS)? {
+            config.latency_budget_ms = v;
+        }
+        let mut buf = [0_u8; 64];
+        if let Some(v) = self.nvs.get_raw(KEY_LED_CROSSTALK, &mut buf)? {
+            config.led_crosstalk = CrosstalkCurve::from_bytes(v);
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_CROSSTALK_COMPENSATION)? {
+            config.crosstalk_compensation = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_MAX_BRIGHTNESS_PCT)? {
+            config.max_brightness_pct = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_ADAPTIVE_LUX_FACTOR)? {
+            config.adaptive_lux_factor = f32::from_bits(v);
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_DIMMING_CURVE)?.and_then(DimmingCurve::from_u8) {
+            config.dimming_curve = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_LED_HW_FADE)? {
+            config.led_hw_fade = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_SECOND_LED)? {
+            config.second_led = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_SECOND_LED_DELAY_MS)? {
+            config.second_led_delay_ms = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_SECOND_LED_PCT)? {
+            config.second_led_pct = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_TUNABLE_WHITE)? {
+            config.tunable_white = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_CCT_WARM_K)? {
+            config.cct_warm_k = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_CCT_COLD_K)? {
+            config.cct_cold_k = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_CCT_K)? {
+            config.cct_k = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_CCT_NIGHT_K)? {
+            config.cct_night_k = v;
+        }
+        if let Some(v) = self.nvs.get_u16(KEY_CCT_NIGHT_FROM)?.and_then(TimeOfDay::from_minutes) {
+            config.cct_night_from = v;
+        }
+        if let Some(v) = self.nvs.get_u16(KEY_CCT_NIGHT_UNTIL)?.and_then(TimeOfDay::from_minutes) {
+            config.cct_night_until = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_LOCAL_ONLY_ANALYTICS)? {
+            config.local_only_analytics = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_RGBW)? {
+            config.rgbw = v != 0;
+        }
+        if let Some(v) = self.get_string(KEY_LED_COLOR)?.and_then(|s| s.parse().ok()) {
+            config.led_color = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_LED_STRIP_PIXELS)? {
+            config.led_strip_pixels = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_LED_STRIP_RGBW)? {
+            config.led_strip_rgbw = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_STRIP_EFFECT)?.and_then(StripEffect::from_u8) {
+            config.strip_effect = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_CHASE_REVERSE)? {
+            config.chase_reverse = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_NIGHT_LIGHT_PCT)? {
+            config.night_light_pct = v;
+        }
+        if let Some(v) = self.nvs.get_u16(KEY_NIGHT_LIGHT_FROM)?.and_then(TimeOfDay::from_minutes) {
+            config.night_light_from = v;
+        }
+        if let Some(v) = self.nvs.get_u16(KEY_NIGHT_LIGHT_UNTIL)?.and_then(TimeOfDay::from_minutes) {
+            config.night_light_until = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_AMBIENT_BRIGHTNESS_PCT)? {
+            config.ambient_brightness_pct = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_PWM_FREQ_HZ)? {
+            config.pwm_freq_hz = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_PWM_RESOLUTION_BITS)? {
+            config.pwm_resolution_bits = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_INVERT_PWM)? {
+            config.invert_pwm = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_MIN_DUTY_PCT)? {
+            config.min_duty_pct = f32::from_bits(v);
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_MIN_DUTY_POLICY)?.and_then(MinDutyPolicy::from_u8) {
+            config.min_duty_policy = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_MAX_ON_TIME_MIN)? {
+            config.max_on_time_min = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_PRESENCE_STAGE_PCT)? {
+            config.presence_stage_pct = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_FULL_ON_AFTER_MS)? {
+            config.full_on_after_ms = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_RAMP_SHAPE)?.and_then(RampShape::from_u8) {
+            config.ramp_shape = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_LED_SELF_TEST)? {
+            config.led_self_test = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_STANDBY_GLOW_PCT)? {
+            config.standby_glow_pct = f32::from_bits(v);
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_TEMPERATURE_SENSOR)? {
+            config.temperature_sensor = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_DERATE_FROM_C)? {
+            config.derate_from_c = f32::from_bits(v);
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_DERATE_TO_C)? {
+            config.derate_to_c = f32::from_bits(v);
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_DERATE_MIN_PCT)? {
+            config.derate_min_pct = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_LED_DITHER)? {
+            config.led_dither = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_LED_FULL_WATTS)? {
+            config.led_full_watts = f32::from_bits(v);
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_FADE_TAKEOVER_MS)? {
+            config.fade_takeover_ms = v;
+        }
+        if let Some(v) = self.get_string(KEY_MQTT_URL)? {
+            config.mqtt_url = v;
+        }
+        if let Some(v) = self.get_string(KEY_MQTT_TOPIC)? {
+            config.mqtt_topic = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_MQTT_HEARTBEAT_S)? {
+            config.mqtt_heartbeat_s = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_HA_DISCOVERY)? {
+            config.ha_discovery = v != 0;
+        }
+        if let Some(v) = self.get_string(KEY_API_TOKEN)? {
+            config.api_token = v;
+        }
+        if let Some(v) = self.get_string(KEY_HOSTNAME)? {
+            config.hostname = v;
+        }
+        if let Some(v) = self.get_string(KEY_TIMEZONE)? {
+            config.timezone = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_BLE_PIN)? {
+            config.ble_pin = v;
+        }
+        if let Some(v) = self.get_string(KEY_SYSLOG_TARGET)? {
+            config.syslog_target = v;
+        }
+        if let Some(v) = self.get_string(KEY_WEBHOOK_URLS)? {
+            config.webhook_urls = v;
+        }
+        if let Some(v) = self.get_string(KEY_INFLUX_URL)? {
+            config.influx_url = v;
+        }
+        if let Some(v) = self.get_string(KEY_INFLUX_TOKEN)? {
+            config.influx_token = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_INFLUX_INTERVAL_S)? {
+            config.influx_interval_s = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_GROUP_ROLE)?.and_then(GroupRole::from_u8) {
+            config.group_role = v;
+        }
+        if let Some(v) = self.get_string(KEY_GROUP_TOPIC)? {
+            config.group_topic = v;
+        }
+        if let Some(v) = self.get_string(KEY_PROFILE)? {
+            config.profile = v;
+        }
+        if let Some(v) = self.nvs.get_u8(KEY_PERSIST_ALWAYS_ON)? {
+            config.persist_always_on = v != 0;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_CAMERA_PRE_DELAY_MS)? {
+            config.camera_pre_delay_ms = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_CAMERA_PULSE_MS)? {
+            config.camera_pulse_ms = v;
+        }
+        if let Some(v) = self.nvs.get_u32(KEY_CAMERA_HOLD_S)? {
+            config.camera_hold_s = v;
+        }
+        if let Some(v) = self.get_string(KEY_THREAD_DATASET)? {
+            config.thread_dataset = v;
+        }
+        if let Some(v) = self.get_string(KEY_HUE_USER)? {
+            config.hue_user = v;
+        }
+        Ok(config)
+    }
+
+    pub fn save(&mut self, config: &Config) -> Result<()> {
+        self.nvs.set_u32(KEY_CONFIG_VERSION, CONFIG_VERSION)?;
+        // f32 values are stored via their bit representation
+        self.nvs.set_u32(KEY_LUX_THRESHOLD, config.lux_threshold.to_bits())?;
+        self.nvs.set_u8(KEY_LUX_FILTER, config.lux_filter.to_u8())?;
+        self.nvs.set_u32(KEY_LUX_WINDOW, config.lux_window)?;
+        self.nvs.set_u32(KEY_RETRIGGER_GUARD_MS, config.retrigger_guard_ms)?;
+        self.nvs.set_u32(KEY_RETRIGGER_CONFIRM_MS, config.retrigger_confirm_ms)?;
+        self.nvs.set_u32(KEY_SAMPLING_GAP_INTERVAL_S, config.sampling_gap_interval_s)?;
+        self.nvs.set_u32(KEY_SAMPLING_GAP_STAGE, config.sampling_gap_stage)?;
+        self.nvs.set_u32(KEY_LED_LUX_CONTRIBUTION, config.led_lux_contribution.to_bits())?;
+        self.nvs.set_u32(KEY_FADE_UP_S, config.fade_up_s.to_bits())?;
+        self.nvs.set_u32(KEY_FADE_DOWN_S, config.fade_down_s.to_bits())?;
+        self.nvs.set_u32(KEY_ZONE_TRAIL_S, config.zone_trail_s)?;
+        self.nvs.set_u32(KEY_LUX_CALIBRATION, config.lux_calibration.to_bits())?;
+        self.nvs.set_u32(KEY_DARK_LATCH_MIN, config.dark_latch_min)?;
+        self.nvs.set_u32(KEY_DARK_RELEASE_LUX, config.dark_release_lux.to_bits())?;
+        self.nvs.set_u32(KEY_DARK_RELEASE_MIN, config.dark_release_min)?;
+        self.nvs.set_u8(KEY_DIAG_OPT_IN, config.diag_opt_in as u8)?;
+        self.nvs.set_str(KEY_DIAG_URL, &config.diag_url)?;
+        self.nvs.set_u32(KEY_DAY_SLEEP_AFTER_MIN, config.day_sleep_after_min)?;
+        self.nvs.set_u32(KEY_DAY_SLEEP_LUX, config.day_sleep_lux.to_bits())?;
+        self.nvs.set_u32(KEY_DAY_SLEEP_WAKE_MIN, config.day_sleep_wake_min)?;
+        self.nvs.set_u8(KEY_VEML_GAIN, config.veml_gain.to_u8())?;
+        self.nvs.set_u32(KEY_VEML_IT_MS, config.veml_it_ms)?;
+        self.nvs.set_u32(KEY_VEML_PSM, config.veml_psm)?;
+        self.nvs.set_u8(KEY_SATURATION_POLICY, config.saturation_policy.to_u8())?;
+        self.nvs.set_u8(KEY_LUX_STATS_PERSIST, config.lux_stats_persist as u8)?;
+        self.nvs.set_u32(KEY_LUX_HALF_LIFE_MS, config.lux_half_life_ms)?;
+        self.nvs.set_u8(KEY_SECOND_LIGHT_SENSOR, config.second_light_sensor as u8)?;
+        self.nvs.set_u8(KEY_LUX_COMBINE, config.lux_combine.to_u8())?;
+        self.nvs.set_u16(KEY_FALLBACK_DARK_FROM, config.fallback_dark_from.minutes())?;
+        self.nvs.set_u16(KEY_FALLBACK_DARK_UNTIL, config.fallback_dark_until.minutes())?;
+        self.nvs.set_u32(KEY_LATENCY_BUDGET_MS, config.latency_budget_ms)?;
+        match config.led_crosstalk {
+            Some(curve) => self.nvs.set_raw(KEY_LED_CROSSTALK, &curve.to_bytes())?,
+            None => self.nvs.remove(KEY_LED_CROSSTALK)?
+        };
+        self.nvs.set_u8(KEY_CROSSTALK_COMPENSATION, config.crosstalk_compensation as u8)?;
+        self.nvs.set_u32(KEY_MAX_BRIGHTNESS_PCT, config.max_brightness_pct)?;
+        self.nvs.set_u32(KEY_ADAPTIVE_LUX_FACTOR, config.adaptive_lux_factor.to_bits())?;
+        self.nvs.set_u8(KEY_DIMMING_CURVE, config.dimming_curve.to_u8())?;
+        self.nvs.set_u8(KEY_LED_HW_FADE, config.led_hw_fade as u8)?;
+        self.nvs.set_u8(KEY_SECOND_LED, config.second_led as u8)?;
+        self.nvs.set_u32(KEY_SECOND_LED_DELAY_MS, config.second_led_delay_ms)?;
+        self.nvs.set_u32(KEY_SECOND_LED_PCT, config.second_led_pct)?;
+        self.nvs.set_u8(KEY_TUNABLE_WHITE, config.tunable_white as u8)?;
+        self.nvs.set_u32(KEY_CCT_WARM_K, config.cct_warm_k)?;
+        self.nvs.set_u32(KEY_CCT_COLD_K, config.cct_cold_k)?;
+        self.nvs.set_u32(KEY_CCT_K, config.cct_k)?;
+        self.nvs.set_u32(KEY_CCT_NIGHT_K, config.cct_night_k)?;
+        self.nvs.set_u16(KEY_CCT_NIGHT_FROM, config.cct_night_from.minutes())?;
+        self.nvs.set_u16(KEY_CCT_NIGHT_UNTIL, config.cct_night_until.minutes())?;
+        self.nvs.set_u8(KEY_LOCAL_ONLY_ANALYTICS, config.local_only_analytics as u8)?;
+        self.nvs.set_u8(KEY_RGBW, config.rgbw as u8)?;
+        self.nvs.set_str(KEY_LED_COLOR, &config.led_color.to_string())?;
+        self.nvs.set_u32(KEY_LED_STRIP_PIXELS, config.led_strip_pixels)?;
+        self.nvs.set_u8(KEY_LED_STRIP_RGBW, config.led_strip_rgbw as u8)?;
+        self.nvs.set_u8(KEY_STRIP_EFFECT, config.strip_effect.to_u8())?;
+        self.nvs.set_u8(KEY_CHASE_REVERSE, config.chase_reverse as u8)?;
+        self.nvs.set_u32(KEY_NIGHT_LIGHT_PCT, config.night_light_pct)?;
+        self.nvs.set_u16(KEY_NIGHT_LIGHT_FROM, config.night_light_from.minutes())?;
+        self.nvs.set_u16(KEY_NIGHT_LIGHT_UNTIL, config.night_light_until.minutes())?;
+        self.nvs.set_u32(KEY_AMBIENT_BRIGHTNESS_PCT, config.ambient_brightness_pct)?;
+        self.nvs.set_u32(KEY_PWM_FREQ_HZ, config.pwm_freq_hz)?;
+        self.nvs.set_u32(KEY_PWM_RESOLUTION_BITS, config.pwm_resolution_bits)?;
+        self.nvs.set_u8(KEY_INVERT_PWM, config.invert_pwm as u8)?;
+        self.nvs.set_u32(KEY_MIN_DUTY_PCT, config.min_duty_pct.to_bits())?;
+        self.nvs.set_u8(KEY_MIN_DUTY_POLICY, config.min_duty_policy.to_u8())?;
+        self.nvs.set_u32(KEY_MAX_ON_TIME_MIN, config.max_on_time_min)?;
+        self.nvs.set_u32(KEY_PRESENCE_STAGE_PCT, config.presence_stage_pct)?;
+        self.nvs.set_u32(KEY_FULL_ON_AFTER_MS, config.full_on_after_ms)?;
+        self.nvs.set_u8(KEY_RAMP_SHAPE, config.ramp_shape.to_u8())?;
+        self.nvs.set_u8(KEY_LED_SELF_TEST, config.led_self_test as u8)?;
+        self.nvs.set_u32(KEY_STANDBY_GLOW_PCT, config.standby_glow_pct.to_bits())?;
+        self.nvs.set_u8(KEY_TEMPERATURE_SENSOR, config.temperature_sensor as u8)?;
+        self.nvs.set_u32(KEY_DERATE_FROM_C, config.derate_from_c.to_bits())?;
+        self.nvs.set_u32(KEY_DERATE_TO_C, config.derate_to_c.to_bits())?;
+        self.nvs.set_u32(KEY_DERATE_MIN_PCT, config.derate_min_pct)?;
+        self.nvs.set_u8(KEY_LED_DITHER, config.led_dither as u8)?;
+        self.nvs.set_u32(KEY_LED_FULL_WATTS, config.led_full_watts.to_bits())?;
+        self.nvs.set_u32(KEY_FADE_TAKEOVER_MS, config.fade_takeover_ms)?;
+        self.nvs.set_str(KEY_MQTT_URL, &config.mqtt_url)?;
+        self.nvs.set_str(KEY_MQTT_TOPIC, &config.mqtt_topic)?;
+        self.nvs.set_u32(KEY_MQTT_HEARTBEAT_S, config.mqtt_heartbeat_s)?;
+        self.nvs.set_u8(KEY_HA_DISCOVERY, config.ha_discovery as u8)?;
+        self.nvs.set_str(KEY_API_TOKEN, &config.api_token)?;
+        self.nvs.set_str(KEY_HOSTNAME, &config.hostname)?;
+        self.nvs.set_str(KEY_TIMEZONE, &config.timezone)?;
+        self.nvs.set_u32(KEY_BLE_PIN, config.ble_pin)?;
+        self.nvs.set_str(KEY_SYSLOG_TARGET, &config.syslog_target)?;
+        self.nvs.set_str(KEY_WEBHOOK_URLS, &config.webhook_urls)?;
+        self.nvs.set_str(KEY_INFLUX_URL, &config.influx_url)?;
+        self.nvs.set_str(KEY_INFLUX_TOKEN, &config.influx_token)?;
+        self.nvs.set_u32(KEY_INFLUX_INTERVAL_S, config.influx_interval_s)?;
+        self.nvs.set_u8(KEY_GROUP_ROLE, config.group_role.to_u8())?;
+        self.nvs.set_str(KEY_GROUP_TOPIC, &config.group_topic)?;
+        self.nvs.set_str(KEY_PROFILE, &config.profile)?;
+        self.nvs.set_u8(KEY_PERSIST_ALWAYS_ON, config.persist_always_on as u8)?;
+        self.nvs.set_u32(KEY_CAMERA_PRE_DELAY_MS, config.camera_pre_delay_ms)?;
+        self.nvs.set_u32(KEY_CAMERA_PULSE_MS, config.camera_pulse_ms)?;
+        self.nvs.set_u32(KEY_CAMERA_HOLD_S, config.camera_hold_s)?;
+        self.nvs.set_str(KEY_THREAD_DATASET, &config.thread_dataset)?;
+        self.nvs.set_str(KEY_HUE_USER, &config.hue_user)?;
+        Ok(())
+    }
+
+    pub fn load_always_on(&self) -> Result<bool> {
+        Ok(self.nvs.get_u8(KEY_ALWAYS_ON)?.is_some_and(|v| v != 0))
+    }
+
+    pub fn save_always_on(&mut self, always_on: bool) -> Result<()> {
+        self.nvs.set_u8(KEY_ALWAYS_ON, always_on as u8)?;
+        Ok(())
+    }
+
+    pub fn profile_names(&self) -> Result<Vec<String>> {
+        Ok(self.get_string(KEY_PROFILE_NAMES)?
+            .map(|names| names.split(',').filter(|name| !name.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default())
+    }
+
+    /// The values of the profile (see [profile::encode])
+    pub fn load_profile(&self, name: &str) -> Result<Option<String>> {
+        self.get_string(&format!("{}{}", KEY_PROFILE_PREFIX, name))
+    }
+
+    /// Stores the values of the profile - replaces a profile with the same name
+    pub fn save_profile(&mut self, name: &str, values: &str) -> Result<()> {
+        profile::check_name(name)?;
+        self.nvs.set_str(&format!("{}{}", KEY_PROFILE_PREFIX, name), values)?;
+        let mut names = self.profile_names()?;
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+            self.nvs.set_str(KEY_PROFILE_NAMES, &names.join(","))?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether the profile existed
+    pub fn delete_profile(&mut self, name: &str) -> Result<bool> {
+        let mut names = self.profile_names()?;
+        let count = names.len();
+        names.retain(|n| n != name);
+        self.nvs.remove(&format!("{}{}", KEY_PROFILE_PREFIX, name))?;
+        self.nvs.set_str(KEY_PROFILE_NAMES, &names.join(","))?;
+        Ok(names.len() < count)
+    }
+}
@@ -0,0 +1,109 @@
+//! Export / import of the configuration as JSON (opt-in)
+//!
+//! Clones a tuned setup onto other units: `{"config_version": 1, "params": {"lux_threshold": "20", ...}}` - the
+//! values as strings like `get`. The `hostname` is left out, as it identifies the unit.
+//!
+//! An import is checked as a whole before it is applied: the layout version must be supported by the firmware and
+//! every value must be valid - otherwise nothing is changed. Parameters missing in the import keep their value (e.g.
+//! the credentials, which the REST API does not export).
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Map, Value};
+
+use crate::config::{Config, Param, CONFIG_VERSION};
+
+/// The configuration as JSON; with `credentials` including the parameters, which may contain credentials
+pub fn export(config: &Config, credentials: bool) -> String {
+    let params = Param::ALL.iter()
+        .filter(|param| **param != Param::Hostname && (credentials || !param.is_credential()))
+        .map(|param| (param.name().to_string(), Value::from(config.get(*param))))
+        .collect::<Map<_, _>>();
+    json!({"config_version": CONFIG_VERSION, "params": params}).to_string()
+}
+
+/// The configuration with the values of the JSON export applied
+pub fn import(config: &Config, data: &[u8]) -> Result<Config> {
+    let export = serde_json::from_slice::<Value>(data)?;
+    let version = export.get("config_version").and_then(Value::as_u64)
+        .ok_or_else(|| anyhow!("config_version expected"))?;
+    if version > CONFIG_VERSION as u64 {
+        bail!("configuration of layout version {} (supported: {})", version, CONFIG_VERSION);
+    }
+    let Some(Value::Object(params)) = export.get("params") else {
+        bail!("JSON object of parameter values expected in params");
+    };
+    let mut imported = config.clone();
+    for (name, value) in params {
+        let param = name.parse::<Param>()?;
+        let value = match value {
+            Value::String(s) => s.clone(),
+            Value::Number(_) | Value::Bool(_) => value.to_string(),
+            _ => bail!("invalid value of {}", name)
+        };
+        imported.set(param, &value)?;
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import_str(json: &str) -> Result<Config> {
+        import(&Config::default(), json.as_bytes())
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let mut config = Config::default();
+        config.set(Param::LuxThreshold, "12.5").unwrap();
+        config.set(Param::ApiToken, "secret").unwrap();
+        assert_eq!(import(&Config::default(), export(&config, true).as_bytes()).unwrap(), config);
+    }
+
+    #[test]
+    fn hostname_is_not_exported() {
+        assert!(!export(&Config::default(), true).contains("\"hostname\""));
+    }
+
+    #[test]
+    fn missing_params_keep_their_value() {
+        let mut source = Config::default();
+        source.set(Param::ApiToken, "secret").unwrap();
+        let mut target = Config::default();
+        target.set(Param::ApiToken, "own").unwrap();
+        let imported = import(&target, export(&source, false).as_bytes()).unwrap();
+        assert_eq!(imported.api_token, "own");
+    }
+
+    #[test]
+    fn accepts_numbers_and_booleans() {
+        let config = import_str(r#"{"config_version": 1, "params": {"lux_window": 20, "led_hw_fade": true}}"#).unwrap();
+        assert_eq!(config.lux_window, 20);
+        assert!(config.led_hw_fade);
+    }
+
+    #[test]
+    fn rejects_an_unsupported_layout_version() {
+        let json = format!(r#"{{"config_version": {}, "params": {{}}}}"#, CONFIG_VERSION + 1);
+        assert!(import_str(&json).is_err());
+        assert!(import_str(r#"{"params": {}}"#).is_err());
+        assert!(import_str(r#"{"config_version": "1", "params": {}}"#).is_err());
+    }
+
+    #[test]
+    fn rejects_a_malformed_export() {
+        assert!(import_str("lux_threshold = 2").is_err());
+        assert!(import_str(r#"{"config_version": 1}"#).is_err());
+        assert!(import_str(r#"{"config_version": 1, "params": ["lux_threshold"]}"#).is_err());
+        assert!(import_str(r#"{"config_version": 1, "params": {"lux_threshold": null}}"#).is_err());
+        assert!(import_str(r#"{"config_version": 1, "params": {"lux_threshold": [2]}}"#).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_params_and_invalid_values_as_a_whole() {
+        assert!(import_str(r#"{"config_version": 1, "params": {"lux_threshold": "5", "no_such_param": "1"}}"#).is_err());
+        assert!(import_str(r#"{"config_version": 1, "params": {"lux_threshold": "5", "lux_window": "0"}}"#).is_err());
+        assert!(import_str(r#"{"config_version": 1, "params": {"lux_threshold": "-5"}}"#).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Serial console
+//!
+//! Reads line based commands from stdin in a separate thread and hands them over to the main loop.
+//!
+//! Commands:
+//! ```
+//! status
+//! get <param>
+//! set <param> <value>
+//! save
+//! reboot
+//! config export
+//! config import <JSON of config export>
+//! suggest lux_threshold
+//! stats lux
+//! stats poll
+//! stats energy
+//! radar
+//! audit power
+//! stress <transitions>
+//! effect <breathing|candle|blink|off>
+//! mode [auto|on|off|maintenance|emergency]
+//! power_cycle presence
+//! sensor powercycle
+//! calibrate lux <reference lux>
+//! calibrate crosstalk
+//! preset <staircase|hallway|closet|outdoor>
+//! profile
+//! profile <name>
+//! profile save <name>
+//! profile delete <name>
+//! hue pair
+//! provision duty_cap <percent>   (only within 5 minutes after power-up)
+//! ota <url>
+//! ```
+
+use std::io::Read;
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+
+use crate::config::Param;
+use crate::effects::Effect;
+use crate::mode::Mode;
+use crate::presets::Preset;
+
+const CONSOLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// log the current status
+    Status,
+    Get(Param),
+    Set(Param, String),
+    /// store the configuration (again)
+    Save,
+    /// restart the device
+    Reboot,
+    /// log the configuration as JSON
+    ExportConfig,
+    /// apply a JSON configuration export
+    ImportConfig(String),
+    /// data-driven lux threshold suggestion from the lux histogram
+    SuggestLuxThreshold,
+    /// hourly lux statistics of the last 24 hours
+    LuxStats,
+    /// sensor poll durations and loop time
+    PollStats,
+    /// latest report of the radar presence sensor (targets, distances, energies)
+    Radar,
+    /// estimated energy usage of the LEDs
+    EnergyStats,
+    /// idle-phase power audit
+    PowerAudit,
+    /// back-to-back phase transition stress test with the number of transitions
+    StressTest(u32),
+    /// start a light effect or stop it (`None`)
+    Effect(Option<Effect>),
+    /// show or switch the operating mode
+    Mode(Option<Mode>),
+    /// switch the presence sensor off and on again (e.g. when it got stuck)
+    PowerCyclePresenceSensor,
+    /// calibrate the lux readings against a reference value (e.g. from a handheld lux meter)
+    CalibrateLux(f32),
+    /// measure the LED's own light on the ambient light sensor (in the dark)
+    CalibrateCrosstalk,
+    /// apply a behavior preset
+    Preset(Preset),
+    /// list the stored configuration profiles
+    ListProfiles,
+    /// store the profile values of the configuration under the name
+    SaveProfile(String),
+    DeleteProfile(String),
+    /// open the pairing of Hue clients for a moment (like the link button of a bridge)
+    HuePair,
+    /// set the hardware safety cap of the LED duty [%]
+    ProvisionDutyCap(u32),
+    /// firmware update from the URL
+    Ota(String),
+}
+
+impl Command {
+    /// Whether the command needs physical access to the unit - accepted on the serial console only
+    pub fn is_local_only(&self) -> bool {
+        matches!(self, Command::ProvisionDutyCap(_))
+    }
+
+    /// Whether the command needs a source with a credential (`api_token`, BLE pairing) - refused from MQTT, whose
+    /// commands carry none
+    pub fn needs_authentication(&self) -> bool {
+        matches!(self, Command::Ota(_) | Command::HuePair)
+    }
+
+    pub fn parse(line: &str) -> Result<Command> {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        match tokens.as_slice() {
+            ["status"] => Ok(Command::Status),
+            ["get", param] => Ok(Command::Get(param.parse()?)),
+            ["set", param, value] => Ok(Command::Set(param.parse()?, value.to_string())),
+            ["save"] => Ok(Command::Save),
+            ["reboot"] => Ok(Command::Reboot),
+            ["config", "export"] => Ok(Command::ExportConfig),
+            // the JSON may contain whitespace
+            ["config", "import", _, ..] => Ok(Command::ImportConfig(
+                line.trim_start()["config".len()..].trim_start()["import".len()..].trim().to_string())),
+            ["suggest", "lux_threshold"] => Ok(Command::SuggestLuxThreshold),
+            ["stats", "lux"] => Ok(Command::LuxStats),
+            ["stats", "poll"] => Ok(Command::PollStats),
+            ["radar"] => Ok(Command::Radar),
+            ["stats", "energy"] => Ok(Command::EnergyStats),
+            ["audit", "power"] => Ok(Command::PowerAudit),
+            ["stress", transitions] => Ok(Command::StressTest(transitions.parse()?)),
+            ["effect", "off"] => Ok(Command::Effect(None)),
+            ["effect", effect] => Ok(Command::Effect(Some(effect.parse()?))),
+            ["mode"] => Ok(Command::Mode(None)),
+            ["mode", mode] => Ok(Command::Mode(Some(mode.parse()?))),
+            ["power_cycle", "presence"] | ["sensor", "powercycle"] => Ok(Command::PowerCyclePresenceSensor),
+            ["calibrate", "lux", reference] => Ok(Command::CalibrateLux(reference.parse()?)),
+            ["calibrate", "crosstalk"] => Ok(Command::CalibrateCrosstalk),
+            ["preset", preset] => Ok(Command::Preset(preset.parse()?)),
+            ["profile"] => Ok(Command::ListProfiles),
+            ["profile", name] => Ok(Command::Set(Param::Profile, name.to_string())),
+            ["profile", "save", name] => Ok(Command::SaveProfile(name.to_string())),
+            ["profile", "delete", name] => Ok(Command::DeleteProfile(name.to_string())),
+            ["hue", "pair"] => Ok(Command::HuePair),
+            ["provision", "duty_cap", percent] => Ok(Command::ProvisionDutyCap(percent.parse()?)),
+            ["ota", url] => Ok(Command::Ota(url.to_string())),
+            _ => Err(anyhow!("invalid command '{}'", line))
+        }
+    }
+}
+
+pub struct Console {
+    receiver: Receiver<Command>,
+}
+
+impl Console {
+    pub fn start() -> Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("console".to_string())
+            .stack_size(4096)
+            .spawn(move || read_commands(sender))?;
+        Ok(Self { receiver })
+    }
+
+    /// Returns the next pending command (non-blocking)
+    pub fn poll(&self) -> Option<Command> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+fn read_commands(sender: Sender<Command>) {
+    let mut stdin = std::io::stdin();
+    let mut line: Vec<u8> = Vec::new();
+    let mut buf = [0_u8; 64];
+    loop {
+        // stdin is non-blocking on ESP-IDF, so we poll
+        let n = match stdin.read(&mut buf) {
+            Ok(n) => n,
+            Err(_) => 0
+        };
+        if n == 0 {
+            thread::sleep(CONSOLE_POLL_INTERVAL);
+            continue;
+        }
+        for &b in &buf[..n] {
+            if b == b'\n' || b == b'\r' {
+                let text = String::from_utf8_lossy(&line).trim().to_string();
+                line.clear();
+                if text.is_empty() {
+                    continue;
+                }
+                match Command::parse(&text) {
+                    Ok(command) => if sender.send(command).is_err() {
+                        return;
+                    },
+                    Err(e) => log::warn!("{}", e)
+                }
+            } else {
+                line.push(b);
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! LED crosstalk model - light of the LED itself, which reaches the ambient light sensor in a shared enclosure
+//!
+//! A one-time calibration (in the dark) measures the LED's lux contribution at several power stages.
+//! During operation the expected contribution at the current stage is interpolated and subtracted,
+//! so the sensor stays useful while the light is on.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::bail;
+
+use crate::LED_MAX_POWER_STAGE;
+
+/// Power stages measured during the calibration (stage 0 contributes nothing)
+pub const CALIBRATION_STAGES: [u32; 5] = [
+    LED_MAX_POWER_STAGE / 5,
+    LED_MAX_POWER_STAGE * 2 / 5,
+    LED_MAX_POWER_STAGE * 3 / 5,
+    LED_MAX_POWER_STAGE * 4 / 5,
+    LED_MAX_POWER_STAGE,
+];
+
+/// Lux contribution of the LED at the [CALIBRATION_STAGES]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CrosstalkCurve {
+    lux: [f32; CALIBRATION_STAGES.len()],
+}
+
+impl CrosstalkCurve {
+    pub fn new(lux: [f32; CALIBRATION_STAGES.len()]) -> Self {
+        Self { lux }
+    }
+
+    /// Expected lux contribution at `stage` (linear interpolation between the calibration points)
+    pub fn contribution(&self, stage: u32) -> f32 {
+        let mut lower = (0, 0.0);
+        for (&upper_stage, &upper_lux) in CALIBRATION_STAGES.iter().zip(self.lux.iter()) {
+            if stage <= upper_stage {
+                let (lower_stage, lower_lux) = lower;
+                let ratio = (stage - lower_stage) as f32 / (upper_stage - lower_stage) as f32;
+                return lower_lux + ratio * (upper_lux - lower_lux);
+            }
+            lower = (upper_stage, upper_lux);
+        }
+        lower.1
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.lux.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != CALIBRATION_STAGES.len() * 4 {
+            return None;
+        }
+        let mut lux = [0.0; CALIBRATION_STAGES.len()];
+        for (v, chunk) in lux.iter_mut().zip(bytes.chunks_exact(4)) {
+            *v = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        Some(Self { lux })
+    }
+}
+
+impl Display for CrosstalkCurve {
+    /// Comma separated lux values at the calibration stages
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let values = self.lux.iter().map(|v| format!("{:.2}", v)).collect::<Vec<_>>();
+        f.write_str(&values.join(","))
+    }
+}
+
+impl FromStr for CrosstalkCurve {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s.split(',').map(|v| v.trim().parse::<f32>()).collect::<Result<Vec<_>, _>>()?;
+        let Ok(lux) = <[f32; CALIBRATION_STAGES.len()]>::try_from(values) else {
+            bail!("expected {} comma separated lux values (at stages {:?})", CALIBRATION_STAGES.len(), CALIBRATION_STAGES);
+        };
+        Ok(Self { lux })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVE: CrosstalkCurve = CrosstalkCurve { lux: [1.0, 2.0, 4.0, 8.0, 10.0] };
+
+    #[test]
+    fn contribution_interpolates_between_the_calibration_stages() {
+        assert_eq!(CURVE.contribution(0), 0.0);
+        assert_eq!(CURVE.contribution(CALIBRATION_STAGES[0] / 2), 0.5);
+        assert_eq!(CURVE.contribution(CALIBRATION_STAGES[0]), 1.0);
+        assert_eq!(CURVE.contribution((CALIBRATION_STAGES[2] + CALIBRATION_STAGES[3]) / 2), 6.0);
+        assert_eq!(CURVE.contribution(LED_MAX_POWER_STAGE), 10.0);
+        // beyond the last calibration point
+        assert_eq!(CURVE.contribution(LED_MAX_POWER_STAGE + 1), 10.0);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        assert_eq!(CrosstalkCurve::from_bytes(&CURVE.to_bytes()), Some(CURVE));
+        assert_eq!(CrosstalkCurve::from_bytes(&[0; 4]), None);
+    }
+
+    #[test]
+    fn parses_the_display_format() {
+        assert_eq!(CURVE.to_string(), "1.00,2.00,4.00,8.00,10.00");
+        assert_eq!(CURVE.to_string().parse::<CrosstalkCurve>().unwrap(), CURVE);
+        assert_eq!(" 1, 2,4 ,8,10".parse::<CrosstalkCurve>().unwrap(), CURVE);
+        assert!("1,2,4,8".parse::<CrosstalkCurve>().is_err());
+        assert!("1,2,4,8,x".parse::<CrosstalkCurve>().is_err());
+    }
+}
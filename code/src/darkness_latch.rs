@@ -0,0 +1,71 @@
+//! Darkness latch - prevents flickering of the darkness decision at night
+//!
+//! Once it has been dark (lux at or below the threshold) for the latch time, we latch the "dark" state.
+//! It is released only after the lux level stayed above the (much higher) release threshold for the release time.
+//! So passing cars or a briefly switched on hall light don't end the night mode.
+
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+
+#[derive(Debug)]
+pub struct DarknessLatch {
+    dark_since: Option<Instant>,
+    above_release_since: Option<Instant>,
+    latched: bool,
+}
+
+impl DarknessLatch {
+    pub fn new() -> Self {
+        Self {
+            dark_since: None,
+            above_release_since: None,
+            latched: false,
+        }
+    }
+
+    pub fn is_latched(&self) -> bool {
+        self.latched
+    }
+
+    /// Updates the latch with the current (filtered) lux level and the effective darkness threshold;
+    /// returns the new latch state, if it changed
+    pub fn update(&mut self, lux: f32, lux_threshold: f32, config: &Config, now: Instant) -> Option<bool> {
+        if config.dark_latch_min == 0 {
+            return self.set_latched(false);
+        }
+
+        if lux <= lux_threshold {
+            self.dark_since.get_or_insert(now);
+        } else {
+            self.dark_since = None;
+        }
+        if lux > config.dark_release_lux {
+            self.above_release_since.get_or_insert(now);
+        } else {
+            self.above_release_since = None;
+        }
+
+        if self.latched {
+            let release_time = Duration::from_secs(config.dark_release_min as u64 * 60);
+            if self.above_release_since.is_some_and(|t| now.duration_since(t) >= release_time) {
+                return self.set_latched(false);
+            }
+        } else {
+            let latch_time = Duration::from_secs(config.dark_latch_min as u64 * 60);
+            if self.dark_since.is_some_and(|t| now.duration_since(t) >= latch_time) {
+                return self.set_latched(true);
+            }
+        }
+        None
+    }
+
+    fn set_latched(&mut self, latched: bool) -> Option<bool> {
+        if self.latched != latched {
+            self.latched = latched;
+            Some(latched)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Daylight deep sleep
+//!
+//! In broad daylight there is nothing to do for hours. Once the lux level stayed above the daylight level
+//! for the configured time, the SoC goes to timed deep sleep and re-checks the light level after waking up.
+//! Waking up from deep sleep is a restart, so a resumed instance only needs a single bright reading
+//! to go back to sleep.
+
+use std::time::{Duration, Instant};
+
+use esp_idf_svc::sys::{esp_deep_sleep, esp_sleep_get_wakeup_cause, esp_sleep_source_t_ESP_SLEEP_WAKEUP_TIMER};
+
+use crate::config::Config;
+
+#[derive(Debug)]
+pub struct DaylightSleep {
+    bright_since: Option<Instant>,
+    /// we woke up from a daylight sleep and it was not dark in between
+    resumed: bool,
+}
+
+impl DaylightSleep {
+    pub fn new(resumed: bool) -> Self {
+        Self {
+            bright_since: None,
+            resumed,
+        }
+    }
+
+    /// Updates with the current (filtered) lux level; returns the sleep duration, when it's time to go to sleep
+    pub fn update(&mut self, lux: f32, config: &Config, now: Instant) -> Option<Duration> {
+        if config.day_sleep_after_min == 0 || lux <= config.day_sleep_lux {
+            self.bright_since = None;
+            self.resumed = false;
+            return None;
+        }
+        let bright_since = *self.bright_since.get_or_insert(now);
+        let bright_time = Duration::from_secs(config.day_sleep_after_min as u64 * 60);
+        if self.resumed || now.duration_since(bright_since) >= bright_time {
+            Some(Duration::from_secs(config.day_sleep_wake_min as u64 * 60))
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether this start is a wake-up from a (timed) daylight sleep
+pub fn woke_from_daylight_sleep() -> bool {
+    unsafe { esp_sleep_get_wakeup_cause() == esp_sleep_source_t_ESP_SLEEP_WAKEUP_TIMER }
+}
+
+/// Enters timed deep sleep. Does not return - the SoC restarts after the sleep duration.
+pub fn deep_sleep(duration: Duration) -> ! {
+    unsafe { esp_deep_sleep(duration.as_micros() as u64) }
+}
@@ -0,0 +1,97 @@
+//! Temporal duty dithering for smooth ultra-low brightness
+//!
+//! With `led_dither` an LED output has [DITHER_BITS] more duty resolution than its PWM. A periodic timer alternates
+//! the PWM duty between the two adjacent integer duties per group of PWM periods (sigma-delta), so that the average
+//! duty includes the fractional part. The lowest levels of a fade then step smoothly despite the 12 bit resolution.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use esp_idf_svc::sys::{ledc_channel_t, ledc_get_duty, ledc_set_duty, ledc_update_duty};
+use esp_idf_svc::timer::{EspTaskTimerService, EspTimer};
+
+use crate::peripheral::LED_LEDC_SPEED_MODE;
+
+/// Additional duty bits of a dithered output
+pub const DITHER_BITS: u32 = 4;
+const FRACTION_MASK: u32 = (1 << DITHER_BITS) - 1;
+/// One group of PWM periods (5 periods at 5 kHz)
+const DITHER_PERIOD: Duration = Duration::from_millis(1);
+
+/// Duty of a dithered LED output, shared with the dither timer
+pub struct DitherSlot {
+    ledc_channel: ledc_channel_t,
+    /// max. duty of the PWM
+    max_duty: u32,
+    /// see `invert_pwm`
+    inverted: bool,
+    /// LED duty with [DITHER_BITS] fractional bits
+    fine_duty: AtomicU32,
+}
+
+impl DitherSlot {
+    pub fn new(ledc_channel: ledc_channel_t, max_duty: u32, inverted: bool) -> Self {
+        Self { ledc_channel, max_duty, inverted, fine_duty: AtomicU32::new(0) }
+    }
+
+    pub fn set_fine_duty(&self, fine_duty: u32) {
+        self.fine_duty.store(fine_duty, Ordering::Relaxed);
+    }
+
+    pub fn fine_duty(&self) -> u32 {
+        self.fine_duty.load(Ordering::Relaxed)
+    }
+
+    /// Whether the PWM `duty` is one of the two duties the fine duty alternates between
+    pub fn matches(&self, duty: u32) -> bool {
+        let fine_duty = self.fine_duty();
+        let floor = fine_duty >> DITHER_BITS;
+        duty == floor || (fine_duty & FRACTION_MASK != 0 && duty == floor + 1)
+    }
+
+    fn signal_duty(&self, duty: u32) -> u32 {
+        if self.inverted { self.max_duty - duty } else { duty }
+    }
+}
+
+/// The dither timer - runs as long as it is kept
+pub struct Ditherer {
+    _timer: EspTimer<'static>,
+}
+
+impl Ditherer {
+    pub fn start(slots: Vec<Arc<DitherSlot>>) -> Result<Self> {
+        // sigma-delta accumulators and whether the last duty was a dithered one
+        let mut accumulators = vec![0_u32; slots.len()];
+        let mut dithering = vec![false; slots.len()];
+        let timer = EspTaskTimerService::new()?.timer(move || {
+            for ((slot, accumulator), dithering) in slots.iter().zip(accumulators.iter_mut()).zip(dithering.iter_mut()) {
+                let fine_duty = slot.fine_duty();
+                let fraction = fine_duty & FRACTION_MASK;
+                if fraction == 0 && !*dithering {
+                    continue;
+                }
+                let floor = fine_duty >> DITHER_BITS;
+                // Safety: reads the duty of an LEDC channel configured by its driver
+                let signal = unsafe { ledc_get_duty(LED_LEDC_SPEED_MODE, slot.ledc_channel) };
+                if fraction != 0 && !slot.matches(slot.signal_duty(signal)) {
+                    // a hardware fade towards the duty is in progress
+                    continue;
+                }
+                *accumulator += fraction;
+                let duty = (floor + (*accumulator >> DITHER_BITS)).min(slot.max_duty);
+                *accumulator &= FRACTION_MASK;
+                *dithering = fraction != 0;
+                // Safety: sets the duty of an LEDC channel configured by its driver - the driver API locks internally
+                unsafe {
+                    ledc_set_duty(LED_LEDC_SPEED_MODE, slot.ledc_channel, slot.signal_duty(duty));
+                    ledc_update_duty(LED_LEDC_SPEED_MODE, slot.ledc_channel);
+                }
+            }
+        })?;
+        timer.every(DITHER_PERIOD)?;
+        Ok(Self { _timer: timer })
+    }
+}
@@ -0,0 +1,125 @@
+//! Software light effects
+//!
+//! An active effect temporarily overrides the duty computed from the power stage - the phase logic goes on
+//! underneath and takes over again, when the effect is stopped or finished.
+
+use std::f32::consts::PI;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+
+/// Loop delay while an effect is active
+pub const EFFECT_STEP_MS: u32 = 20;
+
+const BREATHING_PERIOD: Duration = Duration::from_secs(4);
+/// level range of the breathing glow
+const BREATHING_MIN_LEVEL: f32 = 0.02;
+const BREATHING_MAX_LEVEL: f32 = 0.25;
+
+const CANDLE_BASE_LEVEL: f32 = 0.55;
+const CANDLE_FLICKER: f32 = 0.25;
+/// share of a new random level per tick - the rest is the previous level
+const CANDLE_RESPONSIVENESS: f32 = 0.3;
+
+const BLINK_COUNT: u32 = 3;
+const BLINK_HALF_PERIOD: Duration = Duration::from_millis(200);
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Effect {
+    /// slow breathing standby glow
+    Breathing,
+    /// candle flicker
+    Candle,
+    /// three blinks - error notification, ends on its own
+    TripleBlink,
+}
+
+impl Display for Effect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Effect::Breathing => "breathing",
+            Effect::Candle => "candle",
+            Effect::TripleBlink => "blink",
+        })
+    }
+}
+
+impl FromStr for Effect {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "breathing" => Ok(Effect::Breathing),
+            "candle" => Ok(Effect::Candle),
+            "blink" => Ok(Effect::TripleBlink),
+            _ => Err(anyhow!("unknown effect '{}' (expected breathing, candle or blink)", s))
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ActiveEffect {
+    effect: Effect,
+    started: Instant,
+    /// last level of the candle flicker
+    level: f32,
+    /// xorshift state of the candle flicker
+    random: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct Effects {
+    active: Option<ActiveEffect>,
+}
+
+impl Effects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start(&mut self, effect: Effect, now: Instant) {
+        log::info!("effect {} started", effect);
+        self.active = Some(ActiveEffect { effect, started: now, level: CANDLE_BASE_LEVEL, random: 0x2545_f491 });
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(active) = self.active.take() {
+            log::info!("effect {} stopped", active.effect);
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Level [0..=1] of the full duty overriding the normal duty - `None` without an active effect
+    pub fn level(&mut self, now: Instant) -> Option<f32> {
+        let active = self.active.as_mut()?;
+        let elapsed = now.duration_since(active.started);
+        match active.effect {
+            Effect::Breathing => {
+                let x = (elapsed.as_secs_f32() / BREATHING_PERIOD.as_secs_f32()).fract();
+                let breath = (1.0 - (2.0 * PI * x).cos()) / 2.0;
+                Some(BREATHING_MIN_LEVEL + (BREATHING_MAX_LEVEL - BREATHING_MIN_LEVEL) * breath)
+            }
+            Effect::Candle => {
+                active.random ^= active.random << 13;
+                active.random ^= active.random >> 17;
+                active.random ^= active.random << 5;
+                let target = CANDLE_BASE_LEVEL + CANDLE_FLICKER * (active.random as f32 / u32::MAX as f32 * 2.0 - 1.0);
+                active.level += (target - active.level) * CANDLE_RESPONSIVENESS;
+                Some(active.level)
+            }
+            Effect::TripleBlink => {
+                let half_periods = (elapsed.as_millis() / BLINK_HALF_PERIOD.as_millis()) as u32;
+                if half_periods >= 2 * BLINK_COUNT {
+                    self.stop();
+                    return None;
+                }
+                Some(if half_periods % 2 == 0 { 1.0 } else { 0.0 })
+            }
+        }
+    }
+}
@@ -5,6 +5,7 @@ use std::fmt::{Debug, Display, Formatter};
 pub struct Error {
     pub msg: String
 }
+#[cfg(feature = "veml7700")]
 impl<T: Debug> From<veml7700::Error<T>> for Error {
     fn from(value: veml7700::Error<T>) -> Self {
         Error {
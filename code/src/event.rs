@@ -0,0 +1,188 @@
+//! Events raised by the control logic, to be picked up by notification sinks
+
+use std::fmt::{Display, Formatter};
+
+#[cfg(any(feature = "remote-diagnostics", feature = "webhooks", feature = "mqtt", feature = "influxdb",
+    feature = "rest-api", feature = "ble"))]
+use crate::config::Config;
+#[cfg(any(feature = "remote-diagnostics", feature = "mqtt", feature = "influxdb", feature = "rest-api", feature = "ble"))]
+use crate::light_sensor::LightReading;
+#[cfg(any(feature = "remote-diagnostics", feature = "mqtt", feature = "influxdb", feature = "rest-api", feature = "ble"))]
+use crate::mode::Mode;
+use crate::occlusion::Implausibility;
+#[cfg(any(feature = "remote-diagnostics", feature = "mqtt", feature = "influxdb", feature = "rest-api", feature = "ble"))]
+use crate::ramp::Phase;
+#[cfg(any(feature = "remote-diagnostics", feature = "mqtt", feature = "influxdb", feature = "rest-api", feature = "ble"))]
+use crate::status::StatusSnapshot;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Event {
+    /// first presence detection after a quiet period
+    PresenceStart,
+    /// no presence detected anymore for the quiet period
+    PresenceEnd,
+    /// camera trigger pulse was fired
+    #[cfg_attr(not(feature = "camera-trigger"), allow(dead_code))]
+    CameraTriggered,
+    /// ambient light sensor readings are implausible - sensor possibly occluded or misplaced
+    LightSensorImplausible(Implausibility),
+    /// ambient light sensor stopped responding and could not be re-initialized
+    LightSensorLost,
+    /// ambient light sensor (re)appeared on the bus and was initialized
+    LightSensorFound,
+    /// presence-to-light latency [ms] above the configured budget
+    LatencyBudgetExceeded(u32),
+    /// the test button was pressed - a presence is simulated
+    #[cfg_attr(not(feature = "test-button"), allow(dead_code))]
+    TestTriggered,
+    /// reaction latency [ms] of the test trigger; `None` if the light did not come up
+    #[cfg_attr(not(feature = "test-button"), allow(dead_code))]
+    TestTriggerReaction(Option<u32>),
+    /// the light was on for `max_on_time_min` without a break - powered down regardless of the presence
+    MaxOnTimeReached,
+    /// the component failed at startup (see [crate::bringup])
+    BringupFailed(&'static str),
+}
+
+#[cfg(any(feature = "remote-diagnostics", feature = "webhooks"))]
+impl Event {
+    /// Occupancy related - reveals when somebody is around
+    pub fn is_occupancy(&self) -> bool {
+        matches!(self, Event::PresenceStart | Event::PresenceEnd | Event::CameraTriggered
+            | Event::TestTriggered | Event::TestTriggerReaction(_))
+    }
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::BringupFailed(component) => write!(f, "BringupFailed({})", component),
+            _ => write!(f, "{:?}", self)
+        }
+    }
+}
+
+/// Collects events during a loop iteration, which are dispatched afterwards
+#[derive(Debug, Default)]
+pub struct EventQueue {
+    events: Vec<Event>,
+}
+
+impl EventQueue {
+    pub fn push(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    pub fn drain(&mut self) -> impl Iterator<Item=Event> + '_ {
+        self.events.drain(..)
+    }
+}
+
+/// Gate for events leaving the device (telemetry) - every export passes here.
+/// With `local_only_analytics` occupancy events stay on the device; only the number of presences is exported.
+#[cfg(any(feature = "remote-diagnostics", feature = "webhooks"))]
+#[derive(Debug, Default)]
+pub struct ExportFilter {
+    withheld_presences: u32,
+}
+
+#[cfg(any(feature = "remote-diagnostics", feature = "webhooks"))]
+impl ExportFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The event, if it may leave the device
+    pub fn pass(&mut self, config: &Config, event: Event) -> Option<Event> {
+        if !config.local_only_analytics || !event.is_occupancy() {
+            return Some(event);
+        }
+        if event == Event::PresenceStart {
+            self.withheld_presences += 1;
+        }
+        None
+    }
+
+    /// Number of presences withheld since the last call
+    #[cfg(feature = "remote-diagnostics")]
+    pub fn take_presence_count(&mut self) -> u32 {
+        std::mem::take(&mut self.withheld_presences)
+    }
+}
+
+/// The status as it may leave the device - every export of the status (MQTT, Home Assistant discovery, InfluxDB,
+/// remote diagnostics, REST API, WebSocket, BLE) is built from it.
+/// With `local_only_analytics` the occupancy related values are withheld.
+#[cfg(any(feature = "remote-diagnostics", feature = "mqtt", feature = "influxdb", feature = "rest-api", feature = "ble"))]
+#[derive(Clone, Debug)]
+pub struct ExportedStatus {
+    pub mode: Mode,
+    pub dark_enough: bool,
+    pub darkness_latched: bool,
+    /// filtered lux level
+    pub lux: Option<f32>,
+    pub light_reading: Option<LightReading>,
+    pub light_sensor_failures: u32,
+    pub light_sensor_available: bool,
+    pub presence_sensor_enabled: bool,
+    /// `None` with `local_only_analytics`
+    pub occupancy: Option<Occupancy>,
+}
+
+/// The status values, which reveal when somebody is around: the light and the presence sensor signal
+#[cfg(any(feature = "remote-diagnostics", feature = "mqtt", feature = "influxdb", feature = "rest-api", feature = "ble"))]
+#[derive(Clone, Debug)]
+pub struct Occupancy {
+    pub phase: Phase,
+    pub led_power_stage: u32,
+    pub target_stage: u32,
+    /// logical (non-inverted) duty
+    pub duty: u32,
+    pub max_duty: u32,
+    pub presence: bool,
+    pub reaction_latency_ms: Option<u32>,
+    /// estimated LED power [W] - with `led_full_watts` only
+    pub led_watts: Option<f32>,
+    pub energy_today_wh: f64,
+}
+
+#[cfg(any(feature = "remote-diagnostics", feature = "mqtt", feature = "influxdb", feature = "rest-api", feature = "ble"))]
+impl ExportedStatus {
+    pub fn new(config: &Config, status: &StatusSnapshot) -> Self {
+        let occupancy = Self::includes_occupancy(config).then(|| Occupancy {
+            phase: status.phase,
+            led_power_stage: status.led_power_stage,
+            target_stage: status.target_stage,
+            duty: status.duty,
+            max_duty: status.max_duty,
+            presence: status.presence,
+            reaction_latency_ms: status.reaction_latency_ms,
+            led_watts: status.led_watts,
+            energy_today_wh: status.energy_today_wh,
+        });
+        Self {
+            mode: status.mode,
+            dark_enough: status.dark_enough,
+            darkness_latched: status.darkness_latched,
+            lux: status.lux,
+            light_reading: status.light_reading,
+            light_sensor_failures: status.light_sensor_failures,
+            light_sensor_available: status.light_sensor_available,
+            presence_sensor_enabled: status.presence_sensor_enabled,
+            occupancy,
+        }
+    }
+
+    /// Whether the occupancy related values may leave the device - also for exports without a status
+    pub fn includes_occupancy(config: &Config) -> bool {
+        !config.local_only_analytics
+    }
+}
+
+#[cfg(any(feature = "remote-diagnostics", feature = "mqtt", feature = "influxdb", feature = "rest-api", feature = "ble"))]
+impl Occupancy {
+    /// Duty relative to the max. duty [%]
+    pub fn duty_pct(&self) -> f32 {
+        self.duty as f32 * 100.0 / self.max_duty.max(1) as f32
+    }
+}
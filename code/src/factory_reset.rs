@@ -0,0 +1,41 @@
+//! Factory reset - triggered by a long press of the switch (see [crate::switch])
+//!
+//! Wipes the stored configuration, profiles and statistics from NVS - including the credentials in the configuration,
+//! the BLE bonds of the paired phones and the Thread network settings (the unit doesn't rejoin the network).
+//! The device restarts with the defaults.
+//! The provisioned safety cap of the LED duty is kept, as it belongs to the hardware.
+
+use std::ffi::CString;
+
+use anyhow::Result;
+use esp_idf_svc::sys::{esp, nvs_close, nvs_commit, nvs_erase_all, nvs_open, nvs_open_mode_t_NVS_READWRITE};
+
+use crate::{config, lux_stats, night_baseline};
+
+/// NVS namespaces wiped by a factory reset
+const WIPED_NAMESPACES: &[&str] = &[
+    config::NVS_NAMESPACE,
+    lux_stats::NVS_NAMESPACE,
+    night_baseline::NVS_NAMESPACE,
+    #[cfg(feature = "ble")]
+    crate::ble::BOND_NVS_NAMESPACE,
+    #[cfg(feature = "thread")]
+    crate::thread_net::SETTINGS_NVS_NAMESPACE,
+];
+
+/// Erases the stored configuration and statistics
+pub fn wipe_nvs() -> Result<()> {
+    for &namespace in WIPED_NAMESPACES {
+        let name = CString::new(namespace)?;
+        let mut handle = 0;
+        // Safety: the handle is only used between open and close
+        unsafe {
+            esp!(nvs_open(name.as_ptr(), nvs_open_mode_t_NVS_READWRITE, &mut handle))?;
+            let result = esp!(nvs_erase_all(handle)).and_then(|()| esp!(nvs_commit(handle)));
+            nvs_close(handle);
+            result?;
+        }
+        log::info!("NVS namespace {} wiped", namespace);
+    }
+    Ok(())
+}
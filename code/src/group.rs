@@ -0,0 +1,66 @@
+//! Group of lights with coordinator / follower roles (opt-in)
+//!
+//! Chained lights (e.g. the flights of a staircase) act as one light. The messages are exchanged via the MQTT broker
+//! (see [crate::mqtt]), below `group_topic`:
+//! - `<group_topic>/presence`: the followers report their presence sensor signal, e.g.
+//!   `{"device": "sensor_light_…", "presence": true}` - on a change and repeatedly while a presence lasts
+//! - `<group_topic>/light`: the coordinator announces its LED target stage, e.g. `{"target_stage": 8000}` - on a
+//!   change and repeatedly
+//!
+//! The coordinator handles a follower's presence like its own. A follower moves to the announced stage with its own
+//! ramp - with the same fade settings (e.g. from one profile) the lights fade in step. Without messages of the
+//! coordinator a follower goes on with its own presence logic. A mode other than `auto` overrides the group.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum GroupRole {
+    Standalone,
+    Coordinator,
+    Follower,
+}
+
+impl GroupRole {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            GroupRole::Standalone => 0,
+            GroupRole::Coordinator => 1,
+            GroupRole::Follower => 2,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(GroupRole::Standalone),
+            1 => Some(GroupRole::Coordinator),
+            2 => Some(GroupRole::Follower),
+            _ => None
+        }
+    }
+}
+
+impl Display for GroupRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            GroupRole::Standalone => "standalone",
+            GroupRole::Coordinator => "coordinator",
+            GroupRole::Follower => "follower",
+        })
+    }
+}
+
+impl FromStr for GroupRole {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standalone" => Ok(GroupRole::Standalone),
+            "coordinator" => Ok(GroupRole::Coordinator),
+            "follower" => Ok(GroupRole::Follower),
+            _ => Err(anyhow!("unknown group role '{}' (expected standalone, coordinator or follower)", s))
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Message exchange of a group of lights (see [crate::group])
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::config::Config;
+use crate::group::GroupRole;
+
+/// Repetition of an unchanged message - keeps the receivers from timing out
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+/// Age after which a follower's presence or the coordinator's stage is no longer valid
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(15);
+
+const PRESENCE_SUBTOPIC: &str = "presence";
+const LIGHT_SUBTOPIC: &str = "light";
+
+pub struct GroupSync {
+    device_id: String,
+    /// presence of the followers by device id with the time of the last message (coordinator)
+    follower_presence: HashMap<String, (bool, Instant)>,
+    /// target stage of the coordinator with the time of the last message (follower)
+    coordinator_stage: Option<(u32, Instant)>,
+    /// last sent payload and the time
+    last_sent: Option<(String, Instant)>,
+}
+
+impl GroupSync {
+    pub fn new(device_id: String) -> Self {
+        Self {
+            device_id,
+            follower_presence: HashMap::new(),
+            coordinator_stage: None,
+            last_sent: None,
+        }
+    }
+
+    /// The topic the role receives messages on
+    pub fn subscription(config: &Config) -> Option<String> {
+        match config.group_role {
+            GroupRole::Standalone => None,
+            GroupRole::Coordinator => Some(format!("{}/{}", config.group_topic, PRESENCE_SUBTOPIC)),
+            GroupRole::Follower => Some(format!("{}/{}", config.group_topic, LIGHT_SUBTOPIC)),
+        }
+    }
+
+    /// Takes a message received on the subscription
+    pub fn receive(&mut self, config: &Config, data: &[u8], now: Instant) {
+        let Ok(message) = serde_json::from_slice::<Value>(data) else {
+            log::warn!("invalid group message: {}", String::from_utf8_lossy(data));
+            return;
+        };
+        match config.group_role {
+            GroupRole::Coordinator => {
+                let device = message.get("device").and_then(Value::as_str);
+                let presence = message.get("presence").and_then(Value::as_bool);
+                if let (Some(device), Some(presence)) = (device, presence) {
+                    self.follower_presence.insert(device.to_string(), (presence, now));
+                }
+            }
+            GroupRole::Follower => {
+                if let Some(stage) = message.get("target_stage").and_then(Value::as_u64) {
+                    self.coordinator_stage = Some((stage as u32, now));
+                }
+            }
+            GroupRole::Standalone => ()
+        }
+    }
+
+    /// Coordinator: whether a follower currently reports a presence
+    pub fn follower_presence(&mut self, now: Instant) -> bool {
+        self.follower_presence.retain(|_, (_, t)| now.duration_since(*t) < MESSAGE_TIMEOUT);
+        self.follower_presence.values().any(|(presence, _)| *presence)
+    }
+
+    /// Follower: the target stage of the coordinator, if it is still valid
+    pub fn coordinator_stage(&self, now: Instant) -> Option<u32> {
+        self.coordinator_stage
+            .filter(|(_, t)| now.duration_since(*t) < MESSAGE_TIMEOUT)
+            .map(|(stage, _)| stage)
+    }
+
+    /// The message (topic, payload) of the role to be published now - on a change and when the refresh is due.
+    /// A follower repeats only a presence, the end of a presence times out anyway.
+    pub fn outgoing(&mut self, config: &Config, presence: bool, target_stage: u32, now: Instant) -> Option<(String, String)> {
+        let (subtopic, payload, refresh) = match config.group_role {
+            GroupRole::Standalone => return None,
+            GroupRole::Coordinator => (LIGHT_SUBTOPIC, json!({"target_stage": target_stage}), true),
+            GroupRole::Follower => (PRESENCE_SUBTOPIC, json!({"device": self.device_id, "presence": presence}), presence),
+        };
+        let payload = payload.to_string();
+        let due = match &self.last_sent {
+            Some((last, t)) => *last != payload || (refresh && now.duration_since(*t) >= REFRESH_INTERVAL),
+            None => true
+        };
+        if !due {
+            return None;
+        }
+        self.last_sent = Some((payload.clone(), now));
+        Some((format!("{}/{}", config.group_topic, subtopic), payload))
+    }
+}
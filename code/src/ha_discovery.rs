@@ -0,0 +1,98 @@
+//! Home Assistant MQTT discovery
+//!
+//! Describes the published topics (see [crate::mqtt]) as Home Assistant entities, so that the light shows up in
+//! Home Assistant without manual YAML:
+//! - light (on = forced on, off = forced off) with the duty as brightness - setting the brightness sets
+//!   `max_brightness_pct`
+//! - binary sensor: presence
+//! - sensor: lux
+//! - switch: always-on (on = forced on, off = automatic)
+//!
+//! All entities follow the availability topic of the light.
+//! With `local_only_analytics` the light and the presence sensor are left out - their topics are not published (see
+//! [ExportedStatus]).
+
+use esp_idf_svc::sys;
+use serde_json::{json, Value};
+
+use crate::config::Config;
+use crate::event::ExportedStatus;
+use crate::mqtt::{availability_topic, AVAILABILITY_OFFLINE, AVAILABILITY_ONLINE};
+
+const DISCOVERY_PREFIX: &str = "homeassistant";
+
+/// Unique id of the device, derived from its MAC address
+pub fn device_id() -> String {
+    let mut mac = [0_u8; 6];
+    // Safety: the buffer has the size of a MAC address
+    unsafe {
+        sys::esp_efuse_mac_get_default(mac.as_mut_ptr());
+    }
+    format!("sensor_light_{}", mac.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+/// Retained discovery messages (topic, payload) of all entities
+pub fn discovery_messages(config: &Config, device_id: &str) -> Vec<(String, String)> {
+    let topic = &config.mqtt_topic;
+    let command_topic = format!("{}/cmd", topic);
+    let device = json!({
+        "identifiers": [device_id],
+        "name": topic,
+        "model": "Sensor Light",
+        "manufacturer": "bitmagier",
+        "sw_version": env!("CARGO_PKG_VERSION"),
+    });
+
+    let mut entities = vec![
+        ("sensor", "lux", json!({
+            "name": "Illuminance",
+            "state_topic": format!("{}/lux", topic),
+            "device_class": "illuminance",
+            "state_class": "measurement",
+            "unit_of_measurement": "lx",
+        })),
+        ("switch", "always_on", json!({
+            "name": "Always on",
+            "state_topic": format!("{}/mode", topic),
+            "value_template": "{{ 'ON' if value == 'on' else 'OFF' }}",
+            "command_topic": command_topic,
+            "payload_on": r#"{"command": "force_on"}"#,
+            "payload_off": r#"{"command": "auto"}"#,
+            "state_on": "ON",
+            "state_off": "OFF",
+        })),
+    ];
+    if ExportedStatus::includes_occupancy(config) {
+        entities.push(("light", "light", json!({
+            "name": "Light",
+            "state_topic": format!("{}/phase", topic),
+            "state_value_template": "{{ 'OFF' if value == 'off' else 'ON' }}",
+            "command_topic": command_topic,
+            "payload_on": r#"{"command": "force_on"}"#,
+            "payload_off": r#"{"command": "force_off"}"#,
+            "brightness_state_topic": format!("{}/duty", topic),
+            "brightness_value_template": "{{ value | float | round(0) | int }}",
+            "brightness_command_topic": command_topic,
+            "brightness_command_template": r#"{"command": "max_brightness", "value": {{ value }}}"#,
+            "brightness_scale": 100,
+        })));
+        entities.push(("binary_sensor", "presence", json!({
+            "name": "Presence",
+            "state_topic": format!("{}/presence", topic),
+            "device_class": "occupancy",
+            "payload_on": "true",
+            "payload_off": "false",
+        })));
+    }
+
+    entities.into_iter()
+        .map(|(component, object_id, mut payload)| {
+            payload["unique_id"] = Value::from(format!("{}_{}", device_id, object_id));
+            payload["device"] = device.clone();
+            payload["availability_topic"] = Value::from(availability_topic(topic));
+            payload["payload_available"] = Value::from(AVAILABILITY_ONLINE);
+            payload["payload_not_available"] = Value::from(AVAILABILITY_OFFLINE);
+            (format!("{}/{}/{}/{}/config", DISCOVERY_PREFIX, component, device_id, object_id), payload.to_string())
+        })
+        .collect()
+}
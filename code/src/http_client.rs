@@ -0,0 +1,37 @@
+//! Minimal HTTP(S) client for outgoing notifications and uploads
+
+use anyhow::{bail, Result};
+use embedded_svc::http::client::Client;
+use embedded_svc::io::Write;
+use esp_idf_svc::http::client::{Configuration, EspHttpConnection};
+
+/// POSTs a JSON body; returns the HTTP status code, fails on non-success status codes
+pub fn post_json(url: &str, body: &str) -> Result<u16> {
+    post(url, "application/json", &[], body)
+}
+
+/// POSTs a body with the content type and additional headers; returns the HTTP status code, fails on non-success
+/// status codes
+pub fn post(url: &str, content_type: &str, headers: &[(&str, &str)], body: &str) -> Result<u16> {
+    let connection = EspHttpConnection::new(&Configuration {
+        crt_bundle_attach: Some(esp_idf_svc::sys::esp_crt_bundle_attach),
+        ..Default::default()
+    })?;
+    let mut client = Client::wrap(connection);
+
+    let content_length = body.len().to_string();
+    let mut all_headers = vec![
+        ("content-type", content_type),
+        ("content-length", content_length.as_str()),
+    ];
+    all_headers.extend_from_slice(headers);
+    let mut request = client.post(url, &all_headers)?;
+    request.write_all(body.as_bytes())?;
+    request.flush()?;
+    let response = request.submit()?;
+    let status = response.status();
+    if !(200..300).contains(&status) {
+        bail!("POST {} failed with HTTP status {}", url, status);
+    }
+    Ok(status)
+}
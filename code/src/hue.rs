@@ -0,0 +1,239 @@
+//! Philips Hue bridge emulation (opt-in)
+//!
+//! Announces the light via SSDP as a minimal Hue bridge with one dimmable light, so that voice assistants with local
+//! Hue support (e.g. Amazon Echo devices) can switch and dim it - no cloud account on the device:
+//! - `GET /description.xml`: UPnP description of the bridge
+//! - `POST /api`: pairing - within [PAIRING_WINDOW] after `hue pair` (the link button of a real bridge) a client gets
+//!   the user name in `hue_user`
+//! - `GET /api/<user>/lights[/1]`: the light - on while it is lit, the brightness is `max_brightness_pct`
+//! - `PUT /api/<user>/lights/1/state`: `on` = forced on, `off` = automatic (the light goes off, when nobody is
+//!   around); `bri` sets `max_brightness_pct`
+//!
+//! The endpoints are served by the REST API (port 80 - as with a real bridge). The light is accessible with the paired
+//! user name only - the Hue API has no other authentication.
+
+use std::net::{Ipv4Addr, UdpSocket};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use embedded_svc::http::Headers;
+use embedded_svc::io::Write;
+use esp_idf_svc::http::server::EspHttpServer;
+use esp_idf_svc::http::Method;
+use esp_idf_svc::sys;
+use serde_json::{json, Value};
+
+use crate::config::{Config, Param};
+use crate::console::Command;
+use crate::mode::Mode;
+use crate::ramp::Phase;
+use crate::rest_api::{apply, read_body, respond, ApiRequest};
+use crate::status::SharedStatus;
+
+const SSDP_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+const SSDP_PORT: u16 = 1900;
+/// Id of the one light
+const LIGHT_ID: &str = "1";
+const HUE_MAX_BRI: u32 = 254;
+/// Time the pairing stays open after `hue pair`
+pub const PAIRING_WINDOW: Duration = Duration::from_secs(30);
+
+/// End of the pairing window
+static PAIRING_UNTIL: Mutex<Option<Instant>> = Mutex::new(None);
+
+pub fn register(server: &mut EspHttpServer<'static>, sender: Sender<ApiRequest>, status: SharedStatus,
+                config: Arc<Mutex<Config>>) -> Result<()> {
+    let mac = mac_address();
+
+    let description_mac = mac.clone();
+    server.fn_handler("/description.xml", Method::Get, move |request| {
+        let host = request.host().and_then(|host| host.split(':').next()).unwrap_or_default().to_string();
+        let mut response = request.into_response(200, None, &[("Content-Type", "text/xml")])?;
+        response.write_all(description_xml(&host, &description_mac).as_bytes())?;
+        Ok::<(), anyhow::Error>(())
+    })?;
+
+    let pairing_config = config.clone();
+    server.fn_handler("/api", Method::Post, move |request| {
+        let user = pairing_config.lock().unwrap().hue_user.clone();
+        let body = if !user.is_empty() && pairing_open(Instant::now()) {
+            json!([{"success": {"username": user}}])
+        } else {
+            json!([{"error": {"type": 101, "address": "", "description": "link button not pressed"}}])
+        };
+        respond(request, 200, &body.to_string())
+    })?;
+
+    let light_mac = mac.clone();
+    let light_config = config.clone();
+    server.fn_handler("/api/*", Method::Get, move |request| {
+        let path = request.uri().split('?').next().unwrap_or_default().to_string();
+        if !paired(&light_config, &path) {
+            return respond(request, 200, &unauthorized(&path).to_string());
+        }
+        let light = light_json(&light_config.lock().unwrap(), &status, &light_mac);
+        let body = match path.trim_end_matches('/').split('/').collect::<Vec<_>>().as_slice() {
+            ["", "api", _, "lights"] => json!({LIGHT_ID: light}),
+            ["", "api", _, "lights", LIGHT_ID] => light,
+            ["", "api", _] => json!({"lights": {LIGHT_ID: light}}),
+            _ => json!([{"error": {"type": 3, "address": path, "description": "resource not available"}}])
+        };
+        respond(request, 200, &body.to_string())
+    })?;
+
+    server.fn_handler("/api/*", Method::Put, move |mut request| {
+        let path = request.uri().to_string();
+        if !paired(&config, &path) {
+            return respond(request, 200, &unauthorized(&path).to_string());
+        }
+        if !path.ends_with(&format!("/lights/{}/state", LIGHT_ID)) {
+            return respond(request, 200, &json!([{"error": {"type": 3, "address": path}}]).to_string());
+        }
+        let state = match read_body(&mut request).and_then(|body| Ok(serde_json::from_slice::<Value>(&body)?)) {
+            Ok(state) => state,
+            Err(e) => return respond(request, 400, &json!([{"error": {"type": 2, "description": e.to_string()}}]).to_string())
+        };
+        let mut results = vec![];
+        // the brightness first - it applies to a light switched on with the same request
+        if let Some(bri) = state.get("bri").and_then(Value::as_u64) {
+            let percent = (bri.min(HUE_MAX_BRI as u64) as u32 * 100 + HUE_MAX_BRI / 2) / HUE_MAX_BRI;
+            let command = Command::Set(Param::MaxBrightnessPct, percent.clamp(1, 100).to_string());
+            results.push(state_result("bri", Value::from(bri), apply(&sender, command)));
+        }
+        if let Some(on) = state.get("on").and_then(Value::as_bool) {
+            let mode = if on { Mode::ForcedOn } else { Mode::Auto };
+            results.push(state_result("on", Value::from(on), apply(&sender, Command::Mode(Some(mode)))));
+        }
+        respond(request, 200, &Value::Array(results).to_string())
+    })?;
+
+    thread::Builder::new()
+        .name("ssdp".to_string())
+        .stack_size(6144)
+        .spawn(move || {
+            if let Err(e) = answer_searches(&mac) {
+                log::warn!("SSDP responder stopped: {}", e);
+            }
+        })?;
+    Ok(())
+}
+
+/// Opens the pairing of clients for [PAIRING_WINDOW]
+pub fn open_pairing(now: Instant) {
+    *PAIRING_UNTIL.lock().unwrap() = Some(now + PAIRING_WINDOW);
+}
+
+fn pairing_open(now: Instant) -> bool {
+    PAIRING_UNTIL.lock().unwrap().is_some_and(|until| now < until)
+}
+
+/// A random user name for the clients (128 bits as hex)
+pub fn new_user_name() -> String {
+    // Safety: no preconditions - true random numbers with the radio enabled
+    (0..4).map(|_| format!("{:08x}", unsafe { sys::esp_random() })).collect()
+}
+
+/// Whether the path `/api/<user>/...` has the user name of the paired clients
+fn paired(config: &Mutex<Config>, path: &str) -> bool {
+    let config = config.lock().unwrap();
+    let user = path.split('/').nth(2).unwrap_or_default();
+    !config.hue_user.is_empty() && user == config.hue_user
+}
+
+fn unauthorized(path: &str) -> Value {
+    json!([{"error": {"type": 1, "address": path, "description": "unauthorized user"}}])
+}
+
+fn light_json(config: &Config, status: &SharedStatus, mac: &str) -> Value {
+    let on = status.get().is_some_and(|snapshot| snapshot.phase != Phase::Off);
+    let bri = (config.max_brightness_pct * HUE_MAX_BRI + 50) / 100;
+    json!({
+        "type": "Dimmable light",
+        "manufacturername": "Philips",
+        "modelid": "LWB010",
+        "name": config.hostname,
+        "uniqueid": format!("{}-{}", mac, LIGHT_ID),
+        "swversion": env!("CARGO_PKG_VERSION"),
+        "state": {"on": on, "bri": bri.clamp(1, HUE_MAX_BRI), "alert": "none", "reachable": true},
+    })
+}
+
+fn state_result(attribute: &str, value: Value, result: Result<(), String>) -> Value {
+    let address = format!("/lights/{}/state/{}", LIGHT_ID, attribute);
+    match result {
+        Ok(()) => json!({"success": {address: value}}),
+        Err(e) => json!({"error": {"type": 901, "address": address, "description": e}})
+    }
+}
+
+fn description_xml(host: &str, mac: &str) -> String {
+    format!(r#"<?xml version="1.0" encoding="UTF-8" ?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+<specVersion><major>1</major><minor>0</minor></specVersion>
+<URLBase>http://{host}:80/</URLBase>
+<device>
+<deviceType>urn:schemas-upnp-org:device:Basic:1</deviceType>
+<friendlyName>Sensor Light ({host})</friendlyName>
+<manufacturer>Royal Philips Electronics</manufacturer>
+<manufacturerURL>http://www.philips.com</manufacturerURL>
+<modelDescription>Philips hue Personal Wireless Lighting</modelDescription>
+<modelName>Philips hue bridge 2012</modelName>
+<modelNumber>929000226503</modelNumber>
+<serialNumber>{mac}</serialNumber>
+<UDN>uuid:2f402f80-da50-11e1-9b23-{mac}</UDN>
+<presentationURL>index.html</presentationURL>
+</device>
+</root>
+"#)
+}
+
+/// Answers SSDP searches for a Hue bridge with the location of the description
+fn answer_searches(mac: &str) -> Result<()> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, SSDP_PORT))?;
+    socket.join_multicast_v4(&SSDP_ADDR, &Ipv4Addr::UNSPECIFIED)?;
+    let mut buf = [0_u8; 1024];
+    loop {
+        let (len, from) = socket.recv_from(&mut buf)?;
+        let message = String::from_utf8_lossy(&buf[..len]).to_ascii_lowercase();
+        let wanted = message.starts_with("m-search")
+            && ["ssdp:all", "upnp:rootdevice", "device:basic:1"].iter().any(|st| message.contains(st));
+        if !wanted {
+            continue;
+        }
+        // the connected socket tells the own address on the requester's network
+        let reply = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        reply.connect(from)?;
+        let ip = reply.local_addr()?.ip();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\n\
+             EXT:\r\n\
+             CACHE-CONTROL: max-age=100\r\n\
+             LOCATION: http://{ip}:80/description.xml\r\n\
+             SERVER: FreeRTOS/6.0.5, UPnP/1.0, IpBridge/1.17.0\r\n\
+             hue-bridgeid: {bridge_id}\r\n\
+             ST: urn:schemas-upnp-org:device:basic:1\r\n\
+             USN: uuid:2f402f80-da50-11e1-9b23-{mac}::upnp:rootdevice\r\n\r\n",
+            bridge_id = bridge_id(mac));
+        if let Err(e) = reply.send(response.as_bytes()) {
+            log::warn!("SSDP response to {} failed: {}", from, e);
+        }
+    }
+}
+
+/// MAC address as 12 hex digits
+fn mac_address() -> String {
+    let mut mac = [0_u8; 6];
+    // Safety: the buffer has the size of a MAC address
+    unsafe {
+        sys::esp_efuse_mac_get_default(mac.as_mut_ptr());
+    }
+    mac.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Bridge id as derived from the MAC address by real bridges
+fn bridge_id(mac: &str) -> String {
+    format!("{}FFFE{}", &mac[..6], &mac[6..]).to_uppercase()
+}
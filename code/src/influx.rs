@@ -0,0 +1,75 @@
+//! InfluxDB metrics push (opt-in)
+//!
+//! Periodically POSTs the current metrics in InfluxDB line protocol to `influx_url`, e.g.
+//! `sensor_light,device=<hostname> lux=12.5,duty_pct=40.0,phase="On",presence=true` - a lightweight alternative to
+//! a scraper. The points carry no timestamp (the server assigns it), as the wall clock might not be set.
+//! With `local_only_analytics` the occupancy related fields (phase, presence, duty) are left out (see [ExportedStatus]).
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::event::ExportedStatus;
+use crate::http_client::post;
+
+const MEASUREMENT: &str = "sensor_light";
+
+pub struct InfluxPush {
+    last_push: Option<Instant>,
+}
+
+impl InfluxPush {
+    pub fn new() -> Self {
+        Self { last_push: None }
+    }
+
+    /// Pushes the metrics, if enabled and due
+    pub fn update(&mut self, config: &Config, status: &ExportedStatus, now: Instant) {
+        if config.influx_url.is_empty() {
+            return;
+        }
+        let due = self.last_push
+            .map_or(true, |t| now.duration_since(t) >= Duration::from_secs(config.influx_interval_s as u64));
+        if !due {
+            return;
+        }
+        self.last_push = Some(now);
+        let Some(line) = line_protocol(config, status) else {
+            return;
+        };
+        let url = config.influx_url.clone();
+        let token = config.influx_token.clone();
+        // push in the background, so that the light control is not blocked
+        let result = thread::Builder::new()
+            .name("influxdb".to_string())
+            .stack_size(8192)
+            .spawn(move || {
+                let authorization = format!("Token {}", token);
+                let headers = if token.is_empty() { vec![] } else { vec![("authorization", authorization.as_str())] };
+                if let Err(e) = post(&url, "text/plain; charset=utf-8", &headers, &line) {
+                    log::warn!("InfluxDB push failed: {}", e);
+                }
+            });
+        if let Err(e) = result {
+            log::warn!("InfluxDB push not started: {}", e);
+        }
+    }
+}
+
+/// The metrics as a single point; `None` without any field
+fn line_protocol(config: &Config, status: &ExportedStatus) -> Option<String> {
+    let mut fields = vec![];
+    if let Some(lux) = status.lux {
+        fields.push(format!("lux={:.2}", lux));
+    }
+    if let Some(occupancy) = &status.occupancy {
+        fields.push(format!("duty_pct={:.1}", occupancy.duty_pct()));
+        fields.push(format!("phase=\"{:?}\"", occupancy.phase));
+        fields.push(format!("presence={}", occupancy.presence));
+    }
+    if fields.is_empty() {
+        return None;
+    }
+    // the host name needs no escaping as tag value (letters, digits and '-')
+    Some(format!("{},device={} {}", MEASUREMENT, config.hostname, fields.join(",")))
+}
@@ -0,0 +1,79 @@
+//! Presence-to-light reaction latency
+//!
+//! The presence pin ISR timestamps the rising edge of the sensor signal.
+//! The latency is measured from that edge to the first visible duty increase and checked against the configured budget.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use esp_idf_svc::sys::esp_timer_get_time;
+
+use crate::config::Config;
+use crate::event::{Event, EventQueue};
+
+/// An edge without a following light-up (e.g. daylight) is discarded after this time
+const PENDING_EDGE_TIMEOUT_MS: u32 = 5000;
+
+/// Timestamp [ms] of the last presence edge, 0 = none
+static PRESENCE_EDGE_MS: AtomicU32 = AtomicU32::new(0);
+
+/// Milliseconds since boot (wrapping) - safe to call from an ISR
+pub fn now_ms() -> u32 {
+    (unsafe { esp_timer_get_time() } / 1000) as u32
+}
+
+/// To be called from the presence pin ISR
+pub fn record_presence_edge() {
+    PRESENCE_EDGE_MS.store(now_ms().max(1), Ordering::Relaxed);
+}
+
+#[derive(Debug)]
+pub struct LatencyMonitor {
+    pending_edge_ms: Option<u32>,
+    last_ms: Option<u32>,
+    max_ms: u32,
+}
+
+impl LatencyMonitor {
+    pub fn new() -> Self {
+        Self {
+            pending_edge_ms: None,
+            last_ms: None,
+            max_ms: 0,
+        }
+    }
+
+    /// Last measured presence-to-light latency
+    pub fn last_ms(&self) -> Option<u32> {
+        self.last_ms
+    }
+
+    /// To be called after the duty was applied
+    pub fn update(&mut self, duty: u32, config: &Config, events: &mut EventQueue) {
+        let now = now_ms();
+        let edge = PRESENCE_EDGE_MS.swap(0, Ordering::Relaxed);
+        // only edges while the light is off are relevant
+        if edge != 0 && self.pending_edge_ms.is_none() && duty == 0 {
+            self.pending_edge_ms = Some(edge);
+        }
+        let Some(edge) = self.pending_edge_ms else {
+            return;
+        };
+        let latency_ms = now.wrapping_sub(edge);
+        if duty == 0 {
+            if latency_ms > PENDING_EDGE_TIMEOUT_MS {
+                self.pending_edge_ms = None;
+            }
+            return;
+        }
+        self.pending_edge_ms = None;
+        self.last_ms = Some(latency_ms);
+        self.max_ms = self.max_ms.max(latency_ms);
+        if config.latency_budget_ms > 0 && latency_ms > config.latency_budget_ms {
+            log::warn!("presence-to-light latency {} ms exceeds the budget of {} ms (max: {} ms)",
+                latency_ms, config.latency_budget_ms, self.max_ms);
+            events.push(Event::LatencyBudgetExceeded(latency_ms));
+        } else {
+            log::info!("presence-to-light latency: {} ms (max: {} ms)", latency_ms, self.max_ms);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! LD2410 report frames over UART (opt-in)
+//!
+//! Besides its presence output (GPIO1) the LD2410 sends a report about ten times a second on its UART (256000 baud,
+//! 8N1): the target state and the distance and energy of the moving and the stationary target.
+//! Only the TX line of the sensor is connected (to GPIO25) - the firmware reads the reports, but doesn't configure the
+//! sensor. The presence decision stays with the GPIO output; the reports are diagnostics (`radar` on the console),
+//! e.g. for tuning the gates of the sensor at the installation site.
+
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use esp_idf_hal::delay::NON_BLOCK;
+use esp_idf_hal::gpio::{AnyIOPin, AnyInputPin};
+use esp_idf_hal::prelude::FromValueType;
+use esp_idf_hal::uart::{config, UartRxDriver, UART1};
+
+const BAUD_RATE: u32 = 256_000;
+/// Receive buffer of the driver - covers a few loop iterations of reports in the engineering mode
+const RX_BUFFER_SIZE: usize = 512;
+/// A report older than that is not shown (sensor not connected or not sending)
+const MAX_REPORT_AGE: Duration = Duration::from_secs(2);
+
+const FRAME_HEADER: [u8; 4] = [0xF4, 0xF3, 0xF2, 0xF1];
+const FRAME_FOOTER: [u8; 4] = [0xF8, 0xF7, 0xF6, 0xF5];
+/// header + length
+const FRAME_PREFIX_LEN: usize = FRAME_HEADER.len() + 2;
+/// The longest payload (engineering mode) has 35 bytes - a longer length is a misread header
+const MAX_PAYLOAD_LEN: usize = 64;
+/// data type (basic / engineering), head, target state, moving target (3), stationary target (3), detection distance (2)
+const MIN_PAYLOAD_LEN: usize = 12;
+const PAYLOAD_HEAD: u8 = 0xAA;
+
+/// A target of the radar
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Target {
+    pub distance_cm: u16,
+    /// signal strength (0..=100)
+    pub energy: u8,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct RadarReport {
+    pub moving: Option<Target>,
+    pub stationary: Option<Target>,
+    pub detection_distance_cm: u16,
+}
+
+impl Display for RadarReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let targets = [("moving", self.moving), ("stationary", self.stationary)];
+        let mut any = false;
+        for (kind, target) in targets {
+            if let Some(target) = target {
+                write!(f, "{}{} target at {} cm (energy {})", if any { ", " } else { "" }, kind, target.distance_cm, target.energy)?;
+                any = true;
+            }
+        }
+        if !any {
+            f.write_str("no target")?;
+        }
+        write!(f, ", detection distance {} cm", self.detection_distance_cm)
+    }
+}
+
+/// Collects the received bytes into report frames
+#[derive(Debug, Default)]
+pub struct FrameParser {
+    buffer: Vec<u8>,
+}
+
+impl FrameParser {
+    /// Returns the last complete report in `bytes` (together with the bytes received before)
+    pub fn push(&mut self, bytes: &[u8]) -> Option<RadarReport> {
+        self.buffer.extend_from_slice(bytes);
+        let mut report = None;
+        loop {
+            // skip to the next header - keeping a partial one at the end
+            match self.buffer.windows(FRAME_HEADER.len()).position(|w| w == FRAME_HEADER) {
+                Some(start) => {
+                    self.buffer.drain(..start);
+                }
+                None => {
+                    let keep = self.buffer.len().min(FRAME_HEADER.len() - 1);
+                    self.buffer.drain(..self.buffer.len() - keep);
+                    return report;
+                }
+            }
+            if self.buffer.len() < FRAME_PREFIX_LEN {
+                return report;
+            }
+            let payload_len = u16::from_le_bytes([self.buffer[4], self.buffer[5]]) as usize;
+            if payload_len > MAX_PAYLOAD_LEN {
+                self.buffer.drain(..FRAME_HEADER.len());
+                continue;
+            }
+            let frame_len = FRAME_PREFIX_LEN + payload_len + FRAME_FOOTER.len();
+            if self.buffer.len() < frame_len {
+                return report;
+            }
+            let frame = self.buffer.drain(..frame_len).collect::<Vec<_>>();
+            if frame[frame_len - FRAME_FOOTER.len()..] == FRAME_FOOTER {
+                report = parse_payload(&frame[FRAME_PREFIX_LEN..frame_len - FRAME_FOOTER.len()]).or(report);
+            }
+        }
+    }
+}
+
+fn parse_payload(payload: &[u8]) -> Option<RadarReport> {
+    if payload.len() < MIN_PAYLOAD_LEN || payload[1] != PAYLOAD_HEAD {
+        return None;
+    }
+    let u16_at = |i: usize| u16::from_le_bytes([payload[i], payload[i + 1]]);
+    let state = payload[2];
+    let target = |present: bool, i: usize| present.then(|| Target { distance_cm: u16_at(i), energy: payload[i + 2] });
+    Some(RadarReport {
+        moving: target(state & 0x01 != 0, 3),
+        stationary: target(state & 0x02 != 0, 6),
+        detection_distance_cm: u16_at(9),
+    })
+}
+
+pub struct Ld2410Uart {
+    uart: UartRxDriver<'static>,
+    parser: FrameParser,
+    latest: Option<(RadarReport, Instant)>,
+}
+
+impl Ld2410Uart {
+    pub fn new(uart: UART1, rx: AnyInputPin) -> Result<Self> {
+        let config = config::Config::new()
+            .baudrate(BAUD_RATE.Hz())
+            .rx_fifo_size(RX_BUFFER_SIZE);
+        let uart = UartRxDriver::new(uart, rx, Option::<AnyIOPin>::None, Option::<AnyIOPin>::None, &config)?;
+        Ok(Self {
+            uart,
+            parser: FrameParser::default(),
+            latest: None,
+        })
+    }
+
+    /// Reads the received bytes (non-blocking)
+    pub fn poll(&mut self, now: Instant) {
+        let mut buf = [0_u8; 64];
+        loop {
+            match self.uart.read(&mut buf, NON_BLOCK) {
+                Ok(n) if n > 0 => if let Some(report) = self.parser.push(&buf[..n]) {
+                    self.latest = Some((report, now));
+                }
+                _ => return
+            }
+        }
+    }
+
+    /// The latest report - `None` if there is no recent one
+    pub fn latest(&self, now: Instant) -> Option<RadarReport> {
+        self.latest
+            .filter(|(_, received)| now.duration_since(*received) < MAX_REPORT_AGE)
+            .map(|(report, _)| report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// basic mode frame: moving target at 120 cm (energy 45), stationary target at 80 cm (energy 30)
+    const FRAME: [u8; 23] = [
+        0xF4, 0xF3, 0xF2, 0xF1, 0x0D, 0x00,
+        0x02, 0xAA, 0x03, 0x78, 0x00, 0x2D, 0x50, 0x00, 0x1E, 0x64, 0x00, 0x55, 0x00,
+        0xF8, 0xF7, 0xF6, 0xF5,
+    ];
+
+    #[test]
+    fn parses_a_frame_split_across_reads() {
+        let mut parser = FrameParser::default();
+        assert_eq!(parser.push(&[0x00, 0x12]), None);
+        assert_eq!(parser.push(&FRAME[..10]), None);
+        let report = parser.push(&FRAME[10..]).unwrap();
+        assert_eq!(report.moving, Some(Target { distance_cm: 120, energy: 45 }));
+        assert_eq!(report.stationary, Some(Target { distance_cm: 80, energy: 30 }));
+        assert_eq!(report.detection_distance_cm, 100);
+    }
+
+    #[test]
+    fn skips_a_corrupt_frame() {
+        let mut corrupt = FRAME;
+        corrupt[20] = 0x00;
+        let mut parser = FrameParser::default();
+        assert_eq!(parser.push(&corrupt), None);
+        assert!(parser.push(&FRAME).is_some());
+    }
+}
@@ -0,0 +1,157 @@
+//! Addressable LED strip (WS2812 / SK6812) via the RMT peripheral
+//!
+//! Alternative LED backend: the whole strip is a single [LedChannel] with uniform brightness -
+//! the duty (from the power stage) becomes the level of every pixel, so the phase logic stays the same.
+//! Per-pixel effects can build on [AddressableStrip::write_pixels]:
+//! - [StripEffect::Chase]: the light fills the strip pixel by pixel along with the power stage (the stairs-light look).
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use esp_idf_hal::gpio::OutputPin;
+use esp_idf_hal::peripheral::Peripheral;
+use esp_idf_hal::rmt::{PinState, Pulse, RmtChannel, TxRmtDriver, VariableLengthSignal};
+use esp_idf_hal::rmt::config::TransmitConfig;
+
+use crate::peripheral::LedChannel;
+
+/// 8 bit per color
+const MAX_DUTY: u32 = 255;
+
+// bit timing (WS2812B - SK6812 is compatible)
+const T0H: Duration = Duration::from_nanos(350);
+const T0L: Duration = Duration::from_nanos(800);
+const T1H: Duration = Duration::from_nanos(700);
+const T1L: Duration = Duration::from_nanos(600);
+
+/// How an addressable strip follows the power stage
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum StripEffect {
+    /// all pixels with the same brightness
+    Uniform,
+    /// pixel by pixel from one end, with full brightness
+    Chase,
+}
+
+impl StripEffect {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            StripEffect::Uniform => 0,
+            StripEffect::Chase => 1,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(StripEffect::Uniform),
+            1 => Some(StripEffect::Chase),
+            _ => None
+        }
+    }
+}
+
+impl Display for StripEffect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StripEffect::Uniform => "uniform",
+            StripEffect::Chase => "chase",
+        })
+    }
+}
+
+impl FromStr for StripEffect {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(StripEffect::Uniform),
+            "chase" => Ok(StripEffect::Chase),
+            _ => Err(anyhow!("unknown strip effect '{}' (expected uniform or chase)", s))
+        }
+    }
+}
+
+pub struct AddressableStrip {
+    tx: TxRmtDriver<'static>,
+    pixels: usize,
+    /// GRBW pixels (SK6812 RGBW) instead of GRB
+    white_channel: bool,
+    /// high + low pulse of a 0 and a 1 bit
+    bit_pulses: [[Pulse; 2]; 2],
+}
+
+impl AddressableStrip {
+    pub fn new<C: RmtChannel>(
+        channel: impl Peripheral<P=C> + 'static,
+        pin: impl Peripheral<P=impl OutputPin> + 'static,
+        pixels: usize,
+        white_channel: bool,
+    ) -> Result<Self> {
+        let tx = TxRmtDriver::new(channel, pin, &TransmitConfig::new().clock_divider(1))?;
+        let ticks_hz = tx.counter_clock()?;
+        let bit_pulses = [
+            [Pulse::new_with_duration(ticks_hz, PinState::High, &T0H)?, Pulse::new_with_duration(ticks_hz, PinState::Low, &T0L)?],
+            [Pulse::new_with_duration(ticks_hz, PinState::High, &T1H)?, Pulse::new_with_duration(ticks_hz, PinState::Low, &T1L)?],
+        ];
+        let mut strip = Self { tx, pixels, white_channel, bit_pulses };
+        strip.set_duty(0)?;
+        Ok(strip)
+    }
+
+    /// Sends the pixel colors `[g, r, b, w]` (in the order of the strip) - `w` is ignored without white channel
+    pub fn write_pixels(&mut self, pixels: &[[u8; 4]]) -> Result<()> {
+        let bytes_per_pixel = if self.white_channel { 4 } else { 3 };
+        let mut signal = VariableLengthSignal::with_capacity(pixels.len() * bytes_per_pixel * 8 * 2);
+        for &byte in pixels.iter().flat_map(|pixel| &pixel[..bytes_per_pixel]) {
+            for bit in (0..8).rev() {
+                signal.push(&self.bit_pulses[((byte >> bit) & 1) as usize])?;
+            }
+        }
+        self.tx.start_blocking(&signal)?;
+        Ok(())
+    }
+
+    /// White pixel of the level: the white channel alone, if there is one
+    fn white_pixel(&self, level: u8) -> [u8; 4] {
+        if self.white_channel { [0, 0, 0, level] } else { [level, level, level, 0] }
+    }
+}
+
+/// Pixel levels of a strip filled to `fill` [0..=1] with `level` - the pixel at the edge partly lit
+fn chase_levels(fill: f32, pixels: usize, level: u8) -> Vec<u8> {
+    let lit = fill.clamp(0.0, 1.0) * pixels as f32;
+    (0..pixels)
+        .map(|i| ((lit - i as f32).clamp(0.0, 1.0) * level as f32).round() as u8)
+        .collect()
+}
+
+impl LedChannel for AddressableStrip {
+    fn max_duty(&self) -> u32 {
+        MAX_DUTY
+    }
+
+    fn set_duty(&mut self, duty: u32) -> Result<()> {
+        let pixel = self.white_pixel(duty.min(MAX_DUTY) as u8);
+        self.write_pixels(&vec![pixel; self.pixels])
+    }
+
+    fn set_fill(&mut self, fill: f32, from_end: bool, duty: u32) -> Result<()> {
+        let mut levels = chase_levels(fill, self.pixels, duty.min(MAX_DUTY) as u8);
+        if from_end {
+            levels.reverse();
+        }
+        let pixels = levels.into_iter().map(|level| self.white_pixel(level)).collect::<Vec<_>>();
+        self.write_pixels(&pixels)
+    }
+
+    fn fade_to(&mut self, duty: u32, _fade_ms: u32) -> Result<()> {
+        self.set_duty(duty)
+    }
+
+    /// The pixels keep their color on their own (as long as they are powered)
+    fn hold(&mut self, _hold: bool) -> Result<()> {
+        Ok(())
+    }
+}
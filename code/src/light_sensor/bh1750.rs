@@ -0,0 +1,51 @@
+//! BH1750 ambient light sensor backend
+
+use anyhow::Result;
+use esp_idf_hal::delay::TickType;
+use esp_idf_hal::i2c::I2cDriver;
+
+use crate::light_sensor::{AmbientLightSensor, LightReading};
+
+/// ADDR pin low / high
+pub const I2C_ADDRESSES: [u8; 2] = [0x23, 0x5C];
+
+const I2C_TIMEOUT_MS: u64 = 50;
+
+const OPCODE_POWER_ON: u8 = 0x01;
+/// continuous measurement with 0.5 lx resolution (120 ms measurement time)
+const OPCODE_CONTINUOUS_HIGH_RES_MODE_2: u8 = 0x11;
+
+/// counts per lux in high resolution mode 2 (1.2 counts/lx in mode 1, doubled resolution in mode 2)
+const COUNTS_PER_LUX: f32 = 2.4;
+
+pub struct Bh1750Sensor {
+    i2c: I2cDriver<'static>,
+    address: u8,
+}
+
+impl Bh1750Sensor {
+    pub fn new(i2c: I2cDriver<'static>, address: u8) -> Result<Self> {
+        let mut sensor = Self { i2c, address };
+        sensor.send(OPCODE_POWER_ON)?;
+        sensor.send(OPCODE_CONTINUOUS_HIGH_RES_MODE_2)?;
+        Ok(sensor)
+    }
+
+    fn send(&mut self, opcode: u8) -> Result<()> {
+        self.i2c.write(self.address, &[opcode], TickType::new_millis(I2C_TIMEOUT_MS).ticks())?;
+        Ok(())
+    }
+}
+
+impl AmbientLightSensor for Bh1750Sensor {
+    fn name(&self) -> &'static str {
+        "BH1750"
+    }
+
+    fn read(&mut self) -> Result<LightReading> {
+        let mut buf = [0_u8; 2];
+        self.i2c.read(self.address, &mut buf, TickType::new_millis(I2C_TIMEOUT_MS).ticks())?;
+        let raw = u16::from_be_bytes(buf);
+        Ok(LightReading { lux: raw as f32 / COUNTS_PER_LUX, raw, white_raw: None })
+    }
+}
@@ -0,0 +1,73 @@
+//! An ambient light sensor together with its I2C bus, so that it can be recovered or hot-plugged at runtime
+
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::light_sensor::AmbientLightSensor;
+use crate::peripheral::{init_ambient_light_sensor, LightSensorBus};
+
+/// Interval of recovery attempts while the ambient light sensor is lost
+const RECOVERY_INTERVAL: Duration = Duration::from_secs(10);
+
+pub struct LightSensorChannel {
+    bus: LightSensorBus,
+    /// `None` while the sensor is not available
+    sensor: Option<Box<dyn AmbientLightSensor>>,
+    last_recovery: Option<Instant>,
+}
+
+impl LightSensorChannel {
+    /// Initializes the sensor on the bus - the channel is created even without a sensor (see [Self::recover])
+    pub fn new(mut bus: LightSensorBus) -> Self {
+        let sensor = init_ambient_light_sensor(&mut bus);
+        Self {
+            bus,
+            sensor,
+            last_recovery: None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.bus.name()
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.sensor.is_some()
+    }
+
+    pub fn sensor_mut(&mut self) -> Option<&mut Box<dyn AmbientLightSensor>> {
+        self.sensor.as_mut()
+    }
+
+    /// Applies the configuration to the sensor (if available)
+    pub fn configure(&mut self, config: &Config) {
+        if let Some(sensor) = self.sensor.as_mut() {
+            if let Err(e) = sensor.configure(config) {
+                log::warn!("{}: ambient light sensor configuration failed: {}", self.bus.name(), e);
+            }
+        }
+    }
+
+    pub fn recovery_due(&self) -> bool {
+        self.last_recovery.map_or(true, |t| t.elapsed() >= RECOVERY_INTERVAL)
+    }
+
+    /// Drops the failed sensor - the recovery is due right away
+    pub fn lose(&mut self) {
+        self.sensor = None;
+        self.last_recovery = None;
+    }
+
+    /// Clears the I2C bus and re-initializes the ambient light sensor; returns whether the sensor is available again
+    pub fn recover(&mut self, config: &Config) -> bool {
+        self.last_recovery = Some(Instant::now());
+        // drop the sensor and with it the I2C driver
+        self.sensor = None;
+        if let Err(e) = self.bus.clear() {
+            log::warn!("{}: I2C bus clear failed: {}", self.bus.name(), e);
+        }
+        self.sensor = init_ambient_light_sensor(&mut self.bus);
+        self.configure(config);
+        self.sensor.is_some()
+    }
+}
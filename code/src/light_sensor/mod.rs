@@ -0,0 +1,286 @@
+//! Ambient light sensor abstraction and the supported sensor backends
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use esp_idf_hal::i2c::I2cDriver;
+
+use crate::config::Config;
+
+#[cfg(feature = "bh1750")]
+pub mod bh1750;
+pub mod channel;
+#[cfg(feature = "veml7700")]
+pub mod timeout_i2c;
+#[cfg(feature = "tsl2591")]
+pub mod tsl2591;
+#[cfg(feature = "veml7700")]
+pub mod veml7700;
+
+
+/// A single ambient light measurement
+#[derive(Copy, Clone, Debug)]
+pub struct LightReading {
+    pub lux: f32,
+    /// raw sensor counts of the light channel (65535 = saturated)
+    pub raw: u16,
+    /// raw white channel counts (if the sensor has one)
+    pub white_raw: Option<u16>,
+}
+
+impl LightReading {
+    pub fn is_saturated(&self) -> bool {
+        self.raw == u16::MAX
+    }
+}
+
+impl Display for LightReading {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.2} lux (raw: {}", self.lux, self.raw)?;
+        if let Some(white_raw) = self.white_raw {
+            write!(f, ", white: {}", white_raw)?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Combination of the readings of multiple ambient light sensors
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LuxCombine {
+    /// the darkest reading wins (e.g. one sensor is hit by a streetlight)
+    Min,
+    Average,
+}
+
+impl LuxCombine {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            LuxCombine::Min => 0,
+            LuxCombine::Average => 1,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(LuxCombine::Min),
+            1 => Some(LuxCombine::Average),
+            _ => None
+        }
+    }
+}
+
+impl Display for LuxCombine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LuxCombine::Min => "min",
+            LuxCombine::Average => "average",
+        })
+    }
+}
+
+impl FromStr for LuxCombine {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "min" => Ok(LuxCombine::Min),
+            "average" => Ok(LuxCombine::Average),
+            _ => Err(anyhow!("unknown lux combination '{}' (expected min or average)", s))
+        }
+    }
+}
+
+/// Combines the readings of multiple sensors; `None` without readings.
+/// An average reading keeps the highest raw count, so that a saturation stays visible.
+pub fn combine_readings(readings: &[LightReading], combine: LuxCombine) -> Option<LightReading> {
+    match combine {
+        LuxCombine::Min => readings.iter().copied().min_by(|a, b| a.lux.total_cmp(&b.lux)),
+        LuxCombine::Average => {
+            if readings.len() < 2 {
+                return readings.first().copied();
+            }
+            Some(LightReading {
+                lux: readings.iter().map(|r| r.lux).sum::<f32>() / readings.len() as f32,
+                raw: readings.iter().map(|r| r.raw).max()?,
+                white_raw: None,
+            })
+        }
+    }
+}
+
+/// Handling of saturated sensor readings (e.g. direct sunlight on the sensor)
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SaturationPolicy {
+    /// use the reading as is - it's the max. measurable lux of the current sensor setting
+    Clamp,
+    /// discard the reading - the lux level keeps the last valid value
+    Invalid,
+    /// discard the reading and reduce the sensor sensitivity (if the sensor supports it)
+    AutoGain,
+}
+
+impl SaturationPolicy {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            SaturationPolicy::Clamp => 0,
+            SaturationPolicy::Invalid => 1,
+            SaturationPolicy::AutoGain => 2,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(SaturationPolicy::Clamp),
+            1 => Some(SaturationPolicy::Invalid),
+            2 => Some(SaturationPolicy::AutoGain),
+            _ => None
+        }
+    }
+}
+
+impl Display for SaturationPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SaturationPolicy::Clamp => "clamp",
+            SaturationPolicy::Invalid => "invalid",
+            SaturationPolicy::AutoGain => "auto_gain",
+        })
+    }
+}
+
+impl FromStr for SaturationPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clamp" => Ok(SaturationPolicy::Clamp),
+            "invalid" => Ok(SaturationPolicy::Invalid),
+            "auto_gain" => Ok(SaturationPolicy::AutoGain),
+            _ => Err(anyhow!("unknown saturation policy '{}' (expected clamp, invalid or auto_gain)", s))
+        }
+    }
+}
+
+/// Supported VEML7700 integration times [ms]
+pub const VEML_INTEGRATION_TIMES_MS: [u32; 6] = [25, 50, 100, 200, 400, 800];
+
+/// VEML7700 gain setting (kept independent of the driver crate, so the configuration compiles without the sensor feature)
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum VemlGain {
+    OneEighth,
+    OneQuarter,
+    One,
+    Two,
+}
+
+impl VemlGain {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            VemlGain::OneEighth => 0,
+            VemlGain::OneQuarter => 1,
+            VemlGain::One => 2,
+            VemlGain::Two => 3,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(VemlGain::OneEighth),
+            1 => Some(VemlGain::OneQuarter),
+            2 => Some(VemlGain::One),
+            3 => Some(VemlGain::Two),
+            _ => None
+        }
+    }
+}
+
+impl Display for VemlGain {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            VemlGain::OneEighth => "1/8",
+            VemlGain::OneQuarter => "1/4",
+            VemlGain::One => "1",
+            VemlGain::Two => "2",
+        })
+    }
+}
+
+impl FromStr for VemlGain {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1/8" => Ok(VemlGain::OneEighth),
+            "1/4" => Ok(VemlGain::OneQuarter),
+            "1" => Ok(VemlGain::One),
+            "2" => Ok(VemlGain::Two),
+            _ => Err(anyhow!("unknown gain '{}' (expected 1/8, 1/4, 1 or 2)", s))
+        }
+    }
+}
+
+pub trait AmbientLightSensor {
+    fn name(&self) -> &'static str;
+
+    /// Applies the sensor specific configuration parameters (if there are any for this sensor)
+    fn configure(&mut self, _config: &Config) -> Result<()> {
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<LightReading>;
+
+    /// Takes an immediate reading (e.g. with a short integration time) right after startup,
+    /// so that we don't have to wait for the regular measurement cadence.
+    fn fast_read(&mut self) -> Result<LightReading> {
+        self.read()
+    }
+}
+
+/// Initializes the first supported ambient light sensor found among the responding I2C addresses
+pub fn detect_ambient_light_sensor(i2c: I2cDriver<'static>, addresses: &[u8]) -> Result<Box<dyn AmbientLightSensor>> {
+    #[cfg(feature = "veml7700")]
+    if addresses.contains(&veml7700::I2C_ADDRESS) {
+        return Ok(Box::new(veml7700::Veml7700Sensor::new(i2c)?));
+    }
+    #[cfg(feature = "tsl2591")]
+    if addresses.contains(&tsl2591::I2C_ADDRESS) {
+        return Ok(Box::new(tsl2591::Tsl2591Sensor::new(i2c)?));
+    }
+    #[cfg(feature = "bh1750")]
+    for address in bh1750::I2C_ADDRESSES {
+        if addresses.contains(&address) {
+            return Ok(Box::new(bh1750::Bh1750Sensor::new(i2c, address)?));
+        }
+    }
+    // minimal build: the light works with the fallback dark window (see `fallback_dark_from`)
+    #[cfg(not(any(feature = "veml7700", feature = "bh1750", feature = "tsl2591")))]
+    let _ = (i2c, addresses);
+    bail!("no supported ambient light sensor found on the I2C bus (expected one of: {})", supported_addresses())
+}
+
+/// The I2C addresses of the supported sensors - probing only these keeps a (re-)detection short, even on a bus without
+/// a sensor, where each probe takes until its timeout
+pub fn supported_i2c_addresses() -> Vec<u8> {
+    #[allow(unused_mut)]
+    let mut addresses = Vec::new();
+    #[cfg(feature = "veml7700")]
+    addresses.push(veml7700::I2C_ADDRESS);
+    #[cfg(feature = "tsl2591")]
+    addresses.push(tsl2591::I2C_ADDRESS);
+    #[cfg(feature = "bh1750")]
+    addresses.extend(bh1750::I2C_ADDRESSES);
+    addresses
+}
+
+fn supported_addresses() -> String {
+    #[allow(unused_mut)]
+    let mut names: Vec<String> = Vec::new();
+    #[cfg(feature = "veml7700")]
+    names.push(format!("VEML7700 at {:#04x}", veml7700::I2C_ADDRESS));
+    #[cfg(feature = "tsl2591")]
+    names.push(format!("TSL2591 at {:#04x}", tsl2591::I2C_ADDRESS));
+    #[cfg(feature = "bh1750")]
+    names.push(format!("BH1750 at {:#04x}/{:#04x}", bh1750::I2C_ADDRESSES[0], bh1750::I2C_ADDRESSES[1]));
+    names.join(", ")
+}
@@ -0,0 +1,32 @@
+//! I2C bus access with a bounded time per transaction
+//!
+//! The `embedded-hal` implementation of [I2cDriver] waits without limit for a transaction to complete,
+//! so a wedged bus would stall the main loop - and a dimming ramp with it.
+//! This wrapper gives every transaction a timeout instead. A timed out transaction fails with an error
+//! and is handled by the regular sensor recovery (see [crate::light_sensor::channel]).
+
+use embedded_hal::i2c::{ErrorType, I2c, Operation};
+use esp_idf_hal::delay::TickType;
+use esp_idf_hal::i2c::{I2cDriver, I2cError};
+
+pub struct TimeoutI2c {
+    driver: I2cDriver<'static>,
+    timeout_ms: u64,
+}
+
+impl TimeoutI2c {
+    pub fn new(driver: I2cDriver<'static>, timeout_ms: u64) -> Self {
+        Self { driver, timeout_ms }
+    }
+}
+
+impl ErrorType for TimeoutI2c {
+    type Error = I2cError;
+}
+
+impl I2c for TimeoutI2c {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        self.driver.transaction(address, operations, TickType::new_millis(self.timeout_ms).ticks())
+            .map_err(I2cError::other)
+    }
+}
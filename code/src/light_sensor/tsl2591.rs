@@ -0,0 +1,119 @@
+//! TSL2591 high dynamic range ambient light sensor backend
+//!
+//! Uses an automatic gain control, so that we get a good resolution near zero lux
+//! without saturating in daylight.
+
+use anyhow::{bail, Result};
+use esp_idf_hal::delay::TickType;
+use esp_idf_hal::i2c::I2cDriver;
+
+use crate::light_sensor::{AmbientLightSensor, LightReading};
+
+pub const I2C_ADDRESS: u8 = 0x29;
+
+const I2C_TIMEOUT_MS: u64 = 50;
+
+const COMMAND_BIT: u8 = 0xA0;
+const REGISTER_ENABLE: u8 = 0x00;
+const REGISTER_CONTROL: u8 = 0x01;
+const REGISTER_ID: u8 = 0x12;
+const REGISTER_C0DATAL: u8 = 0x14;
+
+const DEVICE_ID: u8 = 0x50;
+
+/// power on + ALS enable
+const ENABLE_POWER_ON_ALS: u8 = 0x03;
+/// 200 ms integration time (ATIME = 0b001)
+const INTEGRATION_TIME_BITS: u8 = 0x01;
+const INTEGRATION_TIME_MS: f32 = 200.0;
+const MAX_COUNT: u16 = 65535;
+
+/// lux coefficient from the vendor's application notes
+const LUX_DF: f32 = 408.0;
+
+/// gain register bits (AGAIN) and the corresponding gain factor
+const GAINS: [(u8, f32); 4] = [(0x00, 1.0), (0x10, 25.0), (0x20, 428.0), (0x30, 9876.0)];
+
+/// channel 0 count ratios of the max count, which trigger a gain change
+const GAIN_DOWN_RATIO: f32 = 0.9;
+const GAIN_UP_RATIO: f32 = 0.01;
+
+pub struct Tsl2591Sensor {
+    i2c: I2cDriver<'static>,
+    gain_index: usize,
+}
+
+impl Tsl2591Sensor {
+    pub fn new(i2c: I2cDriver<'static>) -> Result<Self> {
+        let mut sensor = Self {
+            i2c,
+            gain_index: 2,
+        };
+        let id = sensor.read_register(REGISTER_ID)?;
+        if id != DEVICE_ID {
+            bail!("unexpected TSL2591 device id: {:#04x}", id);
+        }
+        sensor.write_register(REGISTER_ENABLE, ENABLE_POWER_ON_ALS)?;
+        sensor.apply_gain()?;
+        Ok(sensor)
+    }
+
+    fn apply_gain(&mut self) -> Result<()> {
+        self.write_register(REGISTER_CONTROL, GAINS[self.gain_index].0 | INTEGRATION_TIME_BITS)
+    }
+
+    fn read_register(&mut self, register: u8) -> Result<u8> {
+        let mut buf = [0_u8; 1];
+        self.i2c.write_read(I2C_ADDRESS, &[COMMAND_BIT | register], &mut buf, TickType::new_millis(I2C_TIMEOUT_MS).ticks())?;
+        Ok(buf[0])
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> Result<()> {
+        self.i2c.write(I2C_ADDRESS, &[COMMAND_BIT | register, value], TickType::new_millis(I2C_TIMEOUT_MS).ticks())?;
+        Ok(())
+    }
+
+    /// (full spectrum, infrared) counts
+    fn read_channels(&mut self) -> Result<(u16, u16)> {
+        let mut buf = [0_u8; 4];
+        self.i2c.write_read(I2C_ADDRESS, &[COMMAND_BIT | REGISTER_C0DATAL], &mut buf, TickType::new_millis(I2C_TIMEOUT_MS).ticks())?;
+        Ok((u16::from_le_bytes([buf[0], buf[1]]), u16::from_le_bytes([buf[2], buf[3]])))
+    }
+
+    // adjusts the gain for the next measurement
+    fn adjust_gain(&mut self, ch0: u16) -> Result<()> {
+        let ratio = ch0 as f32 / MAX_COUNT as f32;
+        let new_gain_index = if ratio > GAIN_DOWN_RATIO && self.gain_index > 0 {
+            self.gain_index - 1
+        } else if ratio < GAIN_UP_RATIO && self.gain_index < GAINS.len() - 1 {
+            self.gain_index + 1
+        } else {
+            self.gain_index
+        };
+        if new_gain_index != self.gain_index {
+            self.gain_index = new_gain_index;
+            self.apply_gain()?;
+        }
+        Ok(())
+    }
+}
+
+impl AmbientLightSensor for Tsl2591Sensor {
+    fn name(&self) -> &'static str {
+        "TSL2591"
+    }
+
+    fn read(&mut self) -> Result<LightReading> {
+        let (ch0, ch1) = self.read_channels()?;
+        let gain = GAINS[self.gain_index].1;
+        let counts_per_lux = INTEGRATION_TIME_MS * gain / LUX_DF;
+        let lux = if ch0 == 0 {
+            0.0
+        } else {
+            let (ch0, ch1) = (ch0 as f32, ch1 as f32);
+            ((ch0 - ch1) * (1.0 - ch1 / ch0) / counts_per_lux).max(0.0)
+        };
+        self.adjust_gain(ch0)?;
+        Ok(LightReading { lux, raw: ch0, white_raw: None })
+    }
+}
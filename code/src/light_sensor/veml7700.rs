@@ -0,0 +1,175 @@
+//! VEML7700 ambient light sensor backend
+//!
+//! With the [SaturationPolicy::AutoGain] policy the sensitivity is reduced step by step on saturation
+//! (gain first, then integration time) and raised again up to the configured setting, when the light level drops.
+
+use anyhow::{anyhow, bail, Result};
+use esp_idf_hal::delay::FreeRtos;
+use esp_idf_hal::i2c::I2cDriver;
+use veml7700::{Gain, IntegrationTime, PowerSavingMode, Veml7700};
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::light_sensor::{AmbientLightSensor, LightReading, SaturationPolicy, VEML_INTEGRATION_TIMES_MS};
+use crate::light_sensor::timeout_i2c::TimeoutI2c;
+
+pub const I2C_ADDRESS: u8 = 0x10;
+
+/// per transaction - a register access takes well below 1 ms at 100 kHz
+const I2C_TIMEOUT_MS: u64 = 20;
+
+/// in the order of [VEML_INTEGRATION_TIMES_MS]
+const INTEGRATION_TIMES: [IntegrationTime; 6] = [
+    IntegrationTime::_25ms,
+    IntegrationTime::_50ms,
+    IntegrationTime::_100ms,
+    IntegrationTime::_200ms,
+    IntegrationTime::_400ms,
+    IntegrationTime::_800ms,
+];
+/// in the order of [crate::light_sensor::VemlGain::to_u8]
+const GAINS: [Gain; 4] = [Gain::OneEighth, Gain::OneQuarter, Gain::One, Gain::Two];
+
+/// 100 ms, gain 1
+const DEFAULT_SENSITIVITY: Sensitivity = Sensitivity { integration_time: 2, gain: 2 };
+
+/// shortest integration time with doubled gain for a fast first reading after startup
+const FAST_SENSITIVITY: Sensitivity = Sensitivity { integration_time: 0, gain: 3 };
+/// integration time + margin for the first conversion after reconfiguration
+const FAST_READ_WAIT_MS: u32 = 2 * 25 + 10;
+
+/// raw counts below which a reduced sensitivity is raised again (one step raises the counts by up to factor 4)
+const AUTO_RANGE_UP_RAW: u16 = u16::MAX / 8;
+
+/// Indices into [INTEGRATION_TIMES] and [GAINS]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+struct Sensitivity {
+    integration_time: usize,
+    gain: usize,
+}
+
+impl Sensitivity {
+    fn lower(self) -> Option<Self> {
+        if self.gain > 0 {
+            Some(Sensitivity { gain: self.gain - 1, ..self })
+        } else if self.integration_time > 0 {
+            Some(Sensitivity { integration_time: self.integration_time - 1, ..self })
+        } else {
+            None
+        }
+    }
+
+    /// one step higher, but not beyond `limit`
+    fn higher(self, limit: Sensitivity) -> Option<Self> {
+        if self.integration_time < limit.integration_time {
+            Some(Sensitivity { integration_time: self.integration_time + 1, ..self })
+        } else if self.gain < limit.gain {
+            Some(Sensitivity { gain: self.gain + 1, ..self })
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Veml7700Sensor {
+    device: Veml7700<TimeoutI2c>,
+    configured: Sensitivity,
+    /// lower than the configured sensitivity while auto-ranging after a saturation
+    current: Sensitivity,
+    auto_range: bool,
+}
+
+impl Veml7700Sensor {
+    pub fn new(i2c: I2cDriver<'static>) -> Result<Self> {
+        let mut sensor = Self {
+            device: Veml7700::new(TimeoutI2c::new(i2c, I2C_TIMEOUT_MS)),
+            configured: DEFAULT_SENSITIVITY,
+            current: DEFAULT_SENSITIVITY,
+            auto_range: false,
+        };
+        sensor.set_sensitivity(DEFAULT_SENSITIVITY)?;
+        sensor.device.enable().map_err(Error::from)?;
+        Ok(sensor)
+    }
+
+    fn set_sensitivity(&mut self, sensitivity: Sensitivity) -> Result<()> {
+        self.device.set_integration_time(INTEGRATION_TIMES[sensitivity.integration_time]).map_err(Error::from)?;
+        self.device.set_gain(GAINS[sensitivity.gain]).map_err(Error::from)?;
+        self.current = sensitivity;
+        Ok(())
+    }
+
+    /// `psm`: 0 = off, 1..=4 = power saving mode 1..4
+    fn set_power_saving_mode(&mut self, psm: u32) -> Result<()> {
+        let mode = match psm {
+            0 => None,
+            1 => Some(PowerSavingMode::One),
+            2 => Some(PowerSavingMode::Two),
+            3 => Some(PowerSavingMode::Three),
+            4 => Some(PowerSavingMode::Four),
+            _ => bail!("invalid power saving mode {}", psm)
+        };
+        match mode {
+            Some(mode) => self.device.enable_power_saving(mode).map_err(Error::from)?,
+            None => self.device.disable_power_saving().map_err(Error::from)?
+        }
+        Ok(())
+    }
+
+    fn read_channels(&mut self) -> Result<LightReading> {
+        let lux = self.device.read_lux().map_err(Error::from)?;
+        let raw = self.device.read_raw().map_err(Error::from)?;
+        let white_raw = self.device.read_white().map_err(Error::from)?;
+        Ok(LightReading { lux, raw, white_raw: Some(white_raw) })
+    }
+
+    // adjusts the sensitivity for the next measurement
+    fn auto_range(&mut self, reading: &LightReading) -> Result<()> {
+        let next = if reading.is_saturated() {
+            self.current.lower()
+        } else if reading.raw < AUTO_RANGE_UP_RAW {
+            self.current.higher(self.configured)
+        } else {
+            None
+        };
+        if let Some(sensitivity) = next {
+            self.set_sensitivity(sensitivity)?;
+            log::info!("VEML7700 auto-range: integration time {} ms, gain index {}",
+                VEML_INTEGRATION_TIMES_MS[sensitivity.integration_time], sensitivity.gain);
+        }
+        Ok(())
+    }
+}
+
+impl AmbientLightSensor for Veml7700Sensor {
+    fn name(&self) -> &'static str {
+        "VEML7700"
+    }
+
+    fn configure(&mut self, config: &Config) -> Result<()> {
+        let integration_time = VEML_INTEGRATION_TIMES_MS.iter()
+            .position(|&it_ms| it_ms == config.veml_it_ms)
+            .ok_or_else(|| anyhow!("invalid integration time {} ms", config.veml_it_ms))?;
+        self.configured = Sensitivity { integration_time, gain: config.veml_gain.to_u8() as usize };
+        self.auto_range = config.saturation_policy == SaturationPolicy::AutoGain;
+        self.set_sensitivity(self.configured)?;
+        self.set_power_saving_mode(config.veml_psm)
+    }
+
+    fn read(&mut self) -> Result<LightReading> {
+        let reading = self.read_channels()?;
+        if self.auto_range {
+            self.auto_range(&reading)?;
+        }
+        Ok(reading)
+    }
+
+    fn fast_read(&mut self) -> Result<LightReading> {
+        let sensitivity = self.current;
+        self.set_sensitivity(FAST_SENSITIVITY)?;
+        FreeRtos::delay_ms(FAST_READ_WAIT_MS);
+        let reading = self.read_channels();
+        self.set_sensitivity(sensitivity)?;
+        reading
+    }
+}
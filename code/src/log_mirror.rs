@@ -0,0 +1,40 @@
+//! Logger with mirrors of the log output for the remote log features
+//!
+//! Wraps [EspLogger] - every log line is also handed over to the enabled mirrors ([crate::syslog], [crate::tcp_console]).
+
+use esp_idf_svc::log::EspLogger;
+use log::{Log, Metadata, Record};
+
+static LOGGER: MirroredLogger = MirroredLogger {
+    esp: EspLogger::new(),
+};
+
+struct MirroredLogger {
+    esp: EspLogger,
+}
+
+impl Log for MirroredLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.esp.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.esp.log(record);
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        #[cfg(feature = "syslog")]
+        crate::syslog::mirror(record);
+        #[cfg(feature = "tcp-console")]
+        crate::tcp_console::mirror(record);
+    }
+
+    fn flush(&self) {
+        self.esp.flush();
+    }
+}
+
+/// Replaces [EspLogger::initialize_default]
+pub fn initialize() {
+    ::log::set_logger(&LOGGER).map(|()| LOGGER.esp.initialize()).unwrap();
+}
@@ -0,0 +1,239 @@
+//! Smoothing of ambient light sensor readings
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+
+/// Share of samples dropped at each end of the sorted window by [LuxFilterKind::TrimmedMean]
+const TRIMMED_MEAN_CUT_RATIO: f32 = 0.2;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LuxFilterKind {
+    Median,
+    /// exponential moving average with alpha = 2 / (window + 1)
+    ExponentialMovingAverage,
+    /// mean of the window after dropping the lowest and highest values
+    TrimmedMean,
+    /// median with sample weights decaying by age (half-life), so that older samples lose influence
+    TimeWeightedMedian,
+}
+
+impl LuxFilterKind {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            LuxFilterKind::Median => 0,
+            LuxFilterKind::ExponentialMovingAverage => 1,
+            LuxFilterKind::TrimmedMean => 2,
+            LuxFilterKind::TimeWeightedMedian => 3,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(LuxFilterKind::Median),
+            1 => Some(LuxFilterKind::ExponentialMovingAverage),
+            2 => Some(LuxFilterKind::TrimmedMean),
+            3 => Some(LuxFilterKind::TimeWeightedMedian),
+            _ => None
+        }
+    }
+}
+
+impl Display for LuxFilterKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LuxFilterKind::Median => "median",
+            LuxFilterKind::ExponentialMovingAverage => "ema",
+            LuxFilterKind::TrimmedMean => "trimmed_mean",
+            LuxFilterKind::TimeWeightedMedian => "weighted_median",
+        })
+    }
+}
+
+impl FromStr for LuxFilterKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "median" => Ok(LuxFilterKind::Median),
+            "ema" => Ok(LuxFilterKind::ExponentialMovingAverage),
+            "trimmed_mean" => Ok(LuxFilterKind::TrimmedMean),
+            "weighted_median" => Ok(LuxFilterKind::TimeWeightedMedian),
+            _ => Err(anyhow!("unknown lux filter '{}' (expected median, ema, trimmed_mean or weighted_median)", s))
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Sample {
+    lux: f32,
+    time: Instant,
+}
+
+#[derive(Debug)]
+pub struct LuxFilter {
+    kind: LuxFilterKind,
+    buffer: AllocRingBuffer<Sample>,
+    ema: Option<f32>,
+    /// sample weight half-life of [LuxFilterKind::TimeWeightedMedian]
+    half_life: Duration,
+}
+
+impl LuxFilter {
+    pub fn new(kind: LuxFilterKind, window: usize, half_life: Duration) -> Self {
+        Self {
+            kind,
+            buffer: AllocRingBuffer::new(window.max(1)),
+            ema: None,
+            half_life,
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    pub fn set_kind(&mut self, kind: LuxFilterKind) {
+        self.kind = kind;
+    }
+
+    pub fn set_half_life(&mut self, half_life: Duration) {
+        self.half_life = half_life;
+    }
+
+    /// Changes the window length, keeping the most recent samples
+    pub fn set_window(&mut self, window: usize) {
+        let mut buffer = AllocRingBuffer::new(window.max(1));
+        buffer.extend(self.buffer.iter().copied());
+        self.buffer = buffer;
+    }
+
+    pub fn push(&mut self, lux: f32, now: Instant) {
+        self.buffer.push(Sample { lux, time: now });
+        let alpha = 2.0 / (self.window() as f32 + 1.0);
+        self.ema = Some(match self.ema {
+            Some(ema) => ema + alpha * (lux - ema),
+            None => lux
+        });
+    }
+
+    /// Filtered lux level; `None` as long as there are no samples
+    pub fn level(&self) -> Option<f32> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        match self.kind {
+            LuxFilterKind::Median => {
+                let sorted = self.sorted();
+                Some(sorted[sorted.len() / 2])
+            }
+            LuxFilterKind::ExponentialMovingAverage => self.ema,
+            LuxFilterKind::TrimmedMean => {
+                let sorted = self.sorted();
+                let cut = (sorted.len() as f32 * TRIMMED_MEAN_CUT_RATIO) as usize;
+                let kept = &sorted[cut..sorted.len() - cut];
+                Some(kept.iter().sum::<f32>() / kept.len() as f32)
+            }
+            LuxFilterKind::TimeWeightedMedian => self.time_weighted_median(),
+        }
+    }
+
+    fn sorted(&self) -> Vec<f32> {
+        self.buffer.iter()
+            .map(|s| s.lux)
+            .sorted_by(|a, b| a.total_cmp(b))
+            .collect_vec()
+    }
+
+    // weighted median with weights halving every half-life of sample age (relative to the newest sample)
+    fn time_weighted_median(&self) -> Option<f32> {
+        let newest = self.buffer.back()?.time;
+        let half_life_s = self.half_life.as_secs_f32().max(f32::EPSILON);
+        let weighted = self.buffer.iter()
+            .map(|s| (s.lux, f32::powf(0.5, newest.duration_since(s.time).as_secs_f32() / half_life_s)))
+            .sorted_by(|a, b| a.0.total_cmp(&b.0))
+            .collect_vec();
+        let half_total = weighted.iter().map(|(_, w)| w).sum::<f32>() / 2.0;
+        let mut cumulated = 0.0;
+        for (lux, weight) in &weighted {
+            cumulated += weight;
+            if cumulated >= half_total {
+                return Some(*lux);
+            }
+        }
+        weighted.last().map(|(lux, _)| *lux)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HALF_LIFE: Duration = Duration::from_secs(10);
+
+    fn filter(kind: LuxFilterKind, window: usize, samples: &[(f32, u64)]) -> LuxFilter {
+        let start = Instant::now();
+        let mut filter = LuxFilter::new(kind, window, HALF_LIFE);
+        for &(lux, s) in samples {
+            filter.push(lux, start + Duration::from_secs(s));
+        }
+        filter
+    }
+
+    #[test]
+    fn no_level_without_samples() {
+        for kind in [LuxFilterKind::Median, LuxFilterKind::ExponentialMovingAverage, LuxFilterKind::TrimmedMean,
+            LuxFilterKind::TimeWeightedMedian] {
+            assert_eq!(LuxFilter::new(kind, 5, HALF_LIFE).level(), None);
+        }
+    }
+
+    #[test]
+    fn time_weighted_median_of_simultaneous_samples_is_the_median() {
+        let filter = filter(LuxFilterKind::TimeWeightedMedian, 5, &[(7.0, 0), (1.0, 0), (100.0, 0), (3.0, 0), (5.0, 0)]);
+        assert_eq!(filter.level(), Some(5.0));
+    }
+
+    #[test]
+    fn time_weighted_median_follows_the_recent_samples() {
+        // the plain median would still be 10 lux
+        let samples = [(10.0, 0), (10.0, 0), (10.0, 0), (200.0, 40), (200.0, 41)];
+        assert_eq!(filter(LuxFilterKind::Median, 5, &samples).level(), Some(10.0));
+        assert_eq!(filter(LuxFilterKind::TimeWeightedMedian, 5, &samples).level(), Some(200.0));
+    }
+
+    #[test]
+    fn time_weighted_median_ignores_a_single_recent_outlier() {
+        // the newer samples weigh a bit more - but one sample is not the majority
+        let samples = [(10.0, 0), (11.0, 1), (12.0, 2), (500.0, 3)];
+        assert_eq!(filter(LuxFilterKind::TimeWeightedMedian, 5, &samples).level(), Some(12.0));
+    }
+
+    #[test]
+    fn time_weighted_median_with_zero_half_life_takes_the_newest_sample() {
+        let start = Instant::now();
+        let mut filter = LuxFilter::new(LuxFilterKind::TimeWeightedMedian, 5, Duration::ZERO);
+        filter.push(1.0, start);
+        filter.push(2.0, start + Duration::from_secs(1));
+        filter.push(3.0, start + Duration::from_secs(2));
+        assert_eq!(filter.level(), Some(3.0));
+    }
+
+    #[test]
+    fn window_keeps_the_most_recent_samples() {
+        let mut filter = filter(LuxFilterKind::Median, 3, &[(1.0, 0), (2.0, 1), (50.0, 2), (60.0, 3), (70.0, 4)]);
+        assert_eq!(filter.level(), Some(60.0));
+        filter.set_window(1);
+        assert_eq!(filter.level(), Some(70.0));
+    }
+
+    #[test]
+    fn trimmed_mean_drops_the_extremes() {
+        let samples = [(0.0, 0), (10.0, 1), (11.0, 2), (12.0, 3), (1000.0, 4)];
+        assert_eq!(filter(LuxFilterKind::TrimmedMean, 5, &samples).level(), Some(11.0));
+    }
+}
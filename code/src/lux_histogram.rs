@@ -0,0 +1,108 @@
+//! Long-running lux histograms for a data-driven lux threshold suggestion
+//!
+//! We collect two histograms:
+//! - lux levels at the moments a presence was detected (the light was actually used)
+//! - lux levels sampled periodically while nobody was around
+//!
+//! The suggested threshold is the bin edge, which best separates the two distributions
+//! (maximizing the share of used samples at or below the threshold minus the share of idle samples at or below it).
+
+use std::time::{Duration, Instant};
+
+/// Bins per decade on the logarithmic lux scale
+const BINS_PER_DECADE: usize = 4;
+/// Lower edge of the first regular bin (everything below lands in bin 0)
+const LOWEST_LUX: f32 = 0.01;
+/// 0.01 lux .. 100k lux
+const DECADES: usize = 7;
+const BIN_COUNT: usize = BINS_PER_DECADE * DECADES + 1;
+
+/// Interval for sampling lux levels while nobody is around
+const IDLE_SAMPLE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Minimum number of samples in each histogram, before we make a suggestion
+const MIN_SAMPLES_FOR_SUGGESTION: u32 = 20;
+
+#[derive(Debug)]
+pub struct LuxHistogram {
+    used: [u32; BIN_COUNT],
+    idle: [u32; BIN_COUNT],
+    last_idle_sample: Option<Instant>,
+}
+
+impl LuxHistogram {
+    pub fn new() -> Self {
+        Self {
+            used: [0; BIN_COUNT],
+            idle: [0; BIN_COUNT],
+            last_idle_sample: None,
+        }
+    }
+
+    pub fn record_used(&mut self, lux: f32) {
+        self.used[bin_index(lux)] += 1;
+    }
+
+    /// Records an idle sample, if the sample interval has passed
+    pub fn sample_idle(&mut self, lux: f32, now: Instant) {
+        let due = match self.last_idle_sample {
+            Some(t) => now.duration_since(t) >= IDLE_SAMPLE_INTERVAL,
+            None => true
+        };
+        if due {
+            self.idle[bin_index(lux)] += 1;
+            self.last_idle_sample = Some(now);
+        }
+    }
+
+    pub fn used_count(&self) -> u32 {
+        self.used.iter().sum()
+    }
+
+    pub fn idle_count(&self) -> u32 {
+        self.idle.iter().sum()
+    }
+
+    /// Suggested lux threshold or `None` if we don't have enough data yet
+    pub fn suggest_threshold(&self) -> Option<f32> {
+        let used_total = self.used_count();
+        let idle_total = self.idle_count();
+        if used_total < MIN_SAMPLES_FOR_SUGGESTION || idle_total < MIN_SAMPLES_FOR_SUGGESTION {
+            return None;
+        }
+
+        let mut used_cumulated = 0;
+        let mut idle_cumulated = 0;
+        let mut best: Option<(f32, usize)> = None;
+        for i in 0..BIN_COUNT {
+            used_cumulated += self.used[i];
+            idle_cumulated += self.idle[i];
+            let score = used_cumulated as f32 / used_total as f32 - idle_cumulated as f32 / idle_total as f32;
+            if best.map_or(true, |(best_score, _)| score > best_score) {
+                best = Some((score, i));
+            }
+        }
+        best.map(|(_, i)| bin_upper_edge(i))
+    }
+
+    /// One line per non-empty bin: `<upper edge> used: <n> idle: <n>`
+    pub fn report(&self) -> Vec<String> {
+        (0..BIN_COUNT)
+            .filter(|&i| self.used[i] > 0 || self.idle[i] > 0)
+            .map(|i| format!("<= {:.2} lux  used: {}  idle: {}", bin_upper_edge(i), self.used[i], self.idle[i]))
+            .collect()
+    }
+}
+
+fn bin_index(lux: f32) -> usize {
+    if lux < LOWEST_LUX {
+        0
+    } else {
+        let i = (f32::log10(lux / LOWEST_LUX) * BINS_PER_DECADE as f32).floor() as usize + 1;
+        i.min(BIN_COUNT - 1)
+    }
+}
+
+fn bin_upper_edge(index: usize) -> f32 {
+    LOWEST_LUX * f32::powf(10.0, index as f32 / BINS_PER_DECADE as f32)
+}
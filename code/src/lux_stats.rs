@@ -0,0 +1,138 @@
+//! Long-term lux statistics
+//!
+//! Aggregates the lux level per hour (min / median / max) and keeps the last 24 hours.
+//! Optionally the history is persisted in NVS, so that it survives a restart.
+//! (Without a wall clock the hours are relative to now.)
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use itertools::Itertools;
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+
+/// Interval of lux samples for the aggregation (keeps the sample buffer small)
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+const AGGREGATION_PERIOD: Duration = Duration::from_secs(60 * 60);
+const HISTORY_HOURS: usize = 24;
+
+pub const NVS_NAMESPACE: &str = "lux-stats";
+const KEY_HISTORY: &str = "history";
+/// min, median, max as f32
+const AGGREGATE_SIZE: usize = 3 * 4;
+
+#[derive(Copy, Clone, Debug)]
+pub struct LuxAggregate {
+    pub min: f32,
+    pub median: f32,
+    pub max: f32,
+}
+
+#[derive(Debug)]
+pub struct LuxStats {
+    period_start: Option<Instant>,
+    last_sample: Option<Instant>,
+    samples: Vec<f32>,
+    /// oldest first
+    history: AllocRingBuffer<LuxAggregate>,
+}
+
+impl LuxStats {
+    pub fn new() -> Self {
+        Self {
+            period_start: None,
+            last_sample: None,
+            samples: Vec::new(),
+            history: AllocRingBuffer::new(HISTORY_HOURS),
+        }
+    }
+
+    /// Continues with a (persisted) history - oldest first
+    pub fn restore(&mut self, history: Vec<LuxAggregate>) {
+        self.history.extend(history);
+    }
+
+    /// Records a sample, if the sample interval has passed.
+    /// Returns the aggregate of the hour, when it was completed with this sample.
+    pub fn sample(&mut self, lux: f32, now: Instant) -> Option<LuxAggregate> {
+        if self.last_sample.is_some_and(|t| now.duration_since(t) < SAMPLE_INTERVAL) {
+            return None;
+        }
+        self.last_sample = Some(now);
+        let period_start = *self.period_start.get_or_insert(now);
+        self.samples.push(lux);
+
+        if now.duration_since(period_start) < AGGREGATION_PERIOD {
+            return None;
+        }
+        let sorted = self.samples.iter().copied().sorted_by(f32::total_cmp).collect_vec();
+        let aggregate = LuxAggregate {
+            min: sorted[0],
+            median: sorted[sorted.len() / 2],
+            max: sorted[sorted.len() - 1],
+        };
+        self.history.push(aggregate);
+        self.samples.clear();
+        self.period_start = Some(now);
+        Some(aggregate)
+    }
+
+    pub fn history(&self) -> impl Iterator<Item=&LuxAggregate> {
+        self.history.iter()
+    }
+
+    /// Min / max of the last 24 hours
+    pub fn daily_range(&self) -> Option<(f32, f32)> {
+        self.history.iter().fold(None, |range, a| match range {
+            Some((min, max)) => Some((a.min.min(min), a.max.max(max))),
+            None => Some((a.min, a.max))
+        })
+    }
+
+    /// One line per hour (newest first) and the daily range
+    pub fn report(&self) -> Vec<String> {
+        let mut lines = self.history.iter().rev().enumerate()
+            .map(|(i, a)| format!("hour -{:2}: min: {:.2}  median: {:.2}  max: {:.2} lux", i + 1, a.min, a.median, a.max))
+            .collect_vec();
+        match self.daily_range() {
+            Some((min, max)) => lines.push(format!("last {} hours: min: {:.2}  max: {:.2} lux", self.history.len(), min, max)),
+            None => lines.push("no completed hour yet".to_string())
+        }
+        lines
+    }
+}
+
+/// NVS persistence of the lux statistics history
+pub struct LuxStatsStore {
+    nvs: EspNvs<NvsDefault>,
+}
+
+impl LuxStatsStore {
+    pub fn new(partition: EspDefaultNvsPartition) -> Result<Self> {
+        Ok(Self {
+            nvs: EspNvs::new(partition, NVS_NAMESPACE, true)?
+        })
+    }
+
+    pub fn load(&self) -> Result<Vec<LuxAggregate>> {
+        let mut buf = [0_u8; HISTORY_HOURS * AGGREGATE_SIZE];
+        let Some(data) = self.nvs.get_raw(KEY_HISTORY, &mut buf)? else {
+            return Ok(Vec::new());
+        };
+        Ok(data.chunks_exact(AGGREGATE_SIZE)
+            .map(|chunk| {
+                let value = |i: usize| f32::from_le_bytes([chunk[i], chunk[i + 1], chunk[i + 2], chunk[i + 3]]);
+                LuxAggregate { min: value(0), median: value(4), max: value(8) }
+            })
+            .collect())
+    }
+
+    pub fn save(&mut self, stats: &LuxStats) -> Result<()> {
+        let data = stats.history()
+            .flat_map(|a| [a.min, a.median, a.max])
+            .flat_map(f32::to_le_bytes)
+            .collect_vec();
+        self.nvs.set_raw(KEY_HISTORY, &data)?;
+        Ok(())
+    }
+}
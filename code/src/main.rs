@@ -1,25 +1,148 @@
 #![feature(duration_constructors)]
 
-use std::fmt::{Display, Formatter};
 use std::ops::{Add, Sub};
 use std::time::{Duration, Instant};
 
-use anyhow::Result;
-use esp_idf_hal::ledc::LedcDriver;
-use esp_idf_svc::hal::delay::FreeRtos;
+use anyhow::{anyhow, Result};
+use esp_idf_svc::hal::delay::{FreeRtos, TickType};
 use esp_idf_svc::hal::gpio;
-use esp_idf_svc::hal::gpio::{Level, Pin, PinDriver};
-use esp_idf_svc::hal::i2c::I2cDriver;
+use esp_idf_svc::hal::gpio::{IOPin, Level, OutputPin, Pin, PinDriver};
 use esp_idf_svc::hal::prelude::Peripherals;
-use itertools::Itertools;
-use ringbuffer::{AllocRingBuffer, RingBuffer};
-use veml7700::Veml7700;
+use esp_idf_svc::hal::reset::restart;
+use esp_idf_svc::hal::task::notification::Notification;
+use esp_idf_svc::nvs::EspDefaultNvsPartition;
+use esp_idf_svc::sys::{esp, gpio_hold_dis, gpio_hold_en};
 
-use crate::error::Error;
-use crate::peripheral::{init_led_driver, init_output_pin, init_presence_sensor, init_veml7700, PresenceSensor};
+use crate::animation::{Animation, CurveRamp, MinDutyPolicy};
+use crate::bringup::Bringup;
+#[cfg(feature = "camera-trigger")]
+use crate::camera_trigger::CameraTrigger;
+use crate::clock::Clock;
+use crate::config::{Config, ConfigStore, Param};
+use crate::console::{Command, Console};
+use crate::crosstalk::{CrosstalkCurve, CALIBRATION_STAGES};
+use crate::darkness_latch::DarknessLatch;
+use crate::daylight_sleep::{deep_sleep, woke_from_daylight_sleep, DaylightSleep};
+use crate::dither::Ditherer;
+use crate::effects::{Effect, Effects, EFFECT_STEP_MS};
+use crate::event::{Event, EventQueue};
+use crate::latency::LatencyMonitor;
+#[cfg(feature = "ld2410-uart")]
+use crate::ld2410::Ld2410Uart;
+use crate::led_strip::{AddressableStrip, StripEffect};
+use crate::light_sensor::{combine_readings, LightReading, SaturationPolicy};
+use crate::light_sensor::channel::LightSensorChannel;
+use crate::lux_filter::LuxFilter;
+use crate::lux_histogram::LuxHistogram;
+use crate::lux_stats::{LuxStats, LuxStatsStore};
+use crate::mode::Mode;
+use crate::night_baseline::{NightBaseline, NightBaselineStore};
+use crate::occlusion::OcclusionDetector;
+use crate::peripheral::{init_led_driver, init_led_fade_service, init_output_pin, init_presence_sensor, I2cPort, LedChannel, LedOutput, LightSensorBus, PresenceSensor, LED_LEDC_CHANNELS, LED_MAX_CHANNELS};
+use crate::poll_scheduler::{PollScheduler, PollTaskId};
+use crate::power_audit::{AuditStep, PowerAudit, AUDIT_STEP_DURATION, DEFAULT_CPU_FREQ_MHZ, REDUCED_CPU_FREQ_MHZ};
+use crate::presence_session::PresenceSession;
+use crate::ramp::{Phase, Ramp};
+use crate::safety_cap::{in_provisioning_window, SafetyCapStore, FALLBACK_CAP_PCT, NO_CAP_PCT};
+use crate::schedule::{TimeOfDay, TimeWindow};
+use crate::segment::FollowerSegment;
+use crate::stats::EnergyStats;
+use crate::status::{SharedStatus, StatusSnapshot};
+use crate::stress::{HoldTicks, Sample, StressMonitor, MAX_SETTLE_TICKS, MAX_STRESS_TRANSITIONS, STRESS_TICK};
+#[cfg(feature = "switch")]
+use crate::switch::{Switch, SwitchEvent};
+#[cfg(feature = "test-button")]
+use crate::test_trigger::TestTrigger;
+use crate::thermal::{derating_pct, TemperatureSensor, TEMPERATURE_POLL_BUDGET, TEMPERATURE_POLL_PERIOD};
+use crate::zones::Zones;
 
+mod animation;
+#[cfg(feature = "ble")]
+mod ble;
+mod bringup;
+#[cfg(feature = "camera-trigger")]
+mod camera_trigger;
+mod clock;
+mod color;
+mod config;
+#[cfg(feature = "config-transfer")]
+mod config_transfer;
+mod console;
+mod crosstalk;
+mod darkness_latch;
+mod daylight_sleep;
+mod dither;
+mod effects;
 mod error;
+mod event;
+#[cfg(feature = "switch")]
+mod factory_reset;
+mod group;
+#[cfg(feature = "group")]
+mod group_sync;
+#[cfg(feature = "mqtt")]
+mod ha_discovery;
+mod latency;
+#[cfg(feature = "ld2410-uart")]
+mod ld2410;
+mod led_strip;
+mod log_mirror;
+#[cfg(any(feature = "remote-diagnostics", feature = "webhooks", feature = "influxdb"))]
+mod http_client;
+#[cfg(feature = "hue")]
+mod hue;
+#[cfg(feature = "influxdb")]
+mod influx;
+mod light_sensor;
+mod lux_filter;
+mod lux_histogram;
+mod lux_stats;
+#[cfg(feature = "mdns")]
+mod mdns;
+mod mode;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod night_baseline;
+mod occlusion;
+#[cfg(feature = "ota")]
+mod ota;
 mod peripheral;
+mod poll_scheduler;
+mod power_audit;
+mod presence_session;
+mod presets;
+mod profile;
+mod ramp;
+#[cfg(any(feature = "mqtt", feature = "rest-api"))]
+mod remote_command;
+#[cfg(feature = "remote-diagnostics")]
+mod remote_diagnostics;
+#[cfg(feature = "rest-api")]
+mod rest_api;
+mod safety_cap;
+mod schedule;
+mod segment;
+mod stats;
+mod status;
+mod stress;
+#[cfg(feature = "switch")]
+mod switch;
+#[cfg(feature = "syslog")]
+mod syslog;
+#[cfg(feature = "tcp-console")]
+mod tcp_console;
+#[cfg(feature = "websocket")]
+mod telemetry;
+#[cfg(feature = "test-button")]
+mod test_trigger;
+mod thermal;
+#[cfg(feature = "thread")]
+mod thread_net;
+#[cfg(feature = "web-ui")]
+mod web_ui;
+#[cfg(feature = "webhooks")]
+mod webhook;
+mod zones;
 
 
 /// Number of stages the Led power level is increased from [Phase::Off] to [Phase::On] and vice versa.
@@ -28,173 +151,919 @@ pub const LED_MAX_POWER_STAGE: u32 = 1000;
 /// max. reaction delay when LED Power Phase is in Off or ON state
 const ON_OFF_REACTION_STEP_DELAY_MS: u32 = 500;
 
-// step-delay (and also max. reaction time) when LED Power Phase is in PowerDown or PowerUp state
-const LED_DIMM_DOWN_STEP_DELAY_MS: u32 = 10;
+/// Duration of a hardware fade segment (and the loop delay while fading) with `led_hw_fade`
+const HW_FADE_SEGMENT_MS: u32 = 100;
 
-const LED_DIMM_UP_STEP_DELAY_MS: u32 = 5;
+/// max. loop delay while a delayed target change of the second LED segment is pending
+const SEGMENT_DELAY_RESOLUTION_MS: u32 = 50;
 
-const LUX_BUFFER_SIZE: usize = 10;
-const LUX_THRESHOLD: u32 = 30;
+/// Time the presence sensor is switched off for a power-cycle
+const PRESENCE_SENSOR_POWER_CYCLE_MS: u32 = 1000;
+/// Time for the log output before a restart by command
+const REBOOT_DELAY_MS: u32 = 500;
+
+/// Presence zone of the (single) radar presence sensor
+const PRESENCE_SENSOR_ZONE: usize = 0;
+const ZONE_COUNT: usize = 1;
 
 const STATUS_LOG_INTERVAL: Duration = Duration::from_secs(2);
+/// Interval of the attempts to store the always-on mode after a failed one
+const ALWAYS_ON_STORE_RETRY_INTERVAL: Duration = Duration::from_secs(60);
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
-enum Phase {
-    Off,
-    PowerDown,
-    PowerUp,
-    On,
-}
+/// Time per stage of the crosstalk calibration before we read the ambient light sensor
+/// (covers the longest integration time)
+const CROSSTALK_SETTLE_MS: u32 = 1000;
+
+/// Poll period of the ambient light sensor(s) - a bit below the loop delay in the Off phase, so each of these ticks reads
+const LIGHT_POLL_PERIOD: Duration = Duration::from_millis(400);
+/// Time budget of an ambient light poll (all sensors)
+const LIGHT_POLL_BUDGET: Duration = Duration::from_millis(150);
 
+/// LED self-test at startup: steps per ramp direction, time per step and the peak duty [% of the max. duty]
+const SELF_TEST_STEPS: u32 = 10;
+const SELF_TEST_STEP_MS: u32 = 30;
+const SELF_TEST_PEAK_PCT: u32 = 25;
+
+/// Confirmation of a factory reset: number of LED blinks and their on / off time
+#[cfg(feature = "switch")]
+const FACTORY_RESET_BLINKS: u32 = 5;
+#[cfg(feature = "switch")]
+const FACTORY_RESET_BLINK_MS: u32 = 150;
+
+/// Time the LED stays dimmed during a sampling gap before we read the ambient light sensor
+/// (has to cover at least one integration period of the sensor)
+const SAMPLING_GAP_SETTLE_MS: u32 = 250;
 
 #[derive(Debug)]
 struct State {
-    // ambient light level history buffer (last 10 values)
-    ambient_light_sensor_lux_buffer: AllocRingBuffer<u32>,
-    pub phase: Phase,
-    /// range: 0..LED_POWER_STAGES
-    pub led_power_stage: u32,
-    pub duty: u32
+    pub config: Config,
+    pub mode: Mode,
+    // filtered ambient light level history
+    lux_filter: LuxFilter,
+    pub lux_histogram: LuxHistogram,
+    pub lux_stats: LuxStats,
+    pub night_baseline: NightBaseline,
+    /// last (unfiltered) ambient light measurement
+    pub light_reading: Option<LightReading>,
+    /// without ambient light sensor (degraded mode) we skip the darkness gating or use the fallback dark window
+    pub light_sensor_available: bool,
+    /// local wall clock time, if known
+    pub local_time: Option<TimeOfDay>,
+    /// number of failed ambient light sensor reads since startup
+    pub light_sensor_failures: u32,
+    darkness_latch: DarknessLatch,
+    /// LED power stage and its target, as set by the logic
+    pub ramp: Ramp,
+    pub duty: u32,
+    /// optional second LED segment, following the first one
+    pub second_segment: FollowerSegment,
+    /// presence sensor signal of the last reading
+    pub presence: bool,
+    /// presence signal simulated by the test button
+    pub test_presence: bool,
+    /// presence reported by a follower of the group (coordinator)
+    pub group_presence: bool,
+    /// target stage of the group's coordinator, while it is known (follower)
+    pub group_stage: Option<u32>,
+    /// start of the current continuous presence detection
+    presence_since: Option<Instant>,
+    zones: Zones,
+    /// time of the last completed power-down
+    powered_down_at: Option<Instant>,
+    /// (filtered) ambient light level before the current power-up - for the ambient-adaptive brightness
+    power_up_lux: Option<f32>,
+    /// time of the last sampling gap (or the start of the On phase)
+    last_sampling_gap: Option<Instant>,
+    /// software effects overriding the duty
+    effects: Effects,
+    /// temperature of the LED profile [°C] (if there is a sensor)
+    temperature: Option<f32>,
+    /// estimated energy usage of the LEDs
+    energy_stats: EnergyStats,
+    /// start of the current light request by a presence - for the two-stage brightness
+    presence_power_up_at: Option<Instant>,
+    /// start of the continuous light request in auto mode - for the on-time cutoff
+    light_requested_since: Option<Instant>,
+    /// the on-time cutoff kicked in - a presence powers up again only after the presence signal went low
+    on_time_cutoff: bool,
 }
 
 impl State {
-    pub fn new() -> Self {
+    pub fn new(config: Config) -> Self {
         State {
-            ambient_light_sensor_lux_buffer: AllocRingBuffer::new(LUX_BUFFER_SIZE),
-            phase: Phase::Off,
-            led_power_stage: 0,
-            duty: 0
+            lux_filter: LuxFilter::new(config.lux_filter, config.lux_window as usize,
+                                       Duration::from_millis(config.lux_half_life_ms as u64)),
+            lux_histogram: LuxHistogram::new(),
+            lux_stats: LuxStats::new(),
+            night_baseline: NightBaseline::new(),
+            light_reading: None,
+            light_sensor_available: true,
+            local_time: None,
+            light_sensor_failures: 0,
+            darkness_latch: DarknessLatch::new(),
+            config,
+            mode: Mode::Auto,
+            ramp: Ramp::new(),
+            duty: 0,
+            second_segment: FollowerSegment::new(),
+            presence: false,
+            test_presence: false,
+            group_presence: false,
+            group_stage: None,
+            presence_since: None,
+            zones: Zones::new(ZONE_COUNT),
+            powered_down_at: None,
+            power_up_lux: None,
+            last_sampling_gap: None,
+            effects: Effects::new(),
+            temperature: None,
+            energy_stats: EnergyStats::new(),
+            presence_power_up_at: None,
+            light_requested_since: None,
+            on_time_cutoff: false,
         }
     }
 
-    pub fn lux_level(&self) -> Option<u32> {
-        if self.ambient_light_sensor_lux_buffer.is_empty() {
-            None
-        } else {
-            let sorted = self.ambient_light_sensor_lux_buffer.iter()
-                .sorted()
-                .collect_vec();
-            Some(
-                *sorted[self.ambient_light_sensor_lux_buffer.len() / 2]
-            )
+    /// Propagates (changed) configuration values to the dependent parts of the state
+    pub fn apply_config(&mut self) {
+        self.lux_filter.set_kind(self.config.lux_filter);
+        self.lux_filter.set_half_life(Duration::from_millis(self.config.lux_half_life_ms as u64));
+        if self.lux_filter.window() != self.config.lux_window as usize {
+            self.lux_filter.set_window(self.config.lux_window as usize);
+        }
+    }
+
+    /// Combined phase of the LED segments - a moving segment wins over a steady one, light over no light
+    pub fn phase(&self) -> Phase {
+        let phases = [self.ramp.phase(), self.second_segment.ramp.phase()];
+        [Phase::PowerUp, Phase::PowerDown, Phase::On].into_iter()
+            .find(|p| phases.contains(p))
+            .unwrap_or(Phase::Off)
+    }
+
+    pub fn led_power_stage(&self) -> u32 {
+        self.ramp.stage()
+    }
+
+    /// Whether the logic currently wants the light to be on
+    pub fn is_light_requested(&self) -> bool {
+        self.ramp.target_stage() > 0
+    }
+
+    pub fn power_up(&mut self) {
+        if !self.is_light_requested() {
+            self.power_up_lux = self.lux_level();
+        }
+        self.ramp.set_target_stage(LED_MAX_POWER_STAGE);
+    }
+
+    /// Two-stage brightness: a presence powers up to `presence_stage_pct` first
+    /// and to full brightness, when the presence lasts for `full_on_after_ms` since the power-up
+    fn power_up_for_presence(&mut self, now: Instant) {
+        if !self.is_light_requested() {
+            self.presence_power_up_at = Some(now);
+        }
+        self.power_up();
+        let sustained = self.presence_power_up_at
+            .is_some_and(|t| now.duration_since(t) >= Duration::from_millis(self.config.full_on_after_ms as u64));
+        if !sustained {
+            self.ramp.set_target_stage(LED_MAX_POWER_STAGE * self.config.presence_stage_pct / 100);
+        }
+    }
+
+    pub fn power_down(&mut self) {
+        self.ramp.set_target_stage(0);
+    }
+
+    /// Applies the lux calibration, subtracts the LED's own light contribution (calibrated lux)
+    /// and records the reading - saturated readings according to the saturation policy
+    pub fn record_light_reading(&mut self, measured: LightReading, led_lux_contribution: f32) {
+        let lux = (measured.lux * self.config.lux_calibration - led_lux_contribution).max(0.0);
+        self.light_reading = Some(LightReading { lux, ..measured });
+        if measured.is_saturated() && self.config.saturation_policy != SaturationPolicy::Clamp {
+            return;
+        }
+        self.lux_filter.push(lux, Instant::now());
+    }
+
+    pub fn lux_level(&self) -> Option<f32> {
+        self.lux_filter.level()
+    }
+
+    /// Effective darkness threshold: `lux_threshold`, raised relative to the nightly baseline if configured
+    pub fn lux_threshold(&self) -> f32 {
+        match self.night_baseline.baseline().filter(|_| self.config.adaptive_lux_factor > 0.0) {
+            Some(baseline) => self.config.lux_threshold.max(baseline * self.config.adaptive_lux_factor),
+            None => self.config.lux_threshold
         }
     }
 
     pub fn is_dark_enough_for_operation(&self) -> bool {
+        if !self.light_sensor_available {
+            return self.is_in_fallback_dark_window();
+        }
+        if self.darkness_latch.is_latched() {
+            return true;
+        }
         match self.lux_level() {
-            Some(lux) => lux <= LUX_THRESHOLD,
+            Some(lux) => lux <= self.lux_threshold(),
             None => false
         }
     }
 
-    pub fn duty_step_delay_ms(&self) -> u32 {
-        match self.phase {
-            Phase::Off | Phase::On => ON_OFF_REACTION_STEP_DELAY_MS,
-            Phase::PowerDown => LED_DIMM_DOWN_STEP_DELAY_MS,
-            Phase::PowerUp => LED_DIMM_UP_STEP_DELAY_MS
+    /// Darkness decision without ambient light sensor: the fallback dark window, if configured and the time is known
+    fn is_in_fallback_dark_window(&self) -> bool {
+        let window = TimeWindow::new(self.config.fallback_dark_from, self.config.fallback_dark_until);
+        match self.local_time {
+            Some(t) if !window.is_empty() => window.contains(t),
+            _ => true
         }
     }
 
-    pub fn calc_dimm_progress(&mut self) {
-        match self.phase {
-            Phase::Off => debug_assert_eq!(self.led_power_stage, 0),
-            Phase::PowerDown => {
-                if self.led_power_stage > 0 {
-                    self.led_power_stage -= 1;
-                }
-                if self.led_power_stage == 0 {
-                    self.phase = Phase::Off;
-                }
+    pub fn update_darkness_latch(&mut self, now: Instant) {
+        if let Some(lux) = self.lux_level() {
+            if let Some(latched) = self.darkness_latch.update(lux, self.lux_threshold(), &self.config, now) {
+                log::info!("darkness latch {}", if latched { "engaged" } else { "released" });
             }
-            Phase::PowerUp => {
-                if self.led_power_stage < LED_MAX_POWER_STAGE {
-                    self.led_power_stage += 1;
+        }
+    }
+
+    pub fn update_presence(&mut self, presence: bool, now: Instant) {
+        self.presence = presence;
+        self.zones.update(PRESENCE_SENSOR_ZONE, presence, now);
+        if !presence {
+            self.presence_since = None;
+        } else if self.presence_since.is_none() {
+            self.presence_since = Some(now);
+        }
+    }
+
+    /// Sets the ramp target according to the presence sensor signal and the mode
+    pub fn apply_presence(&mut self, level: Level, now: Instant) {
+        self.update_presence(level == Level::High, now);
+
+        if self.mode.forces_light_on() {
+            self.power_up();
+            if self.mode.is_instant() {
+                self.ramp.jump_to_target();
+            }
+            return;
+        } else if self.mode == Mode::ForcedOff {
+            self.power_down();
+            return;
+        }
+
+        match level {
+            Level::Low => {
+                self.on_time_cutoff = false;
+                if self.is_light_requested() {
+                    let factor = self.zone_brightness_factor(now);
+                    // never brighter than before (two-stage brightness)
+                    let trailing_stage = ((LED_MAX_POWER_STAGE as f32 * factor) as u32).min(self.ramp.target_stage());
+                    if trailing_stage > 0 {
+                        self.ramp.set_target_stage(trailing_stage);
+                    } else {
+                        self.power_down();
+                        log::info!("Powering down");
+                    }
                 }
-                if self.led_power_stage == LED_MAX_POWER_STAGE {
-                    self.phase = Phase::On;
+            }
+            Level::High => {
+                if self.on_time_cutoff {
+                    // (stuck) presence signal since the cutoff
+                } else if !self.is_dark_enough_for_operation() {
+                    if self.is_light_requested() {
+                        // only possible with ambient light measurements from sampling gaps
+                        self.power_down();
+                        log::info!("Powering down - daylight");
+                    }
+                } else if self.is_light_requested() {
+                    // presence is back while trailing out (or continues)
+                    self.power_up_for_presence(now);
+                } else if self.phase() != Phase::Off || self.presence_confirmed(now) {
+                    self.power_up_for_presence(now);
+                    log::info!("Powering up");
                 }
             }
-            Phase::On => debug_assert_eq!(self.led_power_stage, LED_MAX_POWER_STAGE)
         }
     }
-}
 
-impl Display for State {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "logic state: dark_enough: {}, lux: {:?}, phase: {:?}, led_power_stage: {}",
-               self.is_dark_enough_for_operation(),
-               self.lux_level(),
-               self.phase,
-               self.led_power_stage
-        )
+    /// Group follower: moves to the target stage of the coordinator instead of the own presence logic -
+    /// a mode other than auto still wins
+    pub fn follow_group(&mut self, level: Level, target_stage: u32, now: Instant) {
+        if self.mode != Mode::Auto {
+            self.apply_presence(level, now);
+            return;
+        }
+        self.update_presence(level == Level::High, now);
+        self.ramp.set_target_stage(target_stage);
+    }
+
+    /// Safety cutoff: powers down after `max_on_time_min` of continuous light in auto mode,
+    /// so that a stuck presence signal can't keep the light on all night. Returns true, when the cutoff kicked in.
+    pub fn enforce_max_on_time(&mut self, now: Instant) -> bool {
+        if self.mode != Mode::Auto || !self.is_light_requested() {
+            self.light_requested_since = None;
+            return false;
+        }
+        let since = *self.light_requested_since.get_or_insert(now);
+        let max_on_time = Duration::from_mins(self.config.max_on_time_min as u64);
+        if max_on_time.is_zero() || now.duration_since(since) < max_on_time {
+            return false;
+        }
+        self.power_down();
+        self.on_time_cutoff = true;
+        self.light_requested_since = None;
+        true
+    }
+
+    /// Brightness factor [0..1] according to the occupancy age of the zones; 0 if trailing is disabled
+    pub fn zone_brightness_factor(&self, now: Instant) -> f32 {
+        if self.config.zone_trail_s == 0 {
+            return 0.0;
+        }
+        self.zones.factor(Duration::from_secs(self.config.zone_trail_s as u64), now)
+    }
+
+    /// Within the re-trigger guard period after a completed power-down a presence is ignored
+    /// or needs to be detected continuously for the confirmation time.
+    pub fn presence_confirmed(&self, now: Instant) -> bool {
+        let Some(presence_since) = self.presence_since else {
+            return false;
+        };
+        let in_guard_period = match self.powered_down_at {
+            Some(t) => now.duration_since(t) < Duration::from_millis(self.config.retrigger_guard_ms as u64),
+            None => false
+        };
+        if !in_guard_period {
+            true
+        } else if self.config.retrigger_confirm_ms == 0 {
+            false
+        } else {
+            now.duration_since(presence_since) >= Duration::from_millis(self.config.retrigger_confirm_ms as u64)
+        }
+    }
+
+    /// Whether it's time to briefly dim the light for an ambient light measurement
+    pub fn sampling_gap_due(&mut self, now: Instant) -> bool {
+        let lit = self.phase() == Phase::On || self.standby_glow_active();
+        if !lit || self.config.sampling_gap_interval_s == 0 || !self.light_sensor_available {
+            self.last_sampling_gap = None;
+            return false;
+        }
+        match self.last_sampling_gap {
+            Some(t) => now.duration_since(t) >= Duration::from_secs(self.config.sampling_gap_interval_s as u64),
+            None => {
+                self.last_sampling_gap = Some(now);
+                false
+            }
+        }
+    }
+
+    /// Faint standby light for orientation: in auto mode with the light off in the dark
+    pub fn standby_glow_active(&self) -> bool {
+        self.config.standby_glow_pct > 0.0 && self.mode == Mode::Auto && self.phase() == Phase::Off
+            && self.is_dark_enough_for_operation()
+    }
+
+    /// The second LED output is a delayed segment - not the cold white channel of a tunable white strip
+    fn second_segment_enabled(&self) -> bool {
+        self.config.second_led && !self.config.tunable_white && !self.config.rgbw
+    }
+
+    /// Ramp (segment) an LED channel follows: color channels (tunable white, RGBW) all follow the main ramp
+    fn segment_of_channel(&self, channel: usize) -> usize {
+        if self.second_segment_enabled() {
+            channel.min(1)
+        } else {
+            0
+        }
+    }
+
+    /// Color temperature [K] of the light - the night one within the night window (if the time is known)
+    fn cct_k(&self) -> u32 {
+        let night = TimeWindow::new(self.config.cct_night_from, self.config.cct_night_until);
+        match self.local_time {
+            Some(t) if !night.is_empty() && night.contains(t) => self.config.cct_night_k,
+            _ => self.config.cct_k
+        }
+    }
+
+    /// Shares of the LED channels: the RGBW color, the warm / cold white mix with tunable white, full for segments
+    pub fn color_mix(&self) -> [f32; LED_MAX_CHANNELS] {
+        if self.config.led_strip_pixels > 0 {
+            [1.0; LED_MAX_CHANNELS]
+        } else if self.config.rgbw {
+            color::rgbw_shares(self.config.led_color)
+        } else if self.config.tunable_white {
+            let [warm, cold] = color::cct_shares(self.cct_k(), self.config.cct_warm_k, self.config.cct_cold_k);
+            [warm, cold, 0.0, 0.0]
+        } else {
+            [1.0; LED_MAX_CHANNELS]
+        }
+    }
+
+    /// Brightness [%] of the power curve - reduced within the night-light window and according to the ambient light
+    /// before the power-up (not in maintenance / emergency mode)
+    pub fn brightness_pct(&self) -> u32 {
+        if self.mode.is_instant() {
+            return 100;
+        }
+        let night = TimeWindow::new(self.config.night_light_from, self.config.night_light_until);
+        let night_pct = match self.local_time {
+            Some(t) if !night.is_empty() && night.contains(t) => self.config.night_light_pct,
+            _ => 100
+        };
+        night_pct * self.ambient_brightness_pct() / 100
+    }
+
+    /// Very dark - low brightness, dusk - full brightness (linear up to the lux threshold)
+    fn ambient_brightness_pct(&self) -> u32 {
+        let (Some(lux), Some(threshold)) = (self.power_up_lux, Some(self.lux_threshold()).filter(|&t| t > 0.0)) else {
+            return 100;
+        };
+        let dark_pct = self.config.ambient_brightness_pct as f32;
+        let pct = dark_pct + (100.0 - dark_pct) * (lux / threshold).clamp(0.0, 1.0);
+        pct.round() as u32
+    }
+
+    /// Fade duration for the full stage range in the phase
+    fn fade_duration(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::PowerDown => Duration::from_secs_f32(self.config.fade_down_s),
+            Phase::PowerUp => Duration::from_secs_f32(self.config.fade_up_s),
+            Phase::Off | Phase::On => Duration::ZERO
+        }
+    }
+
+    fn fade_takeover(&self) -> Duration {
+        Duration::from_millis(self.config.fade_takeover_ms as u64)
+    }
+
+    /// Loop delay - while fading about the time of one stage, so that every stage gets applied
+    /// (or one hardware fade segment)
+    pub fn duty_step_delay_ms(&self) -> u32 {
+        let delay_ms = match self.phase() {
+            Phase::Off | Phase::On => ON_OFF_REACTION_STEP_DELAY_MS,
+            Phase::PowerDown | Phase::PowerUp if self.config.led_hw_fade => HW_FADE_SEGMENT_MS,
+            Phase::PowerDown | Phase::PowerUp => {
+                let stage_ms = self.fade_duration(self.phase()).as_millis() as u32 / LED_MAX_POWER_STAGE;
+                stage_ms.clamp(1, ON_OFF_REACTION_STEP_DELAY_MS)
+            }
+        };
+        if self.effects.is_active() {
+            delay_ms.min(EFFECT_STEP_MS)
+        } else if self.second_segment.is_pending() {
+            delay_ms.min(SEGMENT_DELAY_RESOLUTION_MS)
+        } else {
+            delay_ms
+        }
+    }
+
+    pub fn calc_dimm_progress(&mut self) {
+        self.calc_dimm_progress_at(Instant::now());
+    }
+
+    fn calc_dimm_progress_at(&mut self, now: Instant) {
+        if self.second_segment_enabled() {
+            self.second_segment.update(self.ramp.target_stage(), self.config.second_led_pct,
+                                       Duration::from_millis(self.config.second_led_delay_ms as u64), now);
+            let fade = self.fade_duration(self.second_segment.ramp.phase());
+            self.second_segment.ramp.advance(fade, self.config.ramp_shape, self.fade_takeover(), now);
+        }
+        if self.ramp.advance(self.fade_duration(self.ramp.phase()), self.config.ramp_shape, self.fade_takeover(), now) == Some(Phase::Off) {
+            self.powered_down_at = Some(now);
+        }
     }
 }
 
 struct Devices<P1: Pin, P2: Pin> {
     presence_sensor: PresenceSensor<P1>,
     presence_sensor_power_pin: PinDriver<'static, P2, gpio::Output>,
-    ambient_light_sensor: Veml7700<I2cDriver<'static>>,
-    led_driver: LedcDriver<'static>,
-    led_power_curve_scale_factor: f32,
+    /// one or two ambient light sensors - each might be unavailable (degraded mode) or lost at runtime
+    light_sensors: Vec<LightSensorChannel>,
+    /// LED outputs: the first segment and optionally a second one (two-zone output),
+    /// the warm and cold white channel or the red, green, blue and white channel
+    leds: Vec<Box<dyn LedChannel>>,
+    /// hardware safety cap - upper bound of every (logical) duty written to the LED driver
+    duty_cap: u32,
+    /// thermal derating - upper bound of every duty as well
+    thermal_cap: u32,
+    /// optional temperature sensor on the LED profile
+    temperature_sensor: Option<TemperatureSensor>,
+    temperature_poll: PollTaskId,
+    /// optional report frames of the radar presence sensor (diagnostics)
+    #[cfg(feature = "ld2410-uart")]
+    radar: Option<Ld2410Uart>,
+    /// translates the power stage into the channel duties
+    animation: Box<dyn Animation>,
+    /// lowest duty of a lit channel (0 = none) and the treatment of lower duties
+    min_duty: u32,
+    min_duty_policy: MinDutyPolicy,
+    /// shares of the channels applied to the duties (tunable white, RGBW)
+    color_mix: [f32; LED_MAX_CHANNELS],
+    /// the LEDs show an effect instead of the duties of the state
+    effect_shown: bool,
+    /// duty of the standby glow - the lower bound of the shown duties (0 = no glow)
+    glow_duty: u32,
+    pub poll_scheduler: PollScheduler,
+    light_poll: PollTaskId,
 }
 
 impl<P1: Pin, P2: Pin> Devices<P1, P2> {
     pub fn new(
         presence_sensor: PresenceSensor<P1>,
         presence_sensor_power_pin: PinDriver<'static, P2, gpio::Output>,
-        ambient_light_sensor: Veml7700<I2cDriver<'static>>,
-        led_driver: LedcDriver<'static>,
+        light_sensors: Vec<LightSensorChannel>,
+        leds: Vec<Box<dyn LedChannel>>,
+        animation: Box<dyn Animation>,
+        temperature_sensor: Option<TemperatureSensor>,
     ) -> Self {
-        let led_power_curve_scale_factor = Self::calc_led_power_curve_scale_factor(led_driver.get_max_duty());
-        log::info!("LED power curve scale factor: {}", led_power_curve_scale_factor);
+        log::info!("LED animation: {}, segments: {}", animation.name(), leds.len());
+        let duty_cap = leds[0].max_duty();
+        let mut poll_scheduler = PollScheduler::new();
+        let light_poll = poll_scheduler.add("ambient light", LIGHT_POLL_PERIOD, LIGHT_POLL_BUDGET);
+        let temperature_poll = poll_scheduler.add("temperature", TEMPERATURE_POLL_PERIOD, TEMPERATURE_POLL_BUDGET);
         Self {
             presence_sensor,
             presence_sensor_power_pin,
-            ambient_light_sensor,
-            led_driver,
-            led_power_curve_scale_factor,
+            light_sensors,
+            leds,
+            duty_cap,
+            thermal_cap: duty_cap,
+            temperature_sensor,
+            temperature_poll,
+            #[cfg(feature = "ld2410-uart")]
+            radar: None,
+            animation,
+            min_duty: 0,
+            min_duty_policy: MinDutyPolicy::Clamp,
+            color_mix: [1.0; LED_MAX_CHANNELS],
+            effect_shown: false,
+            glow_duty: 0,
+            poll_scheduler,
+            light_poll,
         }
     }
-    pub fn read_sensors(&mut self, state: &mut State) -> Result<()> {
-        if state.phase == Phase::Off {
-            self.measure_ambient_light_level(state)?;
-        }
+    pub fn read_sensors(&mut self, state: &mut State, events: &mut EventQueue) -> Result<()> {
+        // presence first: a presence edge must not wait for the (slower) ambient light measurement - the power-up decision
+        // takes the lux level of the previous readings, which are not influenced by the own light yet
         self.read_presence_sensor_and_apply_phase(state);
+        let now = Instant::now();
+        #[cfg(feature = "ld2410-uart")]
+        if let Some(radar) = self.radar.as_mut() {
+            radar.poll(now);
+        }
+        // LED lux contribution, when the ambient light can be measured continuously
+        let led_lux_contribution = match state.phase() {
+            // the standby glow is switched off for sampling gaps
+            Phase::Off if state.standby_glow_active() => None,
+            Phase::Off => Some(0.0),
+            phase => state.config.led_crosstalk
+                .filter(|_| state.config.crosstalk_compensation && phase == Phase::On)
+                .map(|curve| curve.contribution(state.led_power_stage()))
+        };
+        match led_lux_contribution {
+            Some(led_lux_contribution) => if self.poll_scheduler.is_granted(self.light_poll) {
+                let result = self.measure_ambient_light_level(state, led_lux_contribution, events);
+                self.poll_scheduler.finish(self.light_poll, now, Instant::now());
+                result?;
+            }
+            None => if state.sampling_gap_due(now) {
+                self.run_sampling_gap(state, events)?;
+            }
+        }
+        self.update_thermal_derating(state)?;
         Ok(())
     }
 
-    // measure ambient light level - makes only sense to be called if LED is Off
-    fn measure_ambient_light_level(&mut self, state: &mut State) -> Result<()> {
-        let lux: u32 = self.ambient_light_sensor.read_lux()
-            .map_err(Error::from)?.round() as u32;
-        state.ambient_light_sensor_lux_buffer.push(lux);
+    // measure ambient light level - makes only sense to be called if LED is Off, during a sampling gap
+    // or with a calibrated crosstalk compensation
+    // `led_lux_contribution`: lux the LED adds to the reading at its current duty
+    fn measure_ambient_light_level(&mut self, state: &mut State, led_lux_contribution: f32, events: &mut EventQueue) -> Result<()> {
+        let mut readings = Vec::with_capacity(self.light_sensors.len());
+        // a recovery (bus clear + probing) takes up to a few hundred ms - one per loop iteration keeps the loop responsive
+        let mut recovery_pending = true;
+        for channel in self.light_sensors.iter_mut() {
+            let Some(sensor) = channel.sensor_mut() else {
+                // sensor lost at runtime or not connected at startup - we look for it periodically (hot-plug)
+                // and continue with the last known lux level meanwhile
+                if recovery_pending && channel.recovery_due() {
+                    recovery_pending = false;
+                    if channel.recover(&state.config) {
+                        state.light_sensor_available = true;
+                        events.push(Event::LightSensorFound);
+                    }
+                }
+                continue;
+            };
+            match sensor.read() {
+                Ok(reading) => readings.push(reading),
+                Err(e) => {
+                    state.light_sensor_failures += 1;
+                    log::warn!("{}: ambient light sensor read failed ({} failures since startup): {} - recovering",
+                        channel.name(), state.light_sensor_failures, e);
+                    if recovery_pending {
+                        recovery_pending = false;
+                        if !channel.recover(&state.config) {
+                            events.push(Event::LightSensorLost);
+                        }
+                    } else {
+                        // recovered in one of the next loop iterations
+                        channel.lose();
+                        events.push(Event::LightSensorLost);
+                    }
+                }
+            }
+        }
+        if let Some(measured) = combine_readings(&readings, state.config.lux_combine) {
+            state.record_light_reading(measured, led_lux_contribution);
+        }
         Ok(())
     }
 
-    fn read_presence_sensor_and_apply_phase(&mut self, state: &mut State) {
-        match self.presence_sensor.sensor_pin.get_level() {
-            Level::Low => {
-                if state.phase != Phase::Off
-                    && state.phase != Phase::PowerDown
-                {
-                    state.phase = Phase::PowerDown;
-                    log::info!("Powering down");
+    /// Takes an immediate ambient light reading after startup, so that the first lux decision is available right away
+    pub fn fast_start_light_measurement(&mut self, state: &mut State) {
+        let readings = self.light_sensors.iter_mut()
+            .filter_map(|channel| channel.sensor_mut())
+            .filter_map(|sensor| match sensor.fast_read() {
+                Ok(reading) => Some(reading),
+                Err(e) => {
+                    log::warn!("fast-start light reading failed: {}", e);
+                    None
                 }
+            })
+            .collect::<Vec<_>>();
+        if let Some(reading) = combine_readings(&readings, state.config.lux_combine) {
+            state.record_light_reading(reading, 0.0);
+            log::info!("fast-start light reading: {:?}", state.light_reading);
+        }
+    }
+
+    pub fn is_light_sensor_available(&self) -> bool {
+        self.light_sensors.iter().any(|channel| channel.is_available())
+    }
+
+    pub fn set_safety_cap(&mut self, percent: u32) {
+        self.duty_cap = self.max_duty() * percent / 100;
+        if percent < NO_CAP_PCT {
+            log::info!("LED duty safety cap: {} % ({})", percent, self.duty_cap);
+        }
+    }
+
+    // upper bound of every duty written to the LED driver
+    fn cap(&self) -> u32 {
+        self.duty_cap.min(self.thermal_cap)
+    }
+
+    /// Reads the temperature of the LED profile (if there is a sensor) and limits the duty accordingly.
+    /// Without a valid reading we derate to the minimum - the temperature is unknown.
+    fn update_thermal_derating(&mut self, state: &mut State) -> Result<()> {
+        let Some(sensor) = self.temperature_sensor.as_mut().filter(|_| self.poll_scheduler.is_granted(self.temperature_poll)) else {
+            return Ok(());
+        };
+        let started = Instant::now();
+        let reading = sensor.read_celsius();
+        self.poll_scheduler.finish(self.temperature_poll, started, Instant::now());
+        let percent = match reading {
+            Ok(celsius) => {
+                state.temperature = Some(celsius);
+                derating_pct(celsius, &state.config)
             }
-            Level::High => {
-                if state.is_dark_enough_for_operation()
-                    && state.phase != Phase::On
-                    && state.phase != Phase::PowerUp
-                {
-                    state.phase = Phase::PowerUp;
-                    log::info!("Powering up");
+            Err(e) => {
+                log::warn!("temperature sensor read failed: {}", e);
+                state.temperature = None;
+                state.config.derate_min_pct
+            }
+        };
+        let thermal_cap = self.max_duty() * percent / 100;
+        if thermal_cap != self.thermal_cap {
+            self.thermal_cap = thermal_cap;
+            log::info!("thermal derating: {} % at {:?} °C", percent, state.temperature);
+            self.restore_led_duties(state)?;
+        }
+        Ok(())
+    }
+
+    /// (Re)initializes the LED animation with the configured dimming curve and maximum brightness
+    pub fn configure_led(&mut self, config: &Config) {
+        let max_duty = self.max_duty() * config.max_brightness_pct / 100;
+        self.animation.init(config, max_duty, self.leds.len());
+        self.min_duty = (self.max_duty() as f32 * config.min_duty_pct / 100.0).round() as u32;
+        self.min_duty_policy = config.min_duty_policy;
+    }
+
+    /// Applies the configuration to the ambient light sensors
+    pub fn configure_light_sensors(&mut self, config: &Config) {
+        for channel in self.light_sensors.iter_mut() {
+            channel.configure(config);
+        }
+    }
+
+    /// Applies the configuration to the ambient light sensors, if one of their settings changed
+    /// (a reconfiguration restarts the measurement)
+    pub fn reconfigure_light_sensors(&mut self, previous: &Config, config: &Config) {
+        let changed = Param::ALL.iter()
+            .filter(|param| param.configures_light_sensor())
+            .any(|&param| previous.get(param) != config.get(param));
+        if changed {
+            self.configure_light_sensors(config);
+        }
+    }
+
+    /// Measures the LED's own light on the ambient light sensor(s) at the calibration stages.
+    /// Has to run in the dark with the light off - blocks for a few seconds.
+    pub fn calibrate_crosstalk(&mut self, state: &State) -> Result<CrosstalkCurve> {
+        let result = self.measure_crosstalk(&state.config);
+        self.restore_led_duties(state)?;
+        result
+    }
+
+    fn measure_crosstalk(&mut self, config: &Config) -> Result<CrosstalkCurve> {
+        let baseline = self.read_calibrated_lux(config)?;
+        let mut contributions = [0.0; CALIBRATION_STAGES.len()];
+        for (contribution, &stage) in contributions.iter_mut().zip(CALIBRATION_STAGES.iter()) {
+            let duty = self.calc_led_power_level(0, stage);
+            self.set_all_led_duties(duty)?;
+            FreeRtos::delay_ms(CROSSTALK_SETTLE_MS);
+            *contribution = (self.read_calibrated_lux(config)? - baseline).max(0.0);
+            log::info!("crosstalk calibration: stage {}: {:.2} lux", stage, contribution);
+        }
+        Ok(CrosstalkCurve::new(contributions))
+    }
+
+    /// Startup self-test: ramps every LED channel up and down and verifies the duties by readback
+    /// (as far as the LED backend supports it)
+    pub fn run_led_self_test(&mut self) -> Result<()> {
+        let peak_duty = (self.max_duty() * SELF_TEST_PEAK_PCT / 100).min(self.cap());
+        let mut failures = vec![];
+        for (channel, led) in self.leds.iter_mut().enumerate() {
+            let mut mismatch = None;
+            for step in (0..=SELF_TEST_STEPS).chain((0..SELF_TEST_STEPS).rev()) {
+                let duty = peak_duty * step / SELF_TEST_STEPS;
+                led.set_duty(duty)?;
+                FreeRtos::delay_ms(SELF_TEST_STEP_MS);
+                match led.read_duty() {
+                    Some(read) if read != duty && mismatch.is_none() => mismatch = Some((read, duty)),
+                    _ => ()
                 }
             }
+            if let Some((read, duty)) = mismatch {
+                failures.push(format!("channel {}: duty readback {} instead of {}", channel, read, duty));
+            }
+        }
+        if !failures.is_empty() {
+            anyhow::bail!("LED self-test failed: {}", failures.join(", "));
+        }
+        log::info!("LED self-test: {} channel(s) ok", self.leds.len());
+        Ok(())
+    }
+
+    /// Blinks all LED channels (blocking)
+    #[cfg(feature = "switch")]
+    pub fn blink_leds(&mut self, count: u32, on_off_ms: u32) -> Result<()> {
+        let duty = (self.max_duty() * SELF_TEST_PEAK_PCT / 100).min(self.cap());
+        for _ in 0..count {
+            self.set_all_led_duties(duty)?;
+            FreeRtos::delay_ms(on_off_ms);
+            self.set_all_led_duties(0)?;
+            FreeRtos::delay_ms(on_off_ms);
+        }
+        Ok(())
+    }
+
+    /// Idle-phase power audit - blocks for about half a minute. Has to run with the light off.
+    pub fn run_power_audit(&mut self, config: &Config) -> Result<PowerAudit> {
+        let mut audit = PowerAudit::new();
+        audit.add(self.audit_idle_step("baseline", config, true));
+        self.disable_presence_sensor()?;
+        audit.add(self.audit_idle_step("presence sensor off", config, true));
+        self.enable_presence_sensor()?;
+        audit.add(self.audit_idle_step("no light sensor polling", config, false));
+        match power_audit::set_cpu_freq_mhz(REDUCED_CPU_FREQ_MHZ) {
+            Ok(()) => {
+                audit.add(self.audit_idle_step("reduced CPU clock", config, true));
+                power_audit::set_cpu_freq_mhz(DEFAULT_CPU_FREQ_MHZ)?;
+            }
+            Err(e) => audit.skip("reduced CPU clock", e.to_string())
+        }
+        Ok(audit)
+    }
+
+    /// Stress test: `transitions` back-to-back presence changes on a scratch state with simulated time;
+    /// the LEDs follow. Has to run with the light off.
+    pub fn run_stress_test(&mut self, state: &State, transitions: u32) -> Result<StressMonitor> {
+        let mut scratch = State::new(state.config.clone());
+        // dark without a light sensor - there is no local time for a fallback dark window
+        scratch.light_sensor_available = false;
+        // no "Powering up" lines for thousands of transitions
+        let log_level = log::max_level();
+        log::set_max_level(log::LevelFilter::Warn);
+        let result = self.drive_transitions(&mut scratch, transitions);
+        log::set_max_level(log_level);
+        self.restore_led_duties(state)?;
+        result
+    }
+
+    fn drive_transitions(&mut self, scratch: &mut State, transitions: u32) -> Result<StressMonitor> {
+        let mut monitor = StressMonitor::new();
+        let mut hold_ticks = HoldTicks::new(transitions);
+        let mut now = Instant::now();
+        let mut level = Level::Low;
+        for _ in 0..transitions {
+            level = if level == Level::High { Level::Low } else { Level::High };
+            for _ in 0..hold_ticks.next() {
+                now += STRESS_TICK;
+                self.stress_tick(scratch, level, now, &mut monitor)?;
+            }
+            monitor.transition_done();
+        }
+        for _ in 0..MAX_SETTLE_TICKS {
+            if scratch.phase() == Phase::Off {
+                break;
+            }
+            now += STRESS_TICK;
+            self.stress_tick(scratch, Level::Low, now, &mut monitor)?;
+        }
+        monitor.finish(scratch.phase());
+        Ok(monitor)
+    }
+
+    fn stress_tick(&mut self, scratch: &mut State, level: Level, now: Instant, monitor: &mut StressMonitor) -> Result<()> {
+        scratch.apply_presence(level, now);
+        scratch.calc_dimm_progress_at(now);
+        self.apply_led_power_level(scratch)?;
+        monitor.check(Sample {
+            stage: scratch.led_power_stage(),
+            target_stage: scratch.ramp.target_stage(),
+            duty: scratch.duty,
+        });
+        Ok(())
+    }
+
+    // the idle loop (light off) for one audit step
+    fn audit_idle_step(&mut self, name: &'static str, config: &Config, poll_light_sensor: bool) -> AuditStep {
+        let start = Instant::now();
+        let mut iterations = 0;
+        let mut busy = Duration::ZERO;
+        while start.elapsed() < AUDIT_STEP_DURATION {
+            let iteration_start = Instant::now();
+            if poll_light_sensor {
+                let _ = self.read_calibrated_lux(config);
+            }
+            busy += iteration_start.elapsed();
+            iterations += 1;
+            FreeRtos::delay_ms(ON_OFF_REACTION_STEP_DELAY_MS);
+        }
+        AuditStep { name, iterations, busy, duration: start.elapsed() }
+    }
+
+    // a single combined and calibrated lux reading of all available sensors
+    fn read_calibrated_lux(&mut self, config: &Config) -> Result<f32> {
+        let readings = self.light_sensors.iter_mut()
+            .filter_map(|channel| channel.sensor_mut())
+            .map(|sensor| sensor.read())
+            .collect::<Result<Vec<_>>>()?;
+        match combine_readings(&readings, config.lux_combine) {
+            Some(reading) => Ok(reading.lux * config.lux_calibration),
+            None => anyhow::bail!("no ambient light sensor available")
+        }
+    }
+
+    // briefly dims the light to the sampling gap stage and takes an ambient light measurement
+    fn run_sampling_gap(&mut self, state: &mut State, events: &mut EventQueue) -> Result<()> {
+        state.last_sampling_gap = Some(Instant::now());
+        // dark during a sampling gap of the standby glow
+        let gap_stage = if state.phase() == Phase::Off { 0 } else { state.config.sampling_gap_stage };
+        let gap_duty = self.calc_led_power_level(0, gap_stage);
+        self.set_all_led_duties(gap_duty)?;
+        FreeRtos::delay_ms(SAMPLING_GAP_SETTLE_MS);
+        let led_lux_contribution = match state.config.led_crosstalk {
+            _ if gap_stage == 0 => 0.0,
+            Some(curve) => curve.contribution(gap_stage),
+            None => state.config.led_lux_contribution
+        };
+        let result = self.measure_ambient_light_level(state, led_lux_contribution, events);
+        self.restore_led_duties(state)?;
+        result?;
+        log::info!("sampling gap: compensated lux: {:?}", state.light_reading.map(|r| r.lux));
+        Ok(())
+    }
+
+    fn read_presence_sensor_and_apply_phase(&mut self, state: &mut State) {
+        let level = if state.test_presence || state.group_presence {
+            Level::High
+        } else {
+            self.presence_sensor.sensor_pin.get_level()
+        };
+        match state.group_stage {
+            Some(stage) => state.follow_group(level, stage, Instant::now()),
+            None => state.apply_presence(level, Instant::now())
         }
     }
 
     pub fn steer_presence_sensor(&mut self, state: &mut State) -> Result<()> {
-        if state.is_dark_enough_for_operation() || state.phase != Phase::Off {
+        if state.is_dark_enough_for_operation() || state.phase() != Phase::Off {
             self.enable_presence_sensor()?;
         } else {
             self.disable_presence_sensor()?;
@@ -202,6 +1071,13 @@ impl<P1: Pin, P2: Pin> Devices<P1, P2> {
         Ok(())
     }
 
+    /// Switches the presence sensor off for a moment - it is switched on again, if needed
+    pub fn power_cycle_presence_sensor(&mut self, state: &mut State) -> Result<()> {
+        self.disable_presence_sensor()?;
+        FreeRtos::delay_ms(PRESENCE_SENSOR_POWER_CYCLE_MS);
+        self.steer_presence_sensor(state)
+    }
+
     fn enable_presence_sensor(&mut self) -> Result<()> {
         if !self.presence_sensor_power_pin.is_set_high() {
             self.presence_sensor_power_pin.set_high()?;
@@ -216,92 +1092,964 @@ impl<P1: Pin, P2: Pin> Devices<P1, P2> {
         Ok(())
     }
 
+    /// Switches the LED and the presence sensor off, holds these output levels and enters timed deep sleep
+    pub fn enter_deep_sleep(&mut self, duration: Duration) -> Result<()> {
+        self.set_all_led_duties(0)?;
+        self.disable_presence_sensor()?;
+        for led in self.leds.iter_mut() {
+            led.hold(true)?;
+        }
+        unsafe {
+            esp!(gpio_hold_en(self.presence_sensor_power_pin.pin()))?;
+        }
+        deep_sleep(duration)
+    }
+
+    /// Releases the output levels held during a deep sleep - after the LED has been switched off again
+    pub fn release_sleep_hold(&mut self) -> Result<()> {
+        self.set_all_led_duties(0)?;
+        for led in self.leds.iter_mut() {
+            led.hold(false)?;
+        }
+        unsafe {
+            esp!(gpio_hold_dis(self.presence_sensor_power_pin.pin()))?;
+        }
+        Ok(())
+    }
+
+    /// Status of the state and the devices at the end of a loop iteration
+    pub fn status_snapshot(&self, state: &State, latency_monitor: &LatencyMonitor) -> StatusSnapshot {
+        StatusSnapshot {
+            mode: state.mode,
+            dark_enough: state.is_dark_enough_for_operation(),
+            darkness_latched: state.darkness_latch.is_latched(),
+            lux: state.lux_level(),
+            phase: state.phase(),
+            led_power_stage: state.led_power_stage(),
+            target_stage: state.ramp.target_stage(),
+            // use the non-inverted logical value here for better readability
+            duty: state.duty,
+            max_duty: self.max_duty(),
+            light_reading: state.light_reading,
+            saturation_policy: state.config.saturation_policy,
+            light_sensor_failures: state.light_sensor_failures,
+            light_sensor_available: state.light_sensor_available,
+            fallback_dark_window: TimeWindow::new(state.config.fallback_dark_from, state.config.fallback_dark_until),
+            presence_sensor_enabled: self.presence_sensor_power_pin.is_set_high(),
+            presence: state.presence,
+            reaction_latency_ms: latency_monitor.last_ms(),
+            led_watts: Some(state.energy_stats.watts()).filter(|_| state.config.led_full_watts > 0.0),
+            energy_today_wh: state.energy_stats.today_wh(),
+        }
+    }
+
+    /// Average level [0..=1] of the LED channels as written by the last update (effects aside) - for the energy estimate
+    pub fn output_level(&self, state: &State) -> f32 {
+        let duties = [state.duty, state.second_segment.duty];
+        let duty_sum: u32 = (0..self.leds.len())
+            .map(|channel| self.mix_duty(channel, duties[state.segment_of_channel(channel)].max(self.glow_duty)).min(self.cap()))
+            .sum();
+        duty_sum as f32 / (self.leds.len() as u32 * self.max_duty()) as f32
+    }
+
     pub fn apply_led_power_level(&mut self, bar_state: &mut State) -> Result<()> {
-        bar_state.duty = self.calc_led_power_level(bar_state.led_power_stage);
+        let color_mix = bar_state.color_mix();
+        let color_changed = color_mix != self.color_mix;
+        if color_changed {
+            self.color_mix = color_mix;
+            log::info!("LED color mix: {:?}", color_mix.map(|share| (share * 100.0).round() as u32));
+        }
+        let brightness_pct = bar_state.brightness_pct();
+        if let Some(level) = bar_state.effects.level(Instant::now()) {
+            for channel in 0..self.leds.len() {
+                let full_duty = self.calc_led_power_level(channel, LED_MAX_POWER_STAGE) * brightness_pct / 100;
+                self.set_led_duty(channel, (full_duty as f32 * level).round() as u32)?;
+            }
+            self.effect_shown = true;
+            return Ok(());
+        }
+        let glow_duty = self.standby_glow_duty(bar_state);
+        let glow_changed = glow_duty != self.glow_duty;
+        self.glow_duty = glow_duty;
+        // back from an effect or a changed glow: all duties have to be written again
+        let rewrite = color_changed || glow_changed || std::mem::take(&mut self.effect_shown);
+        let stages = [bar_state.led_power_stage(), bar_state.second_segment.ramp.stage()];
+        let previous = [bar_state.duty, bar_state.second_segment.duty];
+        let mut duties = previous;
+        for channel in 0..self.leds.len() {
+            let segment = bar_state.segment_of_channel(channel);
+            let duty = self.apply_min_duty(self.calc_led_power_level(channel, stages[segment]) * brightness_pct / 100);
+            if bar_state.config.strip_effect == StripEffect::Chase && bar_state.config.led_strip_pixels > 0 {
+                // the stage moves the edge of the light, the pixels behind it are at the configured brightness
+                let fill = stages[segment] as f32 / LED_MAX_POWER_STAGE as f32;
+                let full_duty = (self.calc_led_power_level(channel, LED_MAX_POWER_STAGE) * brightness_pct / 100).min(self.cap());
+                self.leds[channel].set_fill(fill, bar_state.config.chase_reverse, full_duty)?;
+            } else if !bar_state.config.led_hw_fade {
+                self.set_led_duty(channel, duty.max(glow_duty))?;
+            } else if duty != previous[segment] || rewrite {
+                // the hardware follows the ramp one segment behind - piecewise linear along the dimming curve
+                self.fade_led_duty(channel, duty.max(glow_duty), HW_FADE_SEGMENT_MS)?;
+            }
+            duties[segment] = duty;
+        }
+        [bar_state.duty, bar_state.second_segment.duty] = duties;
+        Ok(())
+    }
+
+    // the state's duties are the logical ones (0 in the Off phase) - the glow only shows on the LEDs
+    fn standby_glow_duty(&self, state: &State) -> u32 {
+        if !state.standby_glow_active() {
+            return 0;
+        }
+        self.apply_min_duty((self.max_duty() as f32 * state.config.standby_glow_pct / 100.0).round() as u32)
+    }
+
+    /// Duty of an LED channel for the power stage - the tail of a fade doesn't go below the minimum duty
+    fn calc_led_power_level(&mut self, channel: usize, power_stage: u32) -> u32 {
+        let duty = self.animation.tick(power_stage).get(channel).copied().unwrap_or(0);
+        self.apply_min_duty(duty)
+    }
+
+    // also for duties scaled after the calculation (brightness)
+    fn apply_min_duty(&self, duty: u32) -> u32 {
+        self.min_duty_policy.apply(duty, self.min_duty)
+    }
 
-        // We are using a gate driver circuit to feed the PWM signal to a N-channel MOSFET.
-        // Because of the nature of that circuit we need to invert our signal. 
-        // (MOSFET's gate is open when we have our IO pin on low).
-        let inverted_duty = self.led_driver.get_max_duty() - bar_state.duty;
+    fn max_duty(&self) -> u32 {
+        self.leds[0].max_duty()
+    }
+
+    fn set_led_duty(&mut self, channel: usize, duty: u32) -> Result<()> {
+        let duty = self.mix_duty(channel, duty);
+        self.leds[channel].set_duty(duty.min(self.cap()))
+    }
 
-        self.led_driver.set_duty(inverted_duty)?;
+    // channel share of the logical duty
+    fn mix_duty(&self, channel: usize, duty: u32) -> u32 {
+        (duty as f32 * self.color_mix[channel]).round() as u32
+    }
+
+    fn set_all_led_duties(&mut self, duty: u32) -> Result<()> {
+        for channel in 0..self.leds.len() {
+            self.set_led_duty(channel, duty)?;
+        }
         Ok(())
     }
 
-    /// Step comes in range [0..LED_MAX_POWER_STAGE]
-    /// translates to power level in range [0..`max_duty`] via a logarithmic curve,
-    /// scaled so that the highest step reaches `self.led_driver.get_max_duty()`
-    /// ```
-    /// y - duty
-    /// x - power stage [0..LED_MAX_POWER_STAGE]
-    /// z - scale factor to reach LED driver max_duty when we are at 100%
-    /// ```
-    fn calc_led_power_level(&self, power_stage: u32) -> u32 {
-        (Self::led_power_curve(power_stage) * self.led_power_curve_scale_factor).round() as u32
+    // back to the duties of the state after a measurement
+    fn restore_led_duties(&mut self, state: &State) -> Result<()> {
+        let duties = [state.duty, state.second_segment.duty];
+        for channel in 0..self.leds.len() {
+            self.set_led_duty(channel, duties[state.segment_of_channel(channel)].max(self.glow_duty))?;
+        }
+        Ok(())
     }
 
-    fn calc_led_power_curve_scale_factor(led_driver_max_duty: u32) -> f32 {
-        (led_driver_max_duty as f32) / (Self::led_power_curve(LED_MAX_POWER_STAGE))
+    fn fade_led_duty(&mut self, channel: usize, duty: u32, fade_ms: u32) -> Result<()> {
+        let duty = self.mix_duty(channel, duty);
+        self.leds[channel].fade_to(duty.min(self.cap()), fade_ms)
     }
+}
 
-    // pure (unscaled) logarithmic curve
-    fn led_power_curve(power_stage: u32) -> f32 {
-        f32::ln((power_stage as f32) / 50.0 + 1.0)
+/// Health check of a freshly updated firmware: all components up, the ambient light sensor delivers readings and
+/// the LED duty can be set. (No network check - the ESP32-H2 has no Wi-Fi, and a network may come up much later.)
+#[cfg(feature = "ota")]
+fn ota_health_check<P1: Pin, P2: Pin>(bringup: &Bringup, devices: &mut Devices<P1, P2>, state: &State) -> Result<()> {
+    if bringup.failed_count() > 0 {
+        anyhow::bail!("{} component(s) failed the bring-up", bringup.failed_count());
     }
+    if state.light_reading.is_none() {
+        anyhow::bail!("no ambient light reading");
+    }
+    // otherwise it was part of the bring-up
+    if !state.config.led_self_test {
+        devices.run_led_self_test()?;
+    }
+    Ok(())
 }
 
-fn log_status<P1: Pin, P2: Pin>(state: &State, devices: &Devices<P1, P2>, last_log_time: &mut Instant) {
+fn log_status(status: &SharedStatus, last_log_time: &mut Instant) {
     let now = Instant::now();
     if last_log_time.add(STATUS_LOG_INTERVAL) <= now {
         *last_log_time = now;
-        log::info!("{} , duty: {}/{} | Hardware: Presence sensor: enabled: {}, Sensor-signal: {:?}", 
-            state,
-            state.duty, // use the non-inverted logical value here for better readability
-            devices.led_driver.get_max_duty(),
-            devices.presence_sensor_power_pin.is_set_high(),
-            devices.presence_sensor.sensor_pin.get_level(),
-            
-        )
+        if let Some(snapshot) = status.get() {
+            log::info!("{}", snapshot);
+        }
     }
 }
 
+// step delay of the current phase, shortened if the camera trigger needs to act earlier (`camera_action`) - but at
+// least 1 ms, as a delay of 0 ticks would not yield to the lower priority tasks (idle task watchdog)
+fn loop_delay_ms(state: &State, camera_action: Option<Duration>) -> u32 {
+    let step_delay_ms = state.duty_step_delay_ms();
+    match camera_action {
+        Some(d) => step_delay_ms.min(d.as_millis() as u32).max(1),
+        None => step_delay_ms
+    }
+}
+
+/// Wipes the stored settings and restarts with the defaults
+#[cfg(feature = "switch")]
+fn factory_reset<P1: Pin, P2: Pin>(devices: &mut Devices<P1, P2>) -> Result<()> {
+    log::warn!("factory reset");
+    factory_reset::wipe_nvs()?;
+    devices.blink_leds(FACTORY_RESET_BLINKS, FACTORY_RESET_BLINK_MS)?;
+    restart();
+}
+
+fn profile_store(config_store: Option<&mut ConfigStore>) -> Result<&mut ConfigStore> {
+    config_store.ok_or_else(|| anyhow!("profiles need the configuration store (NVS not available)"))
+}
+
+// without config store (NVS not available) changes are applied until the next restart only
+fn save_config(config_store: Option<&mut ConfigStore>, config: &Config) -> Result<()> {
+    match config_store {
+        Some(store) => store.save(config),
+        None => {
+            log::warn!("configuration store not available - change is not persisted");
+            Ok(())
+        }
+    }
+}
+
+/// Applies a command of a network or radio source (TCP console, REST API, BLE, MQTT) - the commands, which need
+/// physical access to the unit, are refused and the credentials are not shown.
+/// `authenticated`: the source checked a credential - otherwise the commands, which need one, are refused too.
+fn apply_remote_command<P1: Pin, P2: Pin>(
+    command: Command,
+    authenticated: bool,
+    state: &mut State,
+    devices: &mut Devices<P1, P2>,
+    status: &SharedStatus,
+    config_store: Option<&mut ConfigStore>,
+) -> Result<()> {
+    if command.is_local_only() {
+        anyhow::bail!("{:?} is accepted on the serial console only", command);
+    }
+    if command.needs_authentication() && !authenticated {
+        anyhow::bail!("{:?} needs an authenticated source (console, REST API, BLE)", command);
+    }
+    match command {
+        Command::Get(param) => log::info!("{} = {}", param, state.config.get_redacted(param)),
+        #[cfg(feature = "config-transfer")]
+        Command::ExportConfig => log::info!("{}", config_transfer::export(&state.config, false)),
+        command => apply_command(command, state, devices, status, config_store, None)?
+    }
+    Ok(())
+}
+
+/// `safety_cap_store`: for the serial console only (see [apply_remote_command])
+fn apply_command<P1: Pin, P2: Pin>(
+    command: Command,
+    state: &mut State,
+    devices: &mut Devices<P1, P2>,
+    status: &SharedStatus,
+    config_store: Option<&mut ConfigStore>,
+    safety_cap_store: Option<&mut SafetyCapStore>,
+) -> Result<()> {
+    match command {
+        Command::Status => match status.get() {
+            Some(snapshot) => log::info!("{}", snapshot),
+            None => log::info!("no status yet")
+        },
+        Command::Save => {
+            save_config(config_store, &state.config)?;
+            log::info!("configuration saved");
+        }
+        Command::Reboot => {
+            log::warn!("restarting");
+            FreeRtos::delay_ms(REBOOT_DELAY_MS);
+            restart();
+        }
+        Command::ExportConfig => {
+            #[cfg(feature = "config-transfer")]
+            log::info!("{}", config_transfer::export(&state.config, true));
+            #[cfg(not(feature = "config-transfer"))]
+            anyhow::bail!("firmware built without the feature config-transfer");
+        }
+        Command::ImportConfig(json) => {
+            #[cfg(feature = "config-transfer")]
+            {
+                let imported = config_transfer::import(&state.config, json.as_bytes())?;
+                let previous = std::mem::replace(&mut state.config, imported);
+                state.apply_config();
+                devices.reconfigure_light_sensors(&previous, &state.config);
+                devices.configure_led(&state.config);
+                save_config(config_store, &state.config)?;
+                log::info!("configuration imported: {:?}", state.config);
+            }
+            #[cfg(not(feature = "config-transfer"))]
+            anyhow::bail!("firmware built without the feature config-transfer - can't import {}", json);
+        }
+        Command::Get(param) => {
+            log::info!("{} = {}", param, state.config.get(param));
+        }
+        Command::Set(Param::Profile, name) if !name.is_empty() => {
+            let store = profile_store(config_store)?;
+            let values = store.load_profile(&name)?.ok_or_else(|| anyhow!("unknown profile {}", name))?;
+            let applied = profile::apply(&state.config, &values)?;
+            let previous = std::mem::replace(&mut state.config, applied);
+            state.config.set(Param::Profile, &name)?;
+            state.apply_config();
+            devices.reconfigure_light_sensors(&previous, &state.config);
+            devices.configure_led(&state.config);
+            store.save(&state.config)?;
+            log::info!("profile {} applied: {}", name, values);
+        }
+        Command::ListProfiles => {
+            let names = match config_store {
+                Some(store) => store.profile_names()?,
+                None => vec![]
+            };
+            log::info!("profiles: {} (selected: {})", names.join(", "), state.config.profile);
+        }
+        Command::SaveProfile(name) => {
+            let store = profile_store(config_store)?;
+            let values = profile::encode(&state.config);
+            store.save_profile(&name, &values)?;
+            state.config.profile = name.clone();
+            state.config.touch();
+            store.save(&state.config)?;
+            log::info!("profile {} saved: {}", name, values);
+        }
+        Command::DeleteProfile(name) => {
+            let store = profile_store(config_store)?;
+            if !store.delete_profile(&name)? {
+                anyhow::bail!("unknown profile {}", name);
+            }
+            if state.config.profile == name {
+                state.config.profile.clear();
+                store.save(&state.config)?;
+            }
+            log::info!("profile {} deleted", name);
+        }
+        Command::HuePair => {
+            #[cfg(feature = "hue")]
+            {
+                if state.config.hue_user.is_empty() {
+                    state.config.set(Param::HueUser, &hue::new_user_name())?;
+                    save_config(config_store, &state.config)?;
+                }
+                hue::open_pairing(Instant::now());
+                log::info!("Hue pairing open for {:?} - search for devices with the voice assistant now", hue::PAIRING_WINDOW);
+            }
+            #[cfg(not(feature = "hue"))]
+            anyhow::bail!("firmware built without the feature hue");
+        }
+        Command::Set(param, value) => {
+            state.config.set(param, &value)?;
+            state.apply_config();
+            if param.configures_light_sensor() {
+                devices.configure_light_sensors(&state.config);
+            }
+            devices.configure_led(&state.config);
+            save_config(config_store, &state.config)?;
+            log::info!("{} set to {}", param, state.config.get_redacted(param));
+        }
+        Command::Mode(None) => {
+            log::info!("mode = {}", state.mode);
+        }
+        Command::Mode(Some(mode)) => {
+            state.mode = mode;
+            log::info!("mode set to {}", state.mode);
+        }
+        Command::PowerCyclePresenceSensor => {
+            devices.power_cycle_presence_sensor(state)?;
+            log::info!("presence sensor power-cycled");
+        }
+        Command::CalibrateLux(reference_lux) => {
+            if !reference_lux.is_finite() || reference_lux <= 0.0 {
+                anyhow::bail!("the reference lux level must be a number greater than 0");
+            }
+            let Some(reading) = state.light_reading.filter(|_| state.phase() == Phase::Off) else {
+                anyhow::bail!("calibration needs a current light reading with the light switched off");
+            };
+            let uncalibrated_lux = reading.lux / state.config.lux_calibration;
+            if !uncalibrated_lux.is_finite() || uncalibrated_lux <= 0.0 {
+                anyhow::bail!("calibration needs some light on the sensor (reading is 0 lux)");
+            }
+            state.config.lux_calibration = reference_lux / uncalibrated_lux;
+            state.config.touch();
+            save_config(config_store, &state.config)?;
+            log::info!("lux_calibration set to {} (sensor: {:.2} lux, reference: {:.2} lux)",
+                state.config.lux_calibration, uncalibrated_lux, reference_lux);
+        }
+        Command::CalibrateCrosstalk => {
+            if state.phase() != Phase::Off {
+                anyhow::bail!("crosstalk calibration needs the light switched off (and a dark room)");
+            }
+            let curve = devices.calibrate_crosstalk(state)?;
+            state.config.led_crosstalk = Some(curve);
+            state.config.touch();
+            save_config(config_store, &state.config)?;
+            log::info!("led_crosstalk set to {}", curve);
+        }
+        Command::PowerAudit => {
+            if state.phase() != Phase::Off {
+                anyhow::bail!("the power audit needs the light switched off");
+            }
+            for line in devices.run_power_audit(&state.config)?.report() {
+                log::info!("{}", line);
+            }
+        }
+        Command::Effect(Some(effect)) => state.effects.start(effect, Instant::now()),
+        Command::Effect(None) => state.effects.stop(),
+        Command::StressTest(transitions) => {
+            if state.phase() != Phase::Off {
+                anyhow::bail!("the stress test needs the light switched off");
+            }
+            if !(1..=MAX_STRESS_TRANSITIONS).contains(&transitions) {
+                anyhow::bail!("transitions must be in the range 1..={}", MAX_STRESS_TRANSITIONS);
+            }
+            let monitor = devices.run_stress_test(state, transitions)?;
+            for line in monitor.report() {
+                log::info!("{}", line);
+            }
+            if monitor.violations() > 0 {
+                anyhow::bail!("stress test failed");
+            }
+        }
+        Command::Preset(preset) => {
+            let previous = state.config.clone();
+            preset.apply(&mut state.config);
+            state.apply_config();
+            devices.reconfigure_light_sensors(&previous, &state.config);
+            devices.configure_led(&state.config);
+            save_config(config_store, &state.config)?;
+            log::info!("preset {} applied: {:?}", preset, state.config);
+        }
+        Command::ProvisionDutyCap(percent) => {
+            if !in_provisioning_window() {
+                anyhow::bail!("the duty cap can only be provisioned within 5 minutes after a power-up (not after a restart)");
+            }
+            if !(1..=NO_CAP_PCT).contains(&percent) {
+                anyhow::bail!("duty cap must be in the range 1..={}", NO_CAP_PCT);
+            }
+            let Some(store) = safety_cap_store else {
+                anyhow::bail!("provisioning store not available");
+            };
+            store.save(percent)?;
+            devices.set_safety_cap(percent);
+            log::info!("duty cap provisioned: {} %", percent);
+        }
+        Command::Ota(url) => {
+            #[cfg(feature = "ota")]
+            {
+                // without a token the REST API is open to everyone in the network - no firmware from there
+                if state.config.api_token.is_empty() {
+                    anyhow::bail!("firmware updates need an api_token");
+                }
+                ota::start_update(url)?;
+            }
+            #[cfg(not(feature = "ota"))]
+            anyhow::bail!("firmware built without the feature ota - can't update from {}", url);
+        }
+        Command::Radar => {
+            #[cfg(feature = "ld2410-uart")]
+            match devices.radar.as_ref().map(|radar| radar.latest(Instant::now())) {
+                Some(Some(report)) => log::info!("radar: {}", report),
+                Some(None) => log::info!("radar: no recent report"),
+                None => anyhow::bail!("radar UART not available")
+            }
+            #[cfg(not(feature = "ld2410-uart"))]
+            anyhow::bail!("firmware built without the feature ld2410-uart");
+        }
+        Command::PollStats => {
+            for line in devices.poll_scheduler.report() {
+                log::info!("{}", line);
+            }
+        }
+        Command::EnergyStats => {
+            if state.config.led_full_watts <= 0.0 {
+                anyhow::bail!("energy estimate needs led_full_watts");
+            }
+            for line in state.energy_stats.report() {
+                log::info!("{}", line);
+            }
+        }
+        Command::LuxStats => {
+            for line in state.lux_stats.report() {
+                log::info!("{}", line);
+            }
+            log::info!("nightly lux minimums (oldest first): {:?}, baseline: {:?}, effective lux threshold: {:.2}",
+                state.night_baseline.minimums().collect::<Vec<_>>(), state.night_baseline.baseline(), state.lux_threshold());
+        }
+        Command::SuggestLuxThreshold => {
+            for line in state.lux_histogram.report() {
+                log::info!("{}", line);
+            }
+            match state.lux_histogram.suggest_threshold() {
+                Some(lux) => log::info!("suggested lux_threshold: {:.2} (current: {})", lux, state.config.lux_threshold),
+                None => log::info!("not enough data for a lux_threshold suggestion yet (used: {}, idle: {})",
+                    state.lux_histogram.used_count(), state.lux_histogram.idle_count())
+            }
+        }
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     // It is necessary to call this function once. Otherwise some patches to the runtime
     // implemented by esp-idf-sys might not link properly. See https://github.com/esp-rs/esp-idf-template/issues/71
     esp_idf_svc::sys::link_patches();
 
     // looks like we can't adjust the maximum loglevel (which is Info) as it seems to be hard-coded in EspLogger 
-    esp_idf_svc::log::EspLogger::initialize_default();
+    log_mirror::initialize();
 
     log::info!("starting up");
 
     let peripherals = Peripherals::take().unwrap();
+    let mut bringup = Bringup::new();
+    let nvs_partition = bringup.init_with_retries("nvs", EspDefaultNvsPartition::take);
+    let mut config_store = nvs_partition.as_ref()
+        .and_then(|p| bringup.init_with_retries("config store", || ConfigStore::new(p.clone())));
+    let config = config_store.as_ref()
+        .and_then(|store| bringup.init_with_retries("config", || store.load()))
+        .unwrap_or_else(|| {
+            log::warn!("continuing with the default configuration");
+            Config::default()
+        });
+    log::info!("{:?}", config);
+    let mut lux_stats_store = nvs_partition.as_ref()
+        .and_then(|p| bringup.init_with_retries("lux stats store", || LuxStatsStore::new(p.clone())));
+    let mut night_baseline_store = nvs_partition.as_ref()
+        .and_then(|p| bringup.init_with_retries("night baseline store", || NightBaselineStore::new(p.clone())));
+    let mut safety_cap_store = nvs_partition.as_ref()
+        .and_then(|p| bringup.init_with_retries("safety cap store", || SafetyCapStore::new(p.clone())));
 
+    let mut light_sensors = vec![LightSensorChannel::new(LightSensorBus::new(
+        I2cPort::I2c0(peripherals.i2c0),
+        peripherals.pins.gpio5.downgrade(),
+        peripherals.pins.gpio4.downgrade(),
+    ))];
+    // the second I2C bus serves either a second ambient light sensor or the temperature sensor
+    let mut temperature_sensor = None;
+    if config.second_light_sensor {
+        light_sensors.push(LightSensorChannel::new(LightSensorBus::new(
+            I2cPort::I2c1(peripherals.i2c1),
+            peripherals.pins.gpio13.downgrade(),
+            peripherals.pins.gpio14.downgrade(),
+        )));
+    } else if config.temperature_sensor {
+        let mut bus = LightSensorBus::new(I2cPort::I2c1(peripherals.i2c1), peripherals.pins.gpio13.downgrade(), peripherals.pins.gpio14.downgrade());
+        temperature_sensor = bringup.init("temperature sensor", || TemperatureSensor::new(bus.driver()?));
+    }
+    for channel in &light_sensors {
+        bringup.report(channel.name(), channel.is_available());
+    }
+    if !light_sensors.iter().any(|channel| channel.is_available()) {
+        log::error!("continuing in degraded mode without darkness gating");
+    }
+
+    let presence_sensor = bringup.init("presence sensor", || init_presence_sensor(peripherals.pins.gpio1));
+    let presence_sensor_power_pin = bringup.init("presence sensor power", || init_output_pin(peripherals.pins.gpio12));
+    // the dither timer runs as long as it is kept
+    let (leds, _ditherer): (Vec<Box<dyn LedChannel>>, Option<Ditherer>) = if config.led_strip_pixels > 0 {
+        let strip = bringup.init("LED strip", || AddressableStrip::new(
+            peripherals.rmt.channel0, peripherals.pins.gpio3, config.led_strip_pixels as usize, config.led_strip_rgbw));
+        (strip.into_iter().map(|strip| Box::new(strip) as Box<dyn LedChannel>).collect(), None)
+    } else {
+        let led = bringup.init("LED driver", || {
+            let led_pin = peripherals.pins.gpio11;
+            let gpio = led_pin.pin();
+            let driver = init_led_driver(peripherals.ledc.channel0, peripherals.ledc.timer0, led_pin, config.pwm_freq_hz, config.pwm_resolution_bits)?;
+            Ok(LedOutput::new(driver, gpio, LED_LEDC_CHANNELS[0], config.invert_pwm, config.led_dither))
+        });
+        #[cfg(not(feature = "second-led"))]
+        let (second_led, third_led, fourth_led) = {
+            if config.second_led || config.tunable_white || config.rgbw {
+                log::warn!("firmware built without the feature second-led - only the first LED output is driven");
+            }
+            (None, None, None)
+        };
+        // second segment, cold white or green channel
+        #[cfg(feature = "second-led")]
+        let second_led = if config.second_led || config.tunable_white || config.rgbw {
+            bringup.init("second LED driver", || {
+                let led_pin = peripherals.pins.gpio2;
+                let gpio = led_pin.pin();
+                let driver = init_led_driver(peripherals.ledc.channel1, peripherals.ledc.timer1, led_pin, config.pwm_freq_hz, config.pwm_resolution_bits)?;
+                Ok(LedOutput::new(driver, gpio, LED_LEDC_CHANNELS[1], config.invert_pwm, config.led_dither))
+            })
+        } else {
+            None
+        };
+        // blue and white channel
+        #[cfg(feature = "second-led")]
+        let (third_led, fourth_led) = if config.rgbw {
+            let third_led = bringup.init("third LED driver", || {
+                let led_pin = peripherals.pins.gpio3;
+                let gpio = led_pin.pin();
+                let driver = init_led_driver(peripherals.ledc.channel2, peripherals.ledc.timer2, led_pin, config.pwm_freq_hz, config.pwm_resolution_bits)?;
+                Ok(LedOutput::new(driver, gpio, LED_LEDC_CHANNELS[2], config.invert_pwm, config.led_dither))
+            });
+            let fourth_led = bringup.init("fourth LED driver", || {
+                let led_pin = peripherals.pins.gpio8;
+                let gpio = led_pin.pin();
+                let driver = init_led_driver(peripherals.ledc.channel3, peripherals.ledc.timer3, led_pin, config.pwm_freq_hz, config.pwm_resolution_bits)?;
+                Ok(LedOutput::new(driver, gpio, LED_LEDC_CHANNELS[3], config.invert_pwm, config.led_dither))
+            });
+            (third_led, fourth_led)
+        } else {
+            (None, None)
+        };
+        bringup.init("LED fade service", init_led_fade_service);
+        // up to the first missing driver - keeps the channel order
+        let outputs = [led, second_led, third_led, fourth_led].into_iter()
+            .map_while(|led| led)
+            .collect::<Vec<_>>();
+        let dither_slots = outputs.iter().filter_map(LedOutput::dither_slot).collect::<Vec<_>>();
+        let ditherer = if dither_slots.is_empty() {
+            None
+        } else {
+            bringup.init("LED dithering", || Ditherer::start(dither_slots))
+        };
+        (outputs.into_iter().map(|led| Box::new(led) as Box<dyn LedChannel>).collect(), ditherer)
+    };
+    #[cfg(feature = "camera-trigger")]
+    let mut camera_trigger = bringup.init("camera trigger", || init_output_pin(peripherals.pins.gpio10.downgrade_output()))
+        .map(CameraTrigger::new);
+    let console = bringup.init("console", Console::start);
+    let presence_notification = Notification::new();
+    #[cfg(feature = "switch")]
+    let mut switch = bringup.init("switch", || Switch::new(peripherals.pins.gpio22.downgrade_input(), presence_notification.notifier()));
+    #[cfg(feature = "test-button")]
+    let mut test_trigger = bringup.init("test button", || TestTrigger::new(peripherals.pins.gpio0.downgrade_input(), presence_notification.notifier()));
+
+    // essential components
+    let (Some(presence_sensor), Some(presence_sensor_power_pin), Some(leds)) =
+        (presence_sensor, presence_sensor_power_pin, Some(leds).filter(|leds| !leds.is_empty())) else {
+        bringup.log_summary();
+        anyhow::bail!("essential components are missing - giving up");
+    };
     let mut devices = Devices::new(
-        init_presence_sensor(peripherals.pins.gpio1)?,
-        init_output_pin(peripherals.pins.gpio12)?,
-        init_veml7700(
-            peripherals.i2c0,
-            peripherals.pins.gpio5,
-            peripherals.pins.gpio4,
-        )?,
-        init_led_driver(
-            peripherals.ledc.channel0,
-            peripherals.ledc.timer0,
-            peripherals.pins.gpio11,
-        )?,
+        presence_sensor,
+        presence_sensor_power_pin,
+        light_sensors,
+        leds,
+        Box::new(CurveRamp::new()),
+        temperature_sensor,
     );
+    #[cfg(feature = "ld2410-uart")]
+    {
+        devices.radar = bringup.init("radar UART", || Ld2410Uart::new(peripherals.uart1, peripherals.pins.gpio25.downgrade_input()));
+    }
 
+    // fail safe: if we can't read the provisioned value, we don't know the limit of the installation
+    let safety_cap_pct = match safety_cap_store.as_ref().map(|store| store.load()) {
+        Some(Ok(percent)) => percent,
+        Some(Err(e)) => {
+            log::error!("could not load the duty safety cap: {} - using {} %", e, FALLBACK_CAP_PCT);
+            FALLBACK_CAP_PCT
+        }
+        None => FALLBACK_CAP_PCT
+    };
+    devices.set_safety_cap(safety_cap_pct);
+    devices.release_sleep_hold()?;
     log::info!("peripherals initialized");
-    let mut state = State::new();
+    let mut state = State::new(config);
+    let mut stored_always_on = match config_store.as_ref().map(|store| store.load_always_on()) {
+        Some(Ok(always_on)) => always_on,
+        Some(Err(e)) => {
+            log::warn!("could not load the always-on mode: {}", e);
+            false
+        }
+        None => false
+    };
+    let mut failed_always_on_store: Option<Instant> = None;
+    if state.config.persist_always_on && stored_always_on {
+        state.mode = Mode::ForcedOn;
+        log::info!("always-on mode restored");
+    }
+    if let Some(lux_stats_store) = lux_stats_store.as_ref().filter(|_| state.config.lux_stats_persist) {
+        match lux_stats_store.load() {
+            Ok(history) => state.lux_stats.restore(history),
+            Err(e) => log::warn!("could not load the lux statistics: {}", e)
+        }
+    }
+    if let Some(night_baseline_store) = &night_baseline_store {
+        match night_baseline_store.load() {
+            Ok(minimums) => state.night_baseline.restore(minimums),
+            Err(e) => log::warn!("could not load the nightly lux minimums: {}", e)
+        }
+    }
+    state.light_sensor_available = devices.is_light_sensor_available();
+    devices.configure_light_sensors(&state.config);
+    devices.configure_led(&state.config);
+    // not after a daylight sleep - the wake-ups would flash the light every time
+    if state.config.led_self_test && !woke_from_daylight_sleep() {
+        bringup.init("LED self-test", || devices.run_led_self_test());
+    }
+    devices.fast_start_light_measurement(&mut state);
+    devices.steer_presence_sensor(&mut state)?;
     let mut last_log_time = Instant::now().sub(Duration::from_mins(1));
+    let mut events = EventQueue::default();
+    let status = SharedStatus::default();
+    // without the interrupt the presence sensor is still polled every loop iteration - only with a longer reaction time
+    let presence_interrupt = bringup.init("presence interrupt",
+        || devices.presence_sensor.subscribe_rising_edge(presence_notification.notifier())).is_some();
+    // the IP interface of the network features below - they start without it, but reach nothing until it is attached
+    #[cfg(feature = "thread")]
+    if let Some(nvs) = nvs_partition.clone().filter(|_| !state.config.thread_dataset.is_empty()) {
+        bringup.init("Thread network", || thread_net::start(peripherals.modem, nvs, &state.config.thread_dataset));
+    }
+    #[cfg(feature = "rest-api")]
+    let mut rest_api = bringup.init("REST API", || rest_api::RestApi::start(status.clone(), &state.config));
+    #[cfg(feature = "tcp-console")]
+    let tcp_console = if state.config.api_token.is_empty() {
+        log::warn!("TCP console disabled - it needs an api_token");
+        None
+    } else {
+        bringup.init("TCP console", || tcp_console::TcpConsole::start(&state.config.api_token))
+    };
+    #[cfg(feature = "mdns")]
+    let _mdns = bringup.init("mDNS", || mdns::advertise(&state.config.hostname));
+    let mut clock = Clock::new(&state.config.timezone);
+    #[cfg(feature = "webhooks")]
+    let webhooks = bringup.init("webhooks", webhook::Webhooks::start);
+    #[cfg(feature = "ble")]
+    let mut ble = if state.config.ble_pin > 0 {
+        bringup.init("BLE configuration service", || ble::BleConfigService::start(&state.config))
+    } else {
+        None
+    };
+    bringup.log_summary();
+    #[cfg(feature = "ota")]
+    if ota::is_pending_verification() {
+        let health = ota_health_check(&bringup, &mut devices, &state);
+        if let Err(e) = ota::verify_running_image(health) {
+            log::warn!("could not confirm the running firmware: {}", e);
+        }
+    }
+    for component in bringup.failed_components() {
+        events.push(Event::BringupFailed(component));
+    }
+    let mut latency_monitor = LatencyMonitor::new();
+    let mut occlusion_detector = OcclusionDetector::new();
+    let mut presence_session = PresenceSession::new();
+    let mut daylight_sleep = DaylightSleep::new(woke_from_daylight_sleep());
+    #[cfg(feature = "syslog")]
+    let mut syslog_link = syslog::SyslogLink::new();
+    #[cfg(feature = "remote-diagnostics")]
+    let mut remote_diagnostics = remote_diagnostics::RemoteDiagnostics::new();
+    #[cfg(any(feature = "remote-diagnostics", feature = "webhooks"))]
+    let mut export_filter = event::ExportFilter::new();
+    #[cfg(feature = "mqtt")]
+    let mut mqtt_link = mqtt::MqttLink::new();
+    #[cfg(feature = "group")]
+    let mut group_sync = group_sync::GroupSync::new(ha_discovery::device_id());
+    #[cfg(feature = "influxdb")]
+    let mut influx_push = influx::InfluxPush::new();
 
     loop {
-        log_status(&state, &devices, &mut last_log_time);
-        FreeRtos::delay_ms(state.duty_step_delay_ms());
-        devices.read_sensors(&mut state)?;
+        while let Some(command) = console.as_ref().and_then(|c| c.poll()) {
+            if let Err(e) = apply_command(command, &mut state, &mut devices, &status, config_store.as_mut(), safety_cap_store.as_mut()) {
+                log::warn!("command failed: {}", e);
+            }
+        }
+        #[cfg(feature = "tcp-console")]
+        while let Some(command) = tcp_console.as_ref().and_then(|c| c.poll()) {
+            if let Err(e) = apply_remote_command(command, true, &mut state, &mut devices, &status, config_store.as_mut()) {
+                log::warn!("command failed: {}", e);
+            }
+        }
+        #[cfg(feature = "rest-api")]
+        while let Some(request) = rest_api.as_ref().and_then(|api| api.poll()) {
+            let result = apply_remote_command(request.command.clone(), true, &mut state, &mut devices, &status, config_store.as_mut());
+            if let Err(e) = &result {
+                log::warn!("REST API command failed: {}", e);
+            }
+            request.reply(&result);
+        }
+        #[cfg(feature = "ble")]
+        while let Some(command) = ble.as_ref().and_then(|ble| ble.poll()) {
+            if let Err(e) = apply_remote_command(command, true, &mut state, &mut devices, &status, config_store.as_mut()) {
+                log::warn!("BLE command failed: {}", e);
+            }
+        }
+        #[cfg(feature = "mqtt")]
+        while let Some(remote) = mqtt_link.poll_command() {
+            let result = remote.command.and_then(|command|
+                apply_remote_command(command, false, &mut state, &mut devices, &status, config_store.as_mut()));
+            if let Err(e) = &result {
+                log::warn!("MQTT command failed: {}", e);
+            }
+            mqtt_link.acknowledge(&state.config, remote.id, &result);
+        }
+        // changes from any command source
+        #[cfg(feature = "rest-api")]
+        if let Some(api) = rest_api.as_mut() {
+            api.update_config(&state.config);
+        }
+        log_status(&status, &mut last_log_time);
+        #[cfg(feature = "camera-trigger")]
+        let camera_action = camera_trigger.as_ref().and_then(|trigger| trigger.time_to_next_action(Instant::now()));
+        #[cfg(not(feature = "camera-trigger"))]
+        let camera_action = None;
+        // a presence edge ends the delay early
+        presence_notification.wait(TickType::new_millis(loop_delay_ms(&state, camera_action) as u64).ticks());
+        devices.poll_scheduler.begin_tick(Instant::now());
+        if presence_interrupt {
+            devices.presence_sensor.rearm_interrupt()?;
+        }
+        #[cfg(feature = "switch")]
+        match switch.as_mut().map(|switch| switch.update(Instant::now())).transpose()?.flatten() {
+            Some(SwitchEvent::Toggle) => {
+                state.mode = if state.mode == Mode::ForcedOn { Mode::Auto } else { Mode::ForcedOn };
+                log::info!("switch: mode set to {}", state.mode);
+            }
+            Some(SwitchEvent::FactoryReset) => if let Err(e) = factory_reset(&mut devices) {
+                log::error!("factory reset failed: {}", e);
+            },
+            None => ()
+        }
+        // the always-on mode from any source
+        let always_on = state.mode == Mode::ForcedOn;
+        if state.config.persist_always_on && always_on != stored_always_on
+            && failed_always_on_store.map_or(true, |t| t.elapsed() >= ALWAYS_ON_STORE_RETRY_INTERVAL) {
+            // without a store there is nothing to retry
+            match config_store.as_mut().map(|store| store.save_always_on(always_on)) {
+                Some(Err(e)) => {
+                    log::warn!("could not store the always-on mode: {} - retrying", e);
+                    failed_always_on_store = Some(Instant::now());
+                }
+                _ => {
+                    stored_always_on = always_on;
+                    failed_always_on_store = None;
+                }
+            }
+        }
+        #[cfg(feature = "test-button")]
+        {
+            state.test_presence = match test_trigger.as_mut() {
+                Some(test_trigger) => test_trigger.update(Instant::now(), &mut events)?,
+                None => false
+            };
+        }
+        clock.set_timezone(&state.config.timezone);
+        let local_time = clock.local_time();
+        if let (None, Some(t)) = (state.local_time, local_time) {
+            log::info!("wall clock set: {}", t);
+        }
+        state.local_time = local_time;
+        #[cfg(feature = "group")]
+        while let Some(message) = mqtt_link.poll_group_message() {
+            group_sync.receive(&state.config, &message, Instant::now());
+        }
+        #[cfg(feature = "group")]
+        {
+            state.group_presence = group_sync.follower_presence(Instant::now());
+            state.group_stage = group_sync.coordinator_stage(Instant::now());
+        }
+        devices.read_sensors(&mut state, &mut events)?;
+        if state.enforce_max_on_time(Instant::now()) {
+            events.push(Event::MaxOnTimeReached);
+        }
+        state.update_darkness_latch(Instant::now());
+        if state.mode == Mode::Auto && state.phase() == Phase::Off && !state.presence {
+            if let Some(lux) = state.lux_level() {
+                if let Some(duration) = daylight_sleep.update(lux, &state.config, Instant::now()) {
+                    log::info!("broad daylight ({:.0} lux) - deep sleep for {:?}", lux, duration);
+                    devices.enter_deep_sleep(duration)?;
+                }
+            }
+        }
         state.calc_dimm_progress();
         devices.apply_led_power_level(&mut state)?;
+        let output_level = devices.output_level(&state);
+        if let Some(day_wh) = state.energy_stats.update(output_level, state.config.led_full_watts, Instant::now()) {
+            log::info!("LED energy of the last 24 hours: {:.1} Wh", day_wh);
+        }
+        latency_monitor.update(state.duty, &state.config, &mut events);
+        #[cfg(feature = "test-button")]
+        if let Some(test_trigger) = test_trigger.as_mut() {
+            test_trigger.check_reaction(state.duty, Instant::now(), &mut events);
+        }
         devices.steer_presence_sensor(&mut state)?;
+        // no presence notifications while someone is working on the installation
+        let hold = Duration::from_secs(state.config.camera_hold_s as u64);
+        #[cfg_attr(not(feature = "camera-trigger"), allow(unused_variables))]
+        let session_started = presence_session.update(state.presence && state.mode != Mode::Maintenance, hold, Instant::now(), &mut events);
+        #[cfg(feature = "camera-trigger")]
+        if let Some(camera_trigger) = camera_trigger.as_mut() {
+            camera_trigger.update(session_started, &state.config, Instant::now(), &mut events)?;
+        }
+        // while lit, the readings include the own light
+        let ambient_lux = state.light_reading.filter(|_| state.phase() == Phase::Off).map(|reading| reading.lux);
+        occlusion_detector.update(ambient_lux, state.presence, state.lux_threshold(), Instant::now(), &mut events);
+        for event in events.drain() {
+            match event {
+                Event::LightSensorImplausible(_) => log::warn!("event: {} - ambient light sensor possibly occluded or misplaced", event),
+                Event::LightSensorLost => log::warn!("event: {} - continuing with the last known lux level", event),
+                Event::BringupFailed(_) => log::warn!("event: {} - see the bring-up summary", event),
+                Event::MaxOnTimeReached => log::warn!("event: {} - light powered down (stuck presence sensor?)", event),
+                Event::TestTriggerReaction(None) => log::warn!("event: {} - light did not come up (not dark enough?)", event),
+                _ => log::info!("event: {}", event)
+            }
+            #[cfg(any(feature = "remote-diagnostics", feature = "webhooks"))]
+            if let Some(event) = export_filter.pass(&state.config, event) {
+                #[cfg(feature = "remote-diagnostics")]
+                remote_diagnostics.record_event(&event);
+                #[cfg(feature = "webhooks")]
+                if let Some(webhooks) = webhooks.as_ref() {
+                    webhooks.notify(&state.config, event);
+                }
+            }
+            if event == Event::LightSensorLost {
+                state.effects.start(Effect::TripleBlink, Instant::now());
+            }
+            if event == Event::PresenceStart {
+                if let Some(lux) = state.lux_level() {
+                    state.lux_histogram.record_used(lux);
+                }
+            }
+        }
+        if !state.presence {
+            if let Some(lux) = state.lux_level() {
+                state.lux_histogram.sample_idle(lux, Instant::now());
+            }
+        }
+        // the lux level is only up-to-date while the light is off
+        if let Some(lux) = state.lux_level().filter(|_| state.phase() == Phase::Off) {
+            if let Some(hour) = state.lux_stats.sample(lux, Instant::now()) {
+                log::info!("lux statistics of the last hour: min: {:.2}, median: {:.2}, max: {:.2}", hour.min, hour.median, hour.max);
+                if let Some(lux_stats_store) = lux_stats_store.as_mut().filter(|_| state.config.lux_stats_persist) {
+                    if let Err(e) = lux_stats_store.save(&state.lux_stats) {
+                        log::warn!("could not save the lux statistics: {}", e);
+                    }
+                }
+            }
+            if let Some(min) = state.night_baseline.sample(lux, Instant::now()) {
+                log::info!("minimum lux of the last night: {:.2}, baseline: {:?}, effective lux threshold: {:.2}",
+                    min, state.night_baseline.baseline(), state.lux_threshold());
+                if let Some(night_baseline_store) = night_baseline_store.as_mut() {
+                    if let Err(e) = night_baseline_store.save(&state.night_baseline) {
+                        log::warn!("could not save the nightly lux minimums: {}", e);
+                    }
+                }
+            }
+        }
+        devices.poll_scheduler.end_tick(Instant::now());
+        let snapshot = devices.status_snapshot(&state, &latency_monitor);
+        // the status exports see the status as it may leave the device only
+        #[cfg(any(feature = "remote-diagnostics", feature = "mqtt", feature = "influxdb", feature = "ble"))]
+        let exported = event::ExportedStatus::new(&state.config, &snapshot);
+        status.publish(snapshot);
+        #[cfg(feature = "syslog")]
+        syslog_link.update(&state.config, Instant::now());
+        #[cfg(feature = "remote-diagnostics")]
+        remote_diagnostics.update(&state.config, &exported, &mut export_filter, Instant::now());
+        #[cfg(feature = "ble")]
+        if let Some(ble) = ble.as_mut() {
+            ble.update(&state.config, &exported, Instant::now());
+        }
+        #[cfg(feature = "mqtt")]
+        mqtt_link.update(&state.config, &exported, Instant::now());
+        #[cfg(feature = "group")]
+        if let Some((topic, payload)) = group_sync.outgoing(&state.config, state.presence, state.ramp.target_stage(), Instant::now()) {
+            mqtt_link.publish_group(&topic, &payload);
+        }
+        #[cfg(feature = "influxdb")]
+        influx_push.update(&state.config, &exported, Instant::now());
     }
 }
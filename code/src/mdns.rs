@@ -0,0 +1,21 @@
+//! mDNS advertisement (opt-in)
+//!
+//! Announces `<hostname>.local` and the service `_sensorlight._tcp` (port of the REST API), so that several units
+//! can be found on the LAN without keeping track of their IP addresses (e.g. `avahi-browse _sensorlight._tcp`).
+
+use anyhow::Result;
+use esp_idf_svc::mdns::EspMdns;
+
+const SERVICE_TYPE: &str = "_sensorlight";
+const SERVICE_PROTOCOL: &str = "_tcp";
+const SERVICE_PORT: u16 = 80;
+
+/// Starts the advertisement - it lasts as long as the returned handle is kept
+pub fn advertise(hostname: &str) -> Result<EspMdns> {
+    let mut mdns = EspMdns::take()?;
+    mdns.set_hostname(hostname)?;
+    mdns.set_instance_name(hostname)?;
+    mdns.add_service(None, SERVICE_TYPE, SERVICE_PROTOCOL, SERVICE_PORT, &[("version", env!("CARGO_PKG_VERSION"))])?;
+    log::info!("mDNS: {}.local, {}.{}", hostname, SERVICE_TYPE, SERVICE_PROTOCOL);
+    Ok(mdns)
+}
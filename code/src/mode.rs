@@ -0,0 +1,59 @@
+//! Operating mode - manual override of the automatic light control
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Mode {
+    /// light is controlled by presence and ambient light level
+    Auto,
+    /// light is on (regular ramp), regardless of presence and ambient light
+    ForcedOn,
+    /// light is off, regardless of presence and ambient light
+    ForcedOff,
+    /// for working on the installation: full brightness without ramp, presence notifications are suppressed
+    Maintenance,
+    /// full brightness without ramp, overriding everything else
+    Emergency,
+}
+
+impl Mode {
+    /// Whether the light is forced on in this mode
+    pub fn forces_light_on(self) -> bool {
+        matches!(self, Mode::ForcedOn | Mode::Maintenance | Mode::Emergency)
+    }
+
+    /// Whether the light skips the ramp and jumps to full brightness in this mode
+    pub fn is_instant(self) -> bool {
+        matches!(self, Mode::Maintenance | Mode::Emergency)
+    }
+}
+
+impl Display for Mode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Mode::Auto => "auto",
+            Mode::ForcedOn => "on",
+            Mode::ForcedOff => "off",
+            Mode::Maintenance => "maintenance",
+            Mode::Emergency => "emergency",
+        })
+    }
+}
+
+impl FromStr for Mode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Mode::Auto),
+            "on" => Ok(Mode::ForcedOn),
+            "off" => Ok(Mode::ForcedOff),
+            "maintenance" => Ok(Mode::Maintenance),
+            "emergency" => Ok(Mode::Emergency),
+            _ => Err(anyhow!("unknown mode '{}' (expected auto, on, off, maintenance or emergency)", s))
+        }
+    }
+}
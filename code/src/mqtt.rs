@@ -0,0 +1,347 @@
+//! MQTT state publishing and remote control (opt-in)
+//!
+//! Publishes the light state to `<mqtt_topic>/phase`, `/lux`, `/presence`, `/duty` and `/mode` (retained) on every
+//! transition and with a periodic heartbeat, e.g. for a home dashboard.
+//! JSON commands on `<mqtt_topic>/cmd` are handed over to the main loop like console commands and acknowledged
+//! on `<mqtt_topic>/result`.
+//! `<mqtt_topic>/status` reports the availability (retained): `online` after connecting, `offline` as Last Will of
+//! the broker connection - so a powered-off light is shown as unavailable instead of with its last state.
+//! With `ha_discovery` the entities are announced to Home Assistant (see [crate::ha_discovery]).
+//! A lost broker connection is re-established with an exponential backoff.
+//! With `local_only_analytics` the occupancy related topics (phase, presence, duty) are not published (see
+//! [ExportedStatus]) - and their transitions don't trigger a publication.
+//! The connection also carries the messages of a group of lights (see [crate::group]).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use esp_idf_svc::mqtt::client::{Details, EspMqttClient, EventPayload, LwtConfiguration, MqttClientConfiguration, QoS};
+use serde_json::{json, Value};
+
+use crate::config::Config;
+use crate::event::ExportedStatus;
+#[cfg(feature = "group")]
+use crate::group_sync::GroupSync;
+use crate::ha_discovery::{device_id, discovery_messages};
+use crate::mode::Mode;
+use crate::ramp::Phase;
+use crate::remote_command::{parse_command, RemoteCommand};
+
+/// Time a connection attempt (or a reconnect of the client) gets before the client is dropped
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const MIN_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(10 * 60);
+
+/// Payloads of the availability topic
+pub const AVAILABILITY_ONLINE: &str = "online";
+pub const AVAILABILITY_OFFLINE: &str = "offline";
+
+/// The published values which make up a transition
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+struct Transition {
+    /// light phase and presence - `None` with `local_only_analytics`
+    occupancy: Option<(Phase, bool)>,
+    mode: Mode,
+}
+
+pub struct MqttLink {
+    client: Option<EspMqttClient<'static>>,
+    connected: Arc<AtomicBool>,
+    command_sender: Sender<RemoteCommand>,
+    command_receiver: Receiver<RemoteCommand>,
+    /// start of the current connection attempt - `None` while connected
+    connecting_since: Option<Instant>,
+    next_attempt: Instant,
+    backoff: Duration,
+    /// broker URL and base topic of the client
+    url: String,
+    topic: String,
+    last_published: Option<Transition>,
+    last_heartbeat: Option<Instant>,
+    /// discovery messages published on the current connection
+    discovery_published: bool,
+    /// subscribed topic of the group messages
+    #[cfg(feature = "group")]
+    group_topic: Option<String>,
+    #[cfg(feature = "group")]
+    group_sender: Sender<Vec<u8>>,
+    #[cfg(feature = "group")]
+    group_receiver: Receiver<Vec<u8>>,
+}
+
+impl MqttLink {
+    pub fn new() -> Self {
+        let (command_sender, command_receiver) = mpsc::channel();
+        #[cfg(feature = "group")]
+        let (group_sender, group_receiver) = mpsc::channel();
+        Self {
+            client: None,
+            connected: Arc::new(AtomicBool::new(false)),
+            command_sender,
+            command_receiver,
+            connecting_since: None,
+            next_attempt: Instant::now(),
+            backoff: MIN_BACKOFF,
+            url: String::new(),
+            topic: String::new(),
+            last_published: None,
+            last_heartbeat: None,
+            discovery_published: false,
+            #[cfg(feature = "group")]
+            group_topic: None,
+            #[cfg(feature = "group")]
+            group_sender,
+            #[cfg(feature = "group")]
+            group_receiver,
+        }
+    }
+
+    /// Keeps the broker connection and publishes the state on a transition or when the heartbeat is due
+    pub fn update(&mut self, config: &Config, status: &ExportedStatus, now: Instant) {
+        let reconfigured = config.mqtt_url != self.url || config.mqtt_topic != self.topic;
+        #[cfg(feature = "group")]
+        let group_topic = GroupSync::subscription(config);
+        #[cfg(feature = "group")]
+        let reconfigured = reconfigured || group_topic != self.group_topic;
+        if reconfigured {
+            // (re-)configured - a new base topic needs a new subscription
+            self.disconnect();
+            self.url = config.mqtt_url.clone();
+            self.topic = config.mqtt_topic.clone();
+            #[cfg(feature = "group")]
+            {
+                self.group_topic = group_topic;
+            }
+            self.backoff = MIN_BACKOFF;
+            self.next_attempt = now;
+        }
+        if self.url.is_empty() {
+            return;
+        }
+        if !self.keep_connection(config, now) {
+            return;
+        }
+        if config.ha_discovery && !self.discovery_published {
+            self.discovery_published = self.publish_discovery(config);
+        }
+        let transition = Transition {
+            occupancy: status.occupancy.as_ref().map(|occupancy| (occupancy.phase, occupancy.presence)),
+            mode: status.mode,
+        };
+        let heartbeat_due = config.mqtt_heartbeat_s > 0 && self.last_heartbeat
+            .map_or(true, |t| now.duration_since(t) >= Duration::from_secs(config.mqtt_heartbeat_s as u64));
+        if self.last_published == Some(transition) && !heartbeat_due {
+            return;
+        }
+        match self.publish_state(config, status) {
+            Ok(()) => {
+                self.last_published = Some(transition);
+                self.last_heartbeat = Some(now);
+            }
+            Err(e) => log::warn!("MQTT publish failed: {}", e)
+        }
+    }
+
+    /// Connects, watches the connection and drops a client which did not (re-)connect in time.
+    /// Returns whether the client is connected.
+    fn keep_connection(&mut self, config: &Config, now: Instant) -> bool {
+        if self.client.is_none() {
+            if now < self.next_attempt {
+                return false;
+            }
+            if let Err(e) = self.connect() {
+                log::warn!("MQTT client not started: {}", e);
+                self.retry_later(now);
+                return false;
+            }
+            self.connecting_since = Some(now);
+        }
+        if self.connected.load(Ordering::Relaxed) {
+            if self.connecting_since.take().is_some() {
+                log::info!("MQTT connected to {}", self.url);
+                self.backoff = MIN_BACKOFF;
+                // publish everything again
+                self.last_published = None;
+                self.discovery_published = false;
+                self.publish_availability(AVAILABILITY_ONLINE);
+                self.subscribe_commands(config);
+            }
+            return true;
+        }
+        let connecting_since = *self.connecting_since.get_or_insert(now);
+        if now.duration_since(connecting_since) >= CONNECT_TIMEOUT {
+            log::warn!("MQTT broker {} not reachable - next attempt in {:?}", self.url, self.backoff);
+            self.disconnect();
+            self.retry_later(now);
+        }
+        false
+    }
+
+    /// Returns the next received command (non-blocking)
+    pub fn poll_command(&self) -> Option<RemoteCommand> {
+        self.command_receiver.try_recv().ok()
+    }
+
+    /// Returns the next received group message (non-blocking)
+    #[cfg(feature = "group")]
+    pub fn poll_group_message(&self) -> Option<Vec<u8>> {
+        self.group_receiver.try_recv().ok()
+    }
+
+    /// Publishes a group message - dropped while not connected
+    #[cfg(feature = "group")]
+    pub fn publish_group(&mut self, topic: &str, payload: &str) {
+        if !self.connected.load(Ordering::Relaxed) {
+            return;
+        }
+        let Some(client) = self.client.as_mut() else {
+            return;
+        };
+        if let Err(e) = client.enqueue(topic, QoS::AtMostOnce, false, payload.as_bytes()) {
+            log::warn!("MQTT group message not published: {}", e);
+        }
+    }
+
+    /// Publishes the result of a command
+    pub fn acknowledge(&mut self, config: &Config, id: Value, result: &Result<()>) {
+        let ack = match result {
+            Ok(()) => json!({"id": id, "ok": true}),
+            Err(e) => json!({"id": id, "ok": false, "error": e.to_string()})
+        };
+        let Some(client) = self.client.as_mut() else {
+            return;
+        };
+        let topic = format!("{}/result", config.mqtt_topic);
+        if let Err(e) = client.enqueue(&topic, QoS::AtLeastOnce, false, ack.to_string().as_bytes()) {
+            log::warn!("MQTT acknowledge failed: {}", e);
+        }
+    }
+
+    fn connect(&mut self) -> Result<()> {
+        let connected = self.connected.clone();
+        let command_sender = self.command_sender.clone();
+        #[cfg(feature = "group")]
+        let (group_sender, group_topic) = (self.group_sender.clone(), self.group_topic.clone());
+        let availability_topic = availability_topic(&self.topic);
+        let client_config = MqttClientConfiguration {
+            lwt: Some(LwtConfiguration {
+                topic: &availability_topic,
+                payload: AVAILABILITY_OFFLINE.as_bytes(),
+                qos: QoS::AtLeastOnce,
+                retain: true,
+            }),
+            ..Default::default()
+        };
+        let client = EspMqttClient::new_cb(&self.url, &client_config, move |event| {
+            match event.payload() {
+                EventPayload::Connected(_) => connected.store(true, Ordering::Relaxed),
+                EventPayload::Disconnected => connected.store(false, Ordering::Relaxed),
+                // the messages fit into a single one
+                #[cfg(feature = "group")]
+                EventPayload::Received { topic, data, details: Details::Complete, .. }
+                    if group_topic.is_some() && topic == group_topic.as_deref() => {
+                    let _ = group_sender.send(data.to_vec());
+                }
+                // besides the group topic the command topic is the only subscription
+                EventPayload::Received { data, details: Details::Complete, .. } => {
+                    let _ = command_sender.send(parse_command(data));
+                }
+                _ => ()
+            }
+        })?;
+        self.client = Some(client);
+        Ok(())
+    }
+
+    /// Returns whether all messages were published
+    fn publish_discovery(&mut self, config: &Config) -> bool {
+        let Some(client) = self.client.as_mut() else {
+            return false;
+        };
+        for (topic, payload) in discovery_messages(config, &device_id()) {
+            if let Err(e) = client.enqueue(&topic, QoS::AtLeastOnce, true, payload.as_bytes()) {
+                log::warn!("MQTT discovery message not published: {}", e);
+                return false;
+            }
+        }
+        true
+    }
+
+    fn subscribe_commands(&mut self, config: &Config) {
+        let Some(client) = self.client.as_mut() else {
+            return;
+        };
+        let topic = format!("{}/cmd", config.mqtt_topic);
+        if let Err(e) = client.subscribe(&topic, QoS::AtLeastOnce) {
+            log::warn!("MQTT subscription of {} failed: {}", topic, e);
+        }
+        #[cfg(feature = "group")]
+        if let Some(topic) = self.group_topic.as_ref() {
+            if let Err(e) = client.subscribe(topic, QoS::AtMostOnce) {
+                log::warn!("MQTT subscription of {} failed: {}", topic, e);
+            }
+        }
+    }
+
+    fn publish_availability(&mut self, availability: &str) {
+        let topic = availability_topic(&self.topic);
+        let Some(client) = self.client.as_mut() else {
+            return;
+        };
+        if let Err(e) = client.enqueue(&topic, QoS::AtLeastOnce, true, availability.as_bytes()) {
+            log::warn!("MQTT availability not published: {}", e);
+        }
+    }
+
+    fn disconnect(&mut self) {
+        // the broker sends the Last Will only for a lost connection
+        if self.connected.load(Ordering::Relaxed) {
+            self.publish_availability(AVAILABILITY_OFFLINE);
+        }
+        self.client = None;
+        self.connected.store(false, Ordering::Relaxed);
+        self.connecting_since = None;
+    }
+
+    fn retry_later(&mut self, now: Instant) {
+        self.next_attempt = now + self.backoff;
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+    }
+
+    fn publish_state(&mut self, config: &Config, status: &ExportedStatus) -> Result<()> {
+        let mut messages = vec![("mode", status.mode.to_string())];
+        if let Some(lux) = status.lux {
+            messages.push(("lux", format!("{:.1}", lux)));
+        }
+        if let Some(occupancy) = &status.occupancy {
+            messages.push(("phase", phase_name(occupancy.phase).to_string()));
+            messages.push(("presence", occupancy.presence.to_string()));
+            messages.push(("duty", format!("{:.1}", occupancy.duty_pct())));
+        }
+        let Some(client) = self.client.as_mut() else {
+            return Ok(());
+        };
+        for (subtopic, payload) in messages {
+            let topic = format!("{}/{}", config.mqtt_topic, subtopic);
+            client.enqueue(&topic, QoS::AtMostOnce, true, payload.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+pub fn availability_topic(base_topic: &str) -> String {
+    format!("{}/status", base_topic)
+}
+
+fn phase_name(phase: Phase) -> &'static str {
+    match phase {
+        Phase::Off => "off",
+        Phase::PowerDown => "power_down",
+        Phase::PowerUp => "power_up",
+        Phase::On => "on",
+    }
+}
@@ -0,0 +1,103 @@
+//! Rolling baseline of the darkest conditions at the installation
+//!
+//! Records the minimum lux level of each day - which is reached at night - and keeps it for the last nights.
+//! The median of these nightly minimums is the baseline (e.g. the level of a streetlight outside the window),
+//! relative to which the darkness threshold may be adapted (see `adaptive_lux_factor`).
+//! The minimums are persisted in NVS, so that they survive a restart.
+//! (Without a wall clock a night is the 24 hour period of lux sampling, which contains it.)
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use itertools::Itertools;
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+
+const NIGHT_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+const NIGHTS: usize = 7;
+/// Minimum number of recorded nights before the baseline is used
+const MIN_NIGHTS_FOR_BASELINE: usize = 3;
+
+pub const NVS_NAMESPACE: &str = "night-base";
+const KEY_MINIMUMS: &str = "minimums";
+
+#[derive(Debug)]
+pub struct NightBaseline {
+    period_start: Option<Instant>,
+    current_min: Option<f32>,
+    /// oldest first
+    minimums: AllocRingBuffer<f32>,
+}
+
+impl NightBaseline {
+    pub fn new() -> Self {
+        Self {
+            period_start: None,
+            current_min: None,
+            minimums: AllocRingBuffer::new(NIGHTS),
+        }
+    }
+
+    /// Continues with persisted nightly minimums - oldest first
+    pub fn restore(&mut self, minimums: Vec<f32>) {
+        self.minimums.extend(minimums);
+    }
+
+    /// Records a lux sample. Returns the minimum of the night, when the period was completed with this sample.
+    pub fn sample(&mut self, lux: f32, now: Instant) -> Option<f32> {
+        let period_start = *self.period_start.get_or_insert(now);
+        let min = self.current_min.map_or(lux, |min| min.min(lux));
+        if now.duration_since(period_start) < NIGHT_PERIOD {
+            self.current_min = Some(min);
+            return None;
+        }
+        self.minimums.push(min);
+        self.current_min = None;
+        self.period_start = Some(now);
+        Some(min)
+    }
+
+    pub fn minimums(&self) -> impl Iterator<Item=&f32> {
+        self.minimums.iter()
+    }
+
+    /// Median of the nightly minimums or `None`, if not enough nights were recorded yet
+    pub fn baseline(&self) -> Option<f32> {
+        if self.minimums.len() < MIN_NIGHTS_FOR_BASELINE {
+            return None;
+        }
+        let sorted = self.minimums.iter().copied().sorted_by(f32::total_cmp).collect_vec();
+        Some(sorted[sorted.len() / 2])
+    }
+}
+
+/// NVS persistence of the nightly minimums
+pub struct NightBaselineStore {
+    nvs: EspNvs<NvsDefault>,
+}
+
+impl NightBaselineStore {
+    pub fn new(partition: EspDefaultNvsPartition) -> Result<Self> {
+        Ok(Self {
+            nvs: EspNvs::new(partition, NVS_NAMESPACE, true)?
+        })
+    }
+
+    pub fn load(&self) -> Result<Vec<f32>> {
+        let mut buf = [0_u8; NIGHTS * 4];
+        let Some(data) = self.nvs.get_raw(KEY_MINIMUMS, &mut buf)? else {
+            return Ok(Vec::new());
+        };
+        Ok(data.chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect())
+    }
+
+    pub fn save(&mut self, baseline: &NightBaseline) -> Result<()> {
+        let data = baseline.minimums()
+            .flat_map(|min| min.to_le_bytes())
+            .collect_vec();
+        self.nvs.set_raw(KEY_MINIMUMS, &data)?;
+        Ok(())
+    }
+}
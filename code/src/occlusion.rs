@@ -0,0 +1,123 @@
+//! Plausibility check of the ambient light sensor readings
+//!
+//! Detects a possibly occluded or misplaced light sensor by evaluating daily lux statistics:
+//! - it never got dark for several days in a row, while the presence sensor saw people come and go
+//!   (sensor pointed at a light source) - days without presence say nothing about the light, as nobody needed it
+//! - lux hardly changed between day and night for several days in a row (sensor covered or dead) - a steady darkness
+//!   is plausible though (e.g. a basement without windows)
+
+use std::time::{Duration, Instant};
+
+use crate::event::{Event, EventQueue};
+
+const EVALUATION_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Number of consecutive implausible periods before we raise a diagnostic
+const IMPLAUSIBLE_PERIODS_FOR_DIAGNOSTIC: u32 = 3;
+
+/// Presence cycles (radar motion) within a period, from which a period without darkness counts as implausible
+const MIN_PRESENCE_CYCLES: u32 = 3;
+
+/// Max/min lux ratio within a period below which we consider the lux to be constant
+const MIN_DAY_NIGHT_RATIO: f32 = 1.5;
+
+/// Absolute lux difference within a period below which we consider the lux to be constant
+const MIN_DAY_NIGHT_DIFFERENCE: f32 = 1.0;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Implausibility {
+    /// lux never dropped to the threshold, although people came and went
+    NeverDark,
+    /// lux was (nearly) identical day and night
+    Constant,
+}
+
+#[derive(Debug)]
+struct PeriodStats {
+    start: Instant,
+    min_lux: f32,
+    max_lux: f32,
+    dark_seen: bool,
+    presence_cycles: u32,
+}
+
+impl PeriodStats {
+    fn new(start: Instant) -> Self {
+        Self {
+            start,
+            min_lux: f32::INFINITY,
+            max_lux: f32::NEG_INFINITY,
+            dark_seen: false,
+            presence_cycles: 0,
+        }
+    }
+
+    fn lux_seen(&self) -> bool {
+        self.min_lux <= self.max_lux
+    }
+}
+
+#[derive(Debug)]
+pub struct OcclusionDetector {
+    period: Option<PeriodStats>,
+    presence: bool,
+    never_dark_periods: u32,
+    constant_periods: u32,
+}
+
+impl OcclusionDetector {
+    pub fn new() -> Self {
+        Self {
+            period: None,
+            presence: false,
+            never_dark_periods: 0,
+            constant_periods: 0,
+        }
+    }
+
+    /// `lux`: the ambient light reading, if it is not influenced by the own light
+    pub fn update(&mut self, lux: Option<f32>, presence: bool, lux_threshold: f32, now: Instant, events: &mut EventQueue) {
+        let period = self.period.get_or_insert_with(|| PeriodStats::new(now));
+        if presence && !self.presence {
+            period.presence_cycles += 1;
+        }
+        self.presence = presence;
+        if let Some(lux) = lux {
+            period.min_lux = period.min_lux.min(lux);
+            period.max_lux = period.max_lux.max(lux);
+            period.dark_seen |= lux <= lux_threshold;
+        }
+
+        if now.duration_since(period.start) >= EVALUATION_PERIOD {
+            if let Some(period) = self.period.take() {
+                self.evaluate(&period, events);
+            }
+        }
+    }
+
+    fn evaluate(&mut self, period: &PeriodStats, events: &mut EventQueue) {
+        if !period.lux_seen() {
+            return;
+        }
+        if period.dark_seen {
+            self.never_dark_periods = 0;
+        } else if period.presence_cycles >= MIN_PRESENCE_CYCLES {
+            self.never_dark_periods += 1;
+            if self.never_dark_periods == IMPLAUSIBLE_PERIODS_FOR_DIAGNOSTIC {
+                events.push(Event::LightSensorImplausible(Implausibility::NeverDark));
+            }
+        }
+
+        let steady_dark = period.max_lux < MIN_DAY_NIGHT_DIFFERENCE;
+        let constant = !steady_dark
+            && (period.max_lux - period.min_lux < MIN_DAY_NIGHT_DIFFERENCE || period.max_lux < period.min_lux * MIN_DAY_NIGHT_RATIO);
+        if constant {
+            self.constant_periods += 1;
+            if self.constant_periods == IMPLAUSIBLE_PERIODS_FOR_DIAGNOSTIC {
+                events.push(Event::LightSensorImplausible(Implausibility::Constant));
+            }
+        } else {
+            self.constant_periods = 0;
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Firmware update over HTTPS (opt-in)
+//!
+//! `ota <url>` (console, MQTT or REST API) downloads the image in a background thread - the light control goes on -
+//! into the inactive OTA slot. ESP-IDF verifies the image - including its signature (signed app images, see
+//! `sdkconfig.ota.defaults`) - before the slot is switched and the device restarts.
+//! Updates are refused while `api_token` is empty.
+//! A new image boots in the pending state: with the rollback enabled bootloader the previous image is started again,
+//! if the new one resets before it passed the health check after its bring-up (see [verify_running_image]) or fails it.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use anyhow::{anyhow, bail, Result};
+use embedded_svc::http::client::Client;
+use embedded_svc::http::Headers;
+use embedded_svc::io::{Read, Write};
+use esp_idf_svc::hal::delay::FreeRtos;
+use esp_idf_svc::hal::reset::restart;
+use esp_idf_svc::http::client::{Configuration, EspHttpConnection};
+use esp_idf_svc::ota::{EspOta, SlotState};
+
+// an image from the network is only accepted with a valid signature
+#[cfg(not(esp_idf_secure_signed_on_update_no_secure_boot))]
+compile_error!("the feature ota needs signed app images - build with the settings of sdkconfig.ota.defaults");
+
+const DOWNLOAD_CHUNK_SIZE: usize = 4096;
+/// Time for the log output before the restart
+const RESTART_DELAY_MS: u32 = 1000;
+
+static UPDATE_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Starts the update from `url` in the background
+pub fn start_update(url: String) -> Result<()> {
+    if !url.starts_with("https://") {
+        bail!("firmware URL must start with https://");
+    }
+    if UPDATE_RUNNING.swap(true, Ordering::SeqCst) {
+        bail!("a firmware update is already running");
+    }
+    let result = thread::Builder::new()
+        .name("ota".to_string())
+        .stack_size(16384)
+        .spawn(move || {
+            match download_and_install(&url) {
+                Ok(()) => {
+                    log::warn!("firmware update installed - restarting");
+                    FreeRtos::delay_ms(RESTART_DELAY_MS);
+                    restart();
+                }
+                Err(e) => log::warn!("firmware update failed: {}", e)
+            }
+            UPDATE_RUNNING.store(false, Ordering::SeqCst);
+        });
+    if let Err(e) = result {
+        UPDATE_RUNNING.store(false, Ordering::SeqCst);
+        bail!("firmware update not started: {}", e);
+    }
+    Ok(())
+}
+
+/// Whether the running image is a new update, which still has to be confirmed
+pub fn is_pending_verification() -> bool {
+    EspOta::new()
+        .and_then(|ota| ota.get_running_slot())
+        .is_ok_and(|slot| slot.state == SlotState::Unverified)
+}
+
+/// Confirms the running (new) image after a successful health check - otherwise restarts into the previous image
+pub fn verify_running_image(health: Result<()>) -> Result<()> {
+    let mut ota = EspOta::new()?;
+    match health {
+        Ok(()) => {
+            ota.mark_running_slot_valid()?;
+            log::info!("updated firmware passed the health check - confirmed");
+            Ok(())
+        }
+        Err(e) => {
+            log::error!("updated firmware failed the health check: {} - rolling back", e);
+            FreeRtos::delay_ms(RESTART_DELAY_MS);
+            // returns only on failure
+            Err(ota.mark_running_slot_invalid_and_reboot().into())
+        }
+    }
+}
+
+fn download_and_install(url: &str) -> Result<()> {
+    let connection = EspHttpConnection::new(&Configuration {
+        crt_bundle_attach: Some(esp_idf_svc::sys::esp_crt_bundle_attach),
+        ..Default::default()
+    })?;
+    let mut client = Client::wrap(connection);
+    let mut response = client.get(url)?.submit()?;
+    if response.status() != 200 {
+        bail!("GET {} failed with HTTP status {}", url, response.status());
+    }
+    let size = response.content_len();
+    log::info!("downloading firmware from {} ({:?} bytes)", url, size);
+
+    let mut ota = EspOta::new()?;
+    let mut update = ota.initiate_update()?;
+    let mut buf = vec![0_u8; DOWNLOAD_CHUNK_SIZE];
+    let mut written: u64 = 0;
+    let mut reported_pct = 0;
+    let download = loop {
+        let n = match response.read(&mut buf) {
+            Ok(0) => break Ok(()),
+            Ok(n) => n,
+            Err(e) => break Err(anyhow!("download failed: {:?}", e))
+        };
+        if let Err(e) = update.write_all(&buf[..n]) {
+            break Err(anyhow!("writing the image failed: {:?}", e));
+        }
+        written += n as u64;
+        if let Some(size) = size.filter(|&size| size > 0) {
+            let pct = written * 100 / size;
+            if pct >= reported_pct + 10 {
+                reported_pct = pct;
+                log::info!("firmware update: {} %", pct);
+            }
+        }
+    };
+    if let Err(e) = download {
+        update.abort()?;
+        return Err(e);
+    }
+    // verifies the image and switches the boot slot
+    update.complete()?;
+    log::info!("firmware image written ({} bytes)", written);
+    Ok(())
+}
@@ -1,16 +1,47 @@
 //! Peripheral initialization
 
-use anyhow::Result;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use esp_idf_hal::delay::{Ets, TickType};
 use esp_idf_hal::gpio;
-use esp_idf_hal::gpio::{InputPin, Output, OutputPin, Pin, PinDriver, Pull};
-use esp_idf_hal::i2c::{I2c, I2cConfig, I2cDriver};
+use esp_idf_hal::gpio::{AnyIOPin, InputPin, InterruptType, Output, OutputPin, Pin, PinDriver, Pull};
+use esp_idf_hal::i2c::{I2C0, I2C1, I2cConfig, I2cDriver};
 use esp_idf_hal::ledc::{LedcChannel, LedcDriver, LedcTimer, LedcTimerDriver, Resolution};
 use esp_idf_hal::ledc::config::TimerConfig;
 use esp_idf_hal::peripheral::Peripheral;
 use esp_idf_hal::prelude::FromValueType;
-use veml7700::Veml7700;
+use esp_idf_hal::task::notification::Notifier;
+use esp_idf_svc::sys::{esp, gpio_hold_dis, gpio_hold_en, ledc_channel_t, ledc_channel_t_LEDC_CHANNEL_0, ledc_channel_t_LEDC_CHANNEL_1,
+                       ledc_channel_t_LEDC_CHANNEL_2, ledc_channel_t_LEDC_CHANNEL_3,
+                       ledc_fade_func_install, ledc_fade_mode_t_LEDC_FADE_NO_WAIT, ledc_fade_start, ledc_mode_t,
+                       ledc_mode_t_LEDC_LOW_SPEED_MODE, ledc_set_fade_with_time};
+
+use crate::dither::{DitherSlot, DITHER_BITS};
+use crate::latency::record_presence_edge;
+use crate::light_sensor::{AmbientLightSensor, detect_ambient_light_sensor, supported_i2c_addresses};
+
+const I2C_PROBE_TIMEOUT_MS: u64 = 50;
+
+/// max. number of LED outputs (RGBW)
+pub const LED_MAX_CHANNELS: usize = 4;
+/// LEDC channels of the LED drivers (channel0..channel3) - for the hardware fade API
+pub const LED_LEDC_CHANNELS: [ledc_channel_t; LED_MAX_CHANNELS] = [
+    ledc_channel_t_LEDC_CHANNEL_0,
+    ledc_channel_t_LEDC_CHANNEL_1,
+    ledc_channel_t_LEDC_CHANNEL_2,
+    ledc_channel_t_LEDC_CHANNEL_3,
+];
+/// LEDC source clock (PLL_F96M) - the PWM counter runs at frequency × 2^resolution
+pub const LED_PWM_CLOCK_HZ: u32 = 96_000_000;
+/// the ESP32-H2 has only the low speed mode
+pub const LED_LEDC_SPEED_MODE: ledc_mode_t = ledc_mode_t_LEDC_LOW_SPEED_MODE;
 
-use crate::error::Error;
+/// max. number of SCL pulses to free a stuck I2C bus (one byte + ACK)
+const I2C_BUS_CLEAR_CLOCK_PULSES: u32 = 9;
+/// 100 kHz bus clock
+const I2C_BUS_CLEAR_HALF_PERIOD_US: u32 = 5;
 
 pub struct PresenceSensor<P1: Pin> {
     pub sensor_pin: PinDriver<'static, P1, gpio::Input>,
@@ -30,20 +61,131 @@ pub fn init_presence_sensor<P: InputPin + OutputPin>(
     })
 }
 
-pub fn init_veml7700<I2C: I2c>(
-    i2c: impl Peripheral<P=I2C> + 'static,
-    sda: impl Peripheral<P=impl InputPin + OutputPin> + 'static,
-    scl: impl Peripheral<P=impl InputPin + OutputPin> + 'static,
-) -> Result<Veml7700<I2cDriver<'static>>>
-{
-    let config = I2cConfig::new().baudrate(100.kHz().into());
-    
-    let i2c_driver = I2cDriver::new(i2c, sda, scl, &config)?;
+impl<P: InputPin> PresenceSensor<P> {
+    /// Timestamps rising edges of the sensor signal in the ISR and wakes up the main task through `notifier`
+    pub fn subscribe_rising_edge(&mut self, notifier: Arc<Notifier>) -> Result<()> {
+        self.sensor_pin.set_interrupt_type(InterruptType::PosEdge)?;
+        // Safety: the callback only touches an atomic and the (ISR-safe) notifier
+        unsafe {
+            self.sensor_pin.subscribe(move || {
+                record_presence_edge();
+                notifier.notify_and_yield(NonZeroU32::MIN);
+            })?;
+        }
+        self.sensor_pin.enable_interrupt()?;
+        Ok(())
+    }
+
+    /// The interrupt is disabled after each trigger - re-enable it
+    pub fn rearm_interrupt(&mut self) -> Result<()> {
+        self.sensor_pin.enable_interrupt()?;
+        Ok(())
+    }
+}
 
-    // Initialize the VEML7700 with I2C
-    let mut veml7700_device = Veml7700::new(i2c_driver);
-    veml7700_device.enable().map_err(Error::from)?;
-    Ok(veml7700_device)
+pub enum I2cPort {
+    I2c0(I2C0),
+    I2c1(I2C1),
+}
+
+/// I2C bus of an ambient light sensor (or the temperature sensor).
+/// Keeps the peripherals, so that we can clear a stuck bus and re-create the driver.
+pub struct LightSensorBus {
+    i2c: I2cPort,
+    sda: AnyIOPin,
+    scl: AnyIOPin,
+}
+
+impl LightSensorBus {
+    pub fn new(i2c: I2cPort, sda: AnyIOPin, scl: AnyIOPin) -> Self {
+        Self { i2c, sda, scl }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.i2c {
+            I2cPort::I2c0(_) => "I2C0",
+            I2cPort::I2c1(_) => "I2C1",
+        }
+    }
+
+    /// Creates a new driver for the bus.
+    /// Any previously created driver (and the sensor owning it) must have been dropped before.
+    pub fn driver(&mut self) -> Result<I2cDriver<'static>> {
+        let config = I2cConfig::new().baudrate(100.kHz().into());
+        // Safety: we hand out only one driver at a time (see above)
+        let driver = unsafe {
+            match &mut self.i2c {
+                I2cPort::I2c0(i2c) => I2cDriver::new(i2c.clone_unchecked(), self.sda.clone_unchecked(), self.scl.clone_unchecked(), &config)?,
+                I2cPort::I2c1(i2c) => I2cDriver::new(i2c.clone_unchecked(), self.sda.clone_unchecked(), self.scl.clone_unchecked(), &config)?,
+            }
+        };
+        Ok(driver)
+    }
+
+    /// Clears a bus, where a slave holds SDA low in the middle of a transfer:
+    /// toggle SCL until the slave releases SDA, then generate a STOP condition.
+    /// There must be no driver alive for the bus while doing this.
+    pub fn clear(&mut self) -> Result<()> {
+        // Safety: no driver is alive for the bus (see above)
+        let mut scl = PinDriver::input_output_od(unsafe { self.scl.clone_unchecked() })?;
+        let mut sda = PinDriver::input_output_od(unsafe { self.sda.clone_unchecked() })?;
+        sda.set_high()?;
+        scl.set_high()?;
+        for _ in 0..I2C_BUS_CLEAR_CLOCK_PULSES {
+            if sda.is_high() {
+                break;
+            }
+            scl.set_low()?;
+            Ets::delay_us(I2C_BUS_CLEAR_HALF_PERIOD_US);
+            scl.set_high()?;
+            Ets::delay_us(I2C_BUS_CLEAR_HALF_PERIOD_US);
+        }
+        // STOP condition: SDA low -> high while SCL is high
+        sda.set_low()?;
+        Ets::delay_us(I2C_BUS_CLEAR_HALF_PERIOD_US);
+        sda.set_high()?;
+        Ets::delay_us(I2C_BUS_CLEAR_HALF_PERIOD_US);
+        Ok(())
+    }
+}
+
+/// Init the ambient light sensor.
+/// Returns `None` if no sensor is available - we continue in a degraded mode then.
+pub fn init_ambient_light_sensor(bus: &mut LightSensorBus) -> Option<Box<dyn AmbientLightSensor>> {
+    match try_init_ambient_light_sensor(bus) {
+        Ok(sensor) => {
+            log::info!("{}: ambient light sensor: {}", bus.name(), sensor.name());
+            Some(sensor)
+        }
+        Err(e) => {
+            log::error!("{}: ambient light sensor not available: {}", bus.name(), e);
+            None
+        }
+    }
+}
+
+fn try_init_ambient_light_sensor(bus: &mut LightSensorBus) -> Result<Box<dyn AmbientLightSensor>> {
+    let mut i2c_driver = bus.driver()?;
+
+    let addresses = probe_i2c_addresses(&mut i2c_driver, &supported_i2c_addresses());
+    log::info!("{} bus probe - responding addresses: [{}]", bus.name(),
+        addresses.iter().map(|a| format!("{:#04x}", a)).collect::<Vec<_>>().join(", "));
+
+    detect_ambient_light_sensor(i2c_driver, &addresses)
+}
+
+/// Returns the addresses acknowledged on the bus
+pub fn probe_i2c_addresses(i2c: &mut I2cDriver<'static>, addresses: &[u8]) -> Vec<u8> {
+    addresses.iter()
+        .copied()
+        .filter(|&address| i2c_probe(i2c, address))
+        .collect()
+}
+
+/// Checks whether a device acknowledges its address on the bus
+pub fn i2c_probe(i2c: &mut I2cDriver<'static>, address: u8) -> bool {
+    let mut buf = [0_u8; 1];
+    i2c.read(address, &mut buf, TickType::new_millis(I2C_PROBE_TIMEOUT_MS).ticks()).is_ok()
 }
 
 pub fn init_output_pin<P: OutputPin>(pin: P) -> Result<PinDriver<'static, P, Output>> {
@@ -56,17 +198,156 @@ pub fn init_led_driver<C, T>(
     channel: impl Peripheral<P=C> + 'static,
     timer: impl Peripheral<P=T> + 'static,
     pin: impl Peripheral<P=impl OutputPin> + 'static,
+    freq_hz: u32,
+    resolution_bits: u32,
 ) -> Result<LedcDriver<'static>>
 where
     C: LedcChannel<SpeedMode=<T as LedcTimer>::SpeedMode>,
     T: LedcTimer + 'static,
 {
     let config = TimerConfig::default()
-        .frequency(5000.Hz())
-        .resolution(Resolution::Bits12);
+        .frequency(freq_hz.Hz())
+        .resolution(pwm_resolution(resolution_bits)?);
 
     let timer_driver = LedcTimerDriver::new(timer, &config)?;
     let mut driver = LedcDriver::new(channel, timer_driver, pin)?;
     driver.enable()?;
     Ok(driver)
 }
+
+fn pwm_resolution(bits: u32) -> Result<Resolution> {
+    Ok(match bits {
+        8 => Resolution::Bits8,
+        9 => Resolution::Bits9,
+        10 => Resolution::Bits10,
+        11 => Resolution::Bits11,
+        12 => Resolution::Bits12,
+        13 => Resolution::Bits13,
+        14 => Resolution::Bits14,
+        _ => bail!("unsupported PWM resolution: {} bit", bits)
+    })
+}
+
+/// Installs the LEDC hardware fade service (see `led_hw_fade`) - once for all channels
+pub fn init_led_fade_service() -> Result<()> {
+    esp!(unsafe { ledc_fade_func_install(0) })?;
+    Ok(())
+}
+
+/// LED output backend - one dimmable channel
+pub trait LedChannel {
+    fn max_duty(&self) -> u32;
+
+    fn set_duty(&mut self, duty: u32) -> Result<()>;
+
+    /// Fills the output to `fill` [0..=1] with the duty, starting at the far end if `from_end`.
+    /// Backends without pixels have no fill - they get the proportional duty.
+    fn set_fill(&mut self, fill: f32, _from_end: bool, duty: u32) -> Result<()> {
+        self.set_duty((duty as f32 * fill.clamp(0.0, 1.0)).round() as u32)
+    }
+
+    /// Starts a hardware fade from the current to the given duty (non-blocking).
+    /// Backends without hardware fading set the duty right away.
+    fn fade_to(&mut self, duty: u32, fade_ms: u32) -> Result<()>;
+
+    /// Holds the output level during deep sleep (or releases it)
+    fn hold(&mut self, hold: bool) -> Result<()>;
+
+    /// The duty programmed in the hardware - `None` without readback
+    fn read_duty(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// PWM output of an LED segment
+pub struct LedOutput {
+    driver: LedcDriver<'static>,
+    /// GPIO number of the PWM output (to hold its level during deep sleep)
+    gpio: i32,
+    ledc_channel: ledc_channel_t,
+    /// low level = light on (see `invert_pwm`)
+    inverted: bool,
+    /// finer LED duties by temporal dithering (see `led_dither`)
+    dither: Option<Arc<DitherSlot>>,
+}
+
+impl LedOutput {
+    pub fn new(driver: LedcDriver<'static>, gpio: i32, ledc_channel: ledc_channel_t, inverted: bool, dither: bool) -> Self {
+        let dither = dither.then(|| Arc::new(DitherSlot::new(ledc_channel, driver.get_max_duty(), inverted)));
+        Self { driver, gpio, ledc_channel, inverted, dither }
+    }
+
+    /// The duty shared with the dither timer - if the output is dithered
+    pub fn dither_slot(&self) -> Option<Arc<DitherSlot>> {
+        self.dither.clone()
+    }
+
+    // PWM duty for the LED duty - the integer part of a dithered duty, which the dither timer picks up
+    fn pwm_duty(&self, duty: u32) -> u32 {
+        match &self.dither {
+            Some(slot) => {
+                slot.set_fine_duty(duty);
+                duty >> DITHER_BITS
+            }
+            None => duty
+        }
+    }
+
+    // duty of the PWM signal for the LED duty
+    fn signal_duty(&self, duty: u32) -> u32 {
+        // With the gate driver circuit (NPN) feeding the PWM signal to the N-channel MOSFET the signal is inverted:
+        // the MOSFET's gate is open when we have our IO pin on low.
+        // Boards driving the gate directly (or with a low-side driver IC) use the signal as is.
+        if self.inverted {
+            self.driver.get_max_duty() - duty
+        } else {
+            duty
+        }
+    }
+}
+
+impl LedChannel for LedOutput {
+    fn max_duty(&self) -> u32 {
+        match self.dither {
+            Some(_) => self.driver.get_max_duty() << DITHER_BITS,
+            None => self.driver.get_max_duty()
+        }
+    }
+
+    fn set_duty(&mut self, duty: u32) -> Result<()> {
+        let signal_duty = self.signal_duty(self.pwm_duty(duty));
+        self.driver.set_duty(signal_duty)?;
+        Ok(())
+    }
+
+    fn fade_to(&mut self, duty: u32, fade_ms: u32) -> Result<()> {
+        let signal_duty = self.signal_duty(self.pwm_duty(duty));
+        // Safety: programs a fade on the LEDC channel owned by our driver; the fade service is installed at init
+        unsafe {
+            esp!(ledc_set_fade_with_time(LED_LEDC_SPEED_MODE, self.ledc_channel, signal_duty, fade_ms as i32))?;
+            esp!(ledc_fade_start(LED_LEDC_SPEED_MODE, self.ledc_channel, ledc_fade_mode_t_LEDC_FADE_NO_WAIT))?;
+        }
+        Ok(())
+    }
+
+    fn hold(&mut self, hold: bool) -> Result<()> {
+        unsafe {
+            if hold {
+                esp!(gpio_hold_en(self.gpio))?;
+            } else {
+                esp!(gpio_hold_dis(self.gpio))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_duty(&self) -> Option<u32> {
+        let duty = self.signal_duty(self.driver.get_duty());
+        match &self.dither {
+            // one of the two alternating duties
+            Some(slot) if slot.matches(duty) => Some(slot.fine_duty()),
+            Some(_) => Some(duty << DITHER_BITS),
+            None => Some(duty)
+        }
+    }
+}
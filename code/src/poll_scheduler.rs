@@ -0,0 +1,179 @@
+//! Time-sliced polling of (slow) sensors
+//!
+//! Each poll task has a period and a time budget (deadline). Per loop tick at most one task is granted -
+//! the most overdue one - so the reads are staggered across the ticks and the worst-case loop time stays bounded
+//! by the largest budget. Poll durations, budget overruns and the loop time are measured.
+
+use std::time::{Duration, Instant};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct PollTaskId(usize);
+
+#[derive(Debug)]
+struct PollTask {
+    name: &'static str,
+    period: Duration,
+    budget: Duration,
+    next_due: Option<Instant>,
+    last_duration: Duration,
+    max_duration: Duration,
+    overruns: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct PollScheduler {
+    tasks: Vec<PollTask>,
+    /// task granted in the current tick
+    granted: Option<PollTaskId>,
+    tick_start: Option<Instant>,
+    last_tick_time: Duration,
+    max_tick_time: Duration,
+}
+
+impl PollScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: &'static str, period: Duration, budget: Duration) -> PollTaskId {
+        self.tasks.push(PollTask {
+            name,
+            period,
+            budget,
+            next_due: None,
+            last_duration: Duration::ZERO,
+            max_duration: Duration::ZERO,
+            overruns: 0,
+        });
+        PollTaskId(self.tasks.len() - 1)
+    }
+
+    /// Starts a loop tick and grants the most overdue task
+    pub fn begin_tick(&mut self, now: Instant) {
+        self.tick_start = Some(now);
+        self.granted = self.tasks.iter().enumerate()
+            .filter(|(_, task)| task.next_due.map_or(true, |t| t <= now))
+            .min_by_key(|(_, task)| task.next_due)
+            .map(|(i, _)| PollTaskId(i));
+    }
+
+    /// Whether the task may poll in this tick
+    pub fn is_granted(&self, id: PollTaskId) -> bool {
+        self.granted == Some(id)
+    }
+
+    /// Records the poll of a granted task, which started at `started`
+    pub fn finish(&mut self, id: PollTaskId, started: Instant, now: Instant) {
+        let task = &mut self.tasks[id.0];
+        let duration = now.duration_since(started);
+        task.last_duration = duration;
+        task.max_duration = task.max_duration.max(duration);
+        if duration > task.budget {
+            task.overruns += 1;
+            log::warn!("poll of {} took {} ms (budget: {} ms)", task.name, duration.as_millis(), task.budget.as_millis());
+        }
+        task.next_due = Some(started + task.period);
+        self.granted = None;
+    }
+
+    /// Ends the loop tick. A granted task, which did not poll (not needed in this tick), waits for its next period.
+    pub fn end_tick(&mut self, now: Instant) {
+        if let Some(id) = self.granted.take() {
+            let task = &mut self.tasks[id.0];
+            task.next_due = Some(now + task.period);
+        }
+        if let Some(start) = self.tick_start.take() {
+            self.last_tick_time = now.duration_since(start);
+            self.max_tick_time = self.max_tick_time.max(self.last_tick_time);
+        }
+    }
+
+    pub fn report(&self) -> Vec<String> {
+        let mut lines = self.tasks.iter()
+            .map(|t| format!("{}: period: {} ms, budget: {} ms, last: {} ms, max: {} ms, overruns: {}",
+                             t.name, t.period.as_millis(), t.budget.as_millis(),
+                             t.last_duration.as_millis(), t.max_duration.as_millis(), t.overruns))
+            .collect::<Vec<_>>();
+        lines.push(format!("loop time (without wait): last: {} ms, max: {} ms",
+                           self.last_tick_time.as_millis(), self.max_tick_time.as_millis()));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERIOD: Duration = Duration::from_millis(100);
+    const BUDGET: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn grants_one_task_per_tick() {
+        let mut scheduler = PollScheduler::new();
+        let light = scheduler.add("light", PERIOD, BUDGET);
+        let temperature = scheduler.add("temperature", PERIOD, BUDGET);
+        let start = Instant::now();
+
+        scheduler.begin_tick(start);
+        assert!(scheduler.is_granted(light));
+        assert!(!scheduler.is_granted(temperature));
+        scheduler.finish(light, start, start + Duration::from_millis(5));
+        scheduler.end_tick(start + Duration::from_millis(5));
+
+        let t = start + Duration::from_millis(10);
+        scheduler.begin_tick(t);
+        assert!(scheduler.is_granted(temperature));
+        scheduler.finish(temperature, t, t);
+        scheduler.end_tick(t);
+
+        // nothing due until the period of the first task is over
+        scheduler.begin_tick(start + Duration::from_millis(50));
+        assert!(!scheduler.is_granted(light) && !scheduler.is_granted(temperature));
+        scheduler.end_tick(start + Duration::from_millis(50));
+        scheduler.begin_tick(start + PERIOD);
+        assert!(scheduler.is_granted(light));
+    }
+
+    #[test]
+    fn grants_the_most_overdue_task() {
+        let mut scheduler = PollScheduler::new();
+        let a = scheduler.add("a", PERIOD, BUDGET);
+        let b = scheduler.add("b", PERIOD * 2, BUDGET);
+        let start = Instant::now();
+        scheduler.begin_tick(start);
+        scheduler.finish(a, start, start);
+        scheduler.begin_tick(start);
+        scheduler.finish(b, start - PERIOD * 2, start);
+        // a is due since start + PERIOD, b since start
+        scheduler.begin_tick(start + PERIOD * 3);
+        assert!(scheduler.is_granted(b));
+    }
+
+    #[test]
+    fn a_granted_task_without_poll_waits_for_its_next_period() {
+        let mut scheduler = PollScheduler::new();
+        let light = scheduler.add("light", PERIOD, BUDGET);
+        let start = Instant::now();
+        scheduler.begin_tick(start);
+        assert!(scheduler.is_granted(light));
+        scheduler.end_tick(start);
+        scheduler.begin_tick(start + PERIOD / 2);
+        assert!(!scheduler.is_granted(light));
+        scheduler.end_tick(start + PERIOD / 2);
+        scheduler.begin_tick(start + PERIOD);
+        assert!(scheduler.is_granted(light));
+    }
+
+    #[test]
+    fn counts_budget_overruns() {
+        let mut scheduler = PollScheduler::new();
+        let light = scheduler.add("light", PERIOD, BUDGET);
+        let start = Instant::now();
+        scheduler.begin_tick(start);
+        scheduler.finish(light, start, start + BUDGET * 2);
+        scheduler.end_tick(start + BUDGET * 2);
+        assert_eq!(scheduler.tasks[light.0].overruns, 1);
+        assert_eq!(scheduler.tasks[light.0].max_duration, BUDGET * 2);
+        assert_eq!(scheduler.last_tick_time, BUDGET * 2);
+    }
+}
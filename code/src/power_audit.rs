@@ -0,0 +1,82 @@
+//! Idle-phase power audit
+//!
+//! Runs the idle loop for a while per step, with one subsystem disabled per step, and measures the loop activity
+//! (share of the time the CPU is busy instead of waiting). Comparing the steps shows the contributors to the
+//! standby power. There is no current sensor on the board, so the report is about activity only;
+//! the ESP32-H2 has no Wi-Fi, so there is no radio step.
+
+use std::ffi::c_void;
+use std::time::Duration;
+
+use anyhow::Result;
+use esp_idf_svc::sys::{esp, esp_pm_config_t, esp_pm_configure};
+
+/// Duration of one audit step
+pub const AUDIT_STEP_DURATION: Duration = Duration::from_secs(5);
+pub const DEFAULT_CPU_FREQ_MHZ: i32 = 96;
+/// XTAL frequency
+pub const REDUCED_CPU_FREQ_MHZ: i32 = 32;
+
+#[derive(Debug)]
+pub struct AuditStep {
+    pub name: &'static str,
+    pub iterations: u32,
+    /// time spent outside the loop delay
+    pub busy: Duration,
+    pub duration: Duration,
+}
+
+impl AuditStep {
+    pub fn activity_pct(&self) -> f32 {
+        self.busy.as_secs_f32() / self.duration.as_secs_f32() * 100.0
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PowerAudit {
+    steps: Vec<AuditStep>,
+    /// steps which could not run
+    skipped: Vec<(&'static str, String)>,
+}
+
+impl PowerAudit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, step: AuditStep) {
+        log::info!("power audit: {}: activity {:.2} %", step.name, step.activity_pct());
+        self.steps.push(step);
+    }
+
+    pub fn skip(&mut self, name: &'static str, reason: String) {
+        log::info!("power audit: {} skipped: {}", name, reason);
+        self.skipped.push((name, reason));
+    }
+
+    /// One line per step with the activity relative to the first (baseline) step
+    pub fn report(&self) -> Vec<String> {
+        let baseline = self.steps.first().map(AuditStep::activity_pct);
+        let mut lines = self.steps.iter()
+            .map(|step| {
+                let difference = baseline.map_or(0.0, |b| step.activity_pct() - b);
+                format!("{}: activity {:.2} % ({:+.2} % vs. baseline), {} loop iterations, busy {} ms",
+                        step.name, step.activity_pct(), difference, step.iterations, step.busy.as_millis())
+            })
+            .collect::<Vec<_>>();
+        lines.extend(self.skipped.iter().map(|(name, reason)| format!("{}: skipped ({})", name, reason)));
+        lines.push("no current sensor - activity only".to_string());
+        lines
+    }
+}
+
+/// Sets the CPU frequency via power management - needs `CONFIG_PM_ENABLE`
+pub fn set_cpu_freq_mhz(mhz: i32) -> Result<()> {
+    let config = esp_pm_config_t {
+        max_freq_mhz: mhz,
+        min_freq_mhz: mhz,
+        light_sleep_enable: false,
+    };
+    esp!(unsafe { esp_pm_configure(&config as *const esp_pm_config_t as *const c_void) })?;
+    Ok(())
+}
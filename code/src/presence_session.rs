@@ -0,0 +1,58 @@
+//! Presence sessions
+//!
+//! A session starts with the first presence detection after a quiet period and ends, when no presence was detected
+//! for `camera_hold_s`. The start and the end are raised as events (e.g. for the webhooks); the start also fires the
+//! camera trigger (see [crate::camera_trigger]).
+
+use std::time::{Duration, Instant};
+
+use crate::event::{Event, EventQueue};
+
+#[derive(Debug, Default)]
+pub struct PresenceSession {
+    active: bool,
+    last_presence: Option<Instant>,
+}
+
+impl PresenceSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether a new session started
+    pub fn update(&mut self, presence: bool, hold: Duration, now: Instant, events: &mut EventQueue) -> bool {
+        if presence {
+            self.last_presence = Some(now);
+            if !self.active {
+                self.active = true;
+                events.push(Event::PresenceStart);
+                return true;
+            }
+        } else if self.active && self.last_presence.is_some_and(|last| now.duration_since(last) >= hold) {
+            self.active = false;
+            events.push(Event::PresenceEnd);
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOLD: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn session_ends_after_the_hold_time() {
+        let start = Instant::now();
+        let mut session = PresenceSession::new();
+        let mut events = EventQueue::default();
+        assert!(session.update(true, HOLD, start, &mut events));
+        assert!(!session.update(true, HOLD, start + Duration::from_secs(1), &mut events));
+        assert!(!session.update(false, HOLD, start + Duration::from_secs(30), &mut events));
+        assert_eq!(events.drain().collect::<Vec<_>>(), vec![Event::PresenceStart]);
+        assert!(!session.update(false, HOLD, start + Duration::from_secs(31), &mut events));
+        assert_eq!(events.drain().collect::<Vec<_>>(), vec![Event::PresenceEnd]);
+        assert!(session.update(true, HOLD, start + Duration::from_secs(40), &mut events));
+    }
+}
@@ -0,0 +1,102 @@
+//! Behavior presets for common installations
+//!
+//! A preset sets the relevant parameters (thresholds, hold times, ramps) in one step.
+//! All other parameters are left untouched and everything can be fine-tuned afterwards.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use crate::config::Config;
+use crate::lux_filter::LuxFilterKind;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Preset {
+    /// quick reaction, full brightness, stays dark-latched for the night
+    Staircase,
+    /// only in real darkness, soft ramps, trailing light
+    HallwayNightLight,
+    /// whenever the door opens - no darkness gating in practice, instant light
+    Closet,
+    /// robust against headlights and moving plants
+    OutdoorEntry,
+}
+
+impl Preset {
+    pub fn apply(self, config: &mut Config) {
+        match self {
+            Preset::Staircase => {
+                config.lux_threshold = 20.0;
+                config.lux_filter = LuxFilterKind::TimeWeightedMedian;
+                config.lux_window = 10;
+                config.retrigger_guard_ms = 2000;
+                config.retrigger_confirm_ms = 500;
+                config.fade_up_s = 3.0;
+                config.fade_down_s = 10.0;
+                config.zone_trail_s = 0;
+                config.dark_latch_min = 10;
+            }
+            Preset::HallwayNightLight => {
+                config.lux_threshold = 5.0;
+                config.lux_filter = LuxFilterKind::TimeWeightedMedian;
+                config.lux_window = 10;
+                config.retrigger_guard_ms = 3000;
+                config.retrigger_confirm_ms = 1000;
+                config.fade_up_s = 8.0;
+                config.fade_down_s = 20.0;
+                config.zone_trail_s = 60;
+                config.dark_latch_min = 30;
+            }
+            Preset::Closet => {
+                config.lux_threshold = 1000.0;
+                config.lux_filter = LuxFilterKind::ExponentialMovingAverage;
+                config.lux_window = 4;
+                config.retrigger_guard_ms = 0;
+                config.retrigger_confirm_ms = 0;
+                config.fade_up_s = 1.0;
+                config.fade_down_s = 5.0;
+                config.zone_trail_s = 0;
+                config.dark_latch_min = 0;
+            }
+            Preset::OutdoorEntry => {
+                config.lux_threshold = 10.0;
+                config.lux_filter = LuxFilterKind::TrimmedMean;
+                config.lux_window = 20;
+                config.retrigger_guard_ms = 5000;
+                config.retrigger_confirm_ms = 2000;
+                config.fade_up_s = 5.0;
+                config.fade_down_s = 15.0;
+                config.zone_trail_s = 30;
+                config.dark_latch_min = 15;
+                config.dark_release_lux = 200.0;
+            }
+        }
+        config.touch();
+    }
+}
+
+impl Display for Preset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Preset::Staircase => "staircase",
+            Preset::HallwayNightLight => "hallway",
+            Preset::Closet => "closet",
+            Preset::OutdoorEntry => "outdoor",
+        })
+    }
+}
+
+impl FromStr for Preset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "staircase" => Ok(Preset::Staircase),
+            "hallway" => Ok(Preset::HallwayNightLight),
+            "closet" => Ok(Preset::Closet),
+            "outdoor" => Ok(Preset::OutdoorEntry),
+            _ => Err(anyhow!("unknown preset '{}' (expected staircase, hallway, closet or outdoor)", s))
+        }
+    }
+}
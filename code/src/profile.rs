@@ -0,0 +1,53 @@
+//! Named configuration profiles
+//!
+//! A profile stores the values of the parameters, which differ between installations - thresholds, fade times and
+//! brightness - under a name like `stairs`, `hallway` or `wardrobe`, so that one firmware serves all of them.
+//! Selecting a profile (parameter `profile`) applies its values; all other parameters are left untouched.
+//! Unlike a [crate::presets::Preset], a profile is defined on the device (`profile save <name>`).
+
+use anyhow::{bail, Result};
+
+use crate::config::{Config, Param};
+
+/// Parameters stored in a profile
+pub const PROFILE_PARAMS: [Param; 7] = [
+    Param::LuxThreshold,
+    Param::DarkReleaseLux,
+    Param::FadeUpS,
+    Param::FadeDownS,
+    Param::ZoneTrailS,
+    Param::MaxBrightnessPct,
+    Param::NightLightPct,
+];
+
+/// Limited by the NVS key length
+pub const MAX_NAME_LEN: usize = 10;
+
+pub fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty() && name.len() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !valid {
+        bail!("a profile name has 1 to {} characters a-z, 0-9 or _", MAX_NAME_LEN);
+    }
+    Ok(())
+}
+
+/// The profile values of the configuration, e.g. `lux_threshold=20;fade_up_s=3;...`
+pub fn encode(config: &Config) -> String {
+    PROFILE_PARAMS.iter()
+        .map(|param| format!("{}={}", param, config.get(*param)))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// The configuration with the stored profile values applied
+pub fn apply(config: &Config, values: &str) -> Result<Config> {
+    let mut applied = config.clone();
+    for entry in values.split(';').filter(|entry| !entry.is_empty()) {
+        let Some((name, value)) = entry.split_once('=') else {
+            bail!("invalid profile entry '{}'", entry);
+        };
+        applied.set(name.parse()?, value)?;
+    }
+    Ok(applied)
+}
@@ -0,0 +1,380 @@
+//! Ramp engine - moves the LED power stage towards the target stage set by the logic
+//!
+//! The progress is derived from the elapsed time, so a fade takes the configured duration
+//! independent of the loop timing and its jitter. The [RampShape] eases the start and the end of a movement.
+//! With a takeover time the (linear) ramp is velocity controlled instead: it accelerates to the fade rate and brakes
+//! in time for the target, so that a new target - also in the opposite direction - is taken over without a kink.
+
+use std::f32::consts::PI;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+
+use crate::LED_MAX_POWER_STAGE;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Phase {
+    Off,
+    PowerDown,
+    PowerUp,
+    /// steady light at the target stage (which might be a partial level)
+    On,
+}
+
+/// Progress of a movement over its time
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum RampShape {
+    /// constant rate
+    Linear,
+    /// ease-in-out along a sine - slow start and end
+    Sine,
+    /// ease-in-out exponential - very slow start and end, fast in the middle
+    Exponential,
+}
+
+impl RampShape {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            RampShape::Linear => 0,
+            RampShape::Sine => 1,
+            RampShape::Exponential => 2,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(RampShape::Linear),
+            1 => Some(RampShape::Sine),
+            2 => Some(RampShape::Exponential),
+            _ => None
+        }
+    }
+
+    /// share of the movement [0..=1] done at the time share `x` [0..=1]
+    fn ease(self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            RampShape::Linear => x,
+            RampShape::Sine => (1.0 - (PI * x).cos()) / 2.0,
+            RampShape::Exponential if x == 0.0 || x == 1.0 => x,
+            RampShape::Exponential if x < 0.5 => 2f32.powf(20.0 * x - 10.0) / 2.0,
+            RampShape::Exponential => (2.0 - 2f32.powf(10.0 - 20.0 * x)) / 2.0,
+        }
+    }
+}
+
+impl Display for RampShape {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RampShape::Linear => "linear",
+            RampShape::Sine => "sine",
+            RampShape::Exponential => "exponential",
+        })
+    }
+}
+
+impl FromStr for RampShape {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(RampShape::Linear),
+            "sine" => Ok(RampShape::Sine),
+            "exponential" => Ok(RampShape::Exponential),
+            _ => Err(anyhow!("unknown ramp shape '{}' (expected linear, sine or exponential)", s))
+        }
+    }
+}
+
+/// An eased movement - from its start stage to its target
+#[derive(Copy, Clone, Debug)]
+struct Movement {
+    from: u32,
+    to: u32,
+    start: Instant,
+}
+
+#[derive(Debug)]
+pub struct Ramp {
+    /// range: 0..=LED_MAX_POWER_STAGE
+    stage: u32,
+    /// range: 0..=LED_MAX_POWER_STAGE
+    target_stage: u32,
+    /// time of the last progress while moving
+    last_advance: Option<Instant>,
+    /// fraction of a stage not yet applied
+    carry: f32,
+    /// the current movement with an eased shape
+    movement: Option<Movement>,
+    /// exact stage and velocity [stages/s] of the velocity controlled movement
+    position: f32,
+    velocity: f32,
+}
+
+impl Ramp {
+    pub fn new() -> Self {
+        Self {
+            stage: 0,
+            target_stage: 0,
+            last_advance: None,
+            carry: 0.0,
+            movement: None,
+            position: 0.0,
+            velocity: 0.0,
+        }
+    }
+
+    pub fn stage(&self) -> u32 {
+        self.stage
+    }
+
+    pub fn target_stage(&self) -> u32 {
+        self.target_stage
+    }
+
+    pub fn set_target_stage(&mut self, target_stage: u32) {
+        self.target_stage = target_stage.min(LED_MAX_POWER_STAGE);
+    }
+
+    pub fn phase(&self) -> Phase {
+        if self.stage < self.target_stage {
+            Phase::PowerUp
+        } else if self.stage > self.target_stage {
+            Phase::PowerDown
+        } else if self.stage == 0 {
+            Phase::Off
+        } else {
+            Phase::On
+        }
+    }
+
+    /// Sets the stage to the target stage immediately
+    pub fn jump_to_target(&mut self) {
+        self.stage = self.target_stage;
+        self.velocity = 0.0;
+    }
+
+    /// Moves the stage towards the target stage according to the time elapsed since the last advance,
+    /// at a rate of `fade` for the full stage range (on average with an eased `shape`).
+    /// With a `takeover` time (and the linear shape) the movement is velocity controlled - `takeover` is the time to
+    /// reach the fade rate from standstill.
+    /// The first advance of a movement takes one stage immediately.
+    /// Returns the new phase, when the target was reached.
+    pub fn advance(&mut self, fade: Duration, shape: RampShape, takeover: Duration, now: Instant) -> Option<Phase> {
+        if self.stage == self.target_stage {
+            self.last_advance = None;
+            self.movement = None;
+            self.velocity = 0.0;
+            return None;
+        }
+        if !takeover.is_zero() && !fade.is_zero() && shape == RampShape::Linear {
+            return self.advance_smooth(fade, takeover, now);
+        }
+        self.velocity = 0.0;
+        let distance = self.stage.abs_diff(self.target_stage);
+        let stages = match self.last_advance {
+            _ if shape != RampShape::Linear => self.eased_stages(fade, shape, now),
+            _ if fade.is_zero() => distance,
+            Some(t) => {
+                let progress = now.duration_since(t).as_secs_f32() / fade.as_secs_f32() * LED_MAX_POWER_STAGE as f32 + self.carry;
+                self.carry = progress.fract();
+                progress as u32
+            }
+            None => {
+                self.carry = 0.0;
+                1
+            }
+        }.min(distance);
+        self.last_advance = Some(now);
+
+        if self.stage < self.target_stage {
+            self.stage += stages;
+        } else {
+            self.stage -= stages;
+        }
+        if self.stage == self.target_stage {
+            self.last_advance = None;
+            self.movement = None;
+            Some(self.phase())
+        } else {
+            None
+        }
+    }
+
+    // velocity controlled: accelerates towards the fade rate, brakes in time for the target and never passes it
+    fn advance_smooth(&mut self, fade: Duration, takeover: Duration, now: Instant) -> Option<Phase> {
+        let Some(last_advance) = self.last_advance else {
+            self.last_advance = Some(now);
+            if self.velocity == 0.0 {
+                // from standstill
+                self.stage = if self.stage < self.target_stage { self.stage + 1 } else { self.stage - 1 };
+                self.position = self.stage as f32;
+            }
+            return self.reached();
+        };
+        self.last_advance = Some(now);
+        let dt = now.duration_since(last_advance).as_secs_f32();
+        let max_speed = LED_MAX_POWER_STAGE as f32 / fade.as_secs_f32();
+        let acceleration = max_speed / takeover.as_secs_f32();
+        let distance = self.target_stage as f32 - self.position;
+        // the speed, from which we can still stop at the target
+        let speed = max_speed.min((2.0 * acceleration * distance.abs()).sqrt());
+        let velocity_change = (distance.signum() * speed - self.velocity).clamp(-acceleration * dt, acceleration * dt);
+        self.velocity += velocity_change;
+        let position = self.position + self.velocity * dt;
+        let remaining = self.target_stage as f32 - position;
+        if remaining.abs() < 0.5 || remaining.signum() != distance.signum() {
+            self.position = self.target_stage as f32;
+            self.velocity = 0.0;
+        } else {
+            self.position = position.clamp(0.0, LED_MAX_POWER_STAGE as f32);
+        }
+        self.stage = self.position.round() as u32;
+        self.reached()
+    }
+
+    // the new phase, if the target was reached
+    fn reached(&mut self) -> Option<Phase> {
+        if self.stage != self.target_stage {
+            return None;
+        }
+        self.last_advance = None;
+        self.velocity = 0.0;
+        Some(self.phase())
+    }
+
+    // stages to go for an eased movement - a new target starts a new movement from the current stage
+    fn eased_stages(&mut self, fade: Duration, shape: RampShape, now: Instant) -> u32 {
+        let movement = match self.movement {
+            Some(movement) if movement.to == self.target_stage => movement,
+            _ => {
+                let movement = Movement { from: self.stage, to: self.target_stage, start: now };
+                self.movement = Some(movement);
+                movement
+            }
+        };
+        let distance = movement.from.abs_diff(movement.to);
+        let duration = fade.mul_f32(distance as f32 / LED_MAX_POWER_STAGE as f32);
+        let x = if duration.is_zero() {
+            1.0
+        } else {
+            now.duration_since(movement.start).as_secs_f32() / duration.as_secs_f32()
+        };
+        let done = ((distance as f32 * shape.ease(x)).round() as u32).max(1);
+        done.saturating_sub(movement.from.abs_diff(self.stage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FADE: Duration = Duration::from_secs(1);
+
+    fn ramp_to(target_stage: u32) -> Ramp {
+        let mut ramp = Ramp::new();
+        ramp.set_target_stage(target_stage);
+        ramp
+    }
+
+    #[test]
+    fn ease_starts_at_0_and_ends_at_1() {
+        for shape in [RampShape::Linear, RampShape::Sine, RampShape::Exponential] {
+            assert_eq!(shape.ease(0.0), 0.0, "{}", shape);
+            assert_eq!(shape.ease(1.0), 1.0, "{}", shape);
+            assert!((shape.ease(0.5) - 0.5).abs() < 1e-6, "{}", shape);
+            // out of range time shares are clamped
+            assert_eq!(shape.ease(-0.5), 0.0, "{}", shape);
+            assert_eq!(shape.ease(1.5), 1.0, "{}", shape);
+        }
+    }
+
+    #[test]
+    fn ease_is_monotonic() {
+        for shape in [RampShape::Linear, RampShape::Sine, RampShape::Exponential] {
+            let values = (0..=100).map(|i| shape.ease(i as f32 / 100.0)).collect::<Vec<_>>();
+            assert!(values.windows(2).all(|w| w[0] <= w[1]), "{}", shape);
+        }
+    }
+
+    #[test]
+    fn linear_fade_takes_the_fade_duration() {
+        let start = Instant::now();
+        let mut ramp = ramp_to(LED_MAX_POWER_STAGE);
+        assert_eq!(ramp.phase(), Phase::PowerUp);
+        // the first advance takes one stage right away
+        assert_eq!(ramp.advance(FADE, RampShape::Linear, Duration::ZERO, start), None);
+        assert_eq!(ramp.stage(), 1);
+        assert_eq!(ramp.advance(FADE, RampShape::Linear, Duration::ZERO, start + FADE / 2), None);
+        assert_eq!(ramp.stage(), 1 + LED_MAX_POWER_STAGE / 2);
+        assert_eq!(ramp.advance(FADE, RampShape::Linear, Duration::ZERO, start + FADE), Some(Phase::On));
+        assert_eq!(ramp.stage(), LED_MAX_POWER_STAGE);
+    }
+
+    #[test]
+    fn linear_fade_progress_does_not_depend_on_the_loop_timing() {
+        let start = Instant::now();
+        let mut ramp = ramp_to(LED_MAX_POWER_STAGE);
+        ramp.advance(FADE, RampShape::Linear, Duration::ZERO, start);
+        // uneven steps, each shorter than one stage - the fractions are carried over (up to a rounding error)
+        for ms in [0.3, 0.7, 1.2, 1.9, 2.5, 3.3, 4.0] {
+            ramp.advance(FADE, RampShape::Linear, Duration::ZERO, start + Duration::from_secs_f32(ms / 1000.0));
+        }
+        assert!((4..=5).contains(&ramp.stage()), "{}", ramp.stage());
+    }
+
+    #[test]
+    fn zero_fade_jumps_to_the_target() {
+        let mut ramp = ramp_to(LED_MAX_POWER_STAGE / 2);
+        assert_eq!(ramp.advance(Duration::ZERO, RampShape::Linear, Duration::ZERO, Instant::now()), Some(Phase::On));
+        assert_eq!(ramp.stage(), LED_MAX_POWER_STAGE / 2);
+        ramp.set_target_stage(0);
+        assert_eq!(ramp.advance(Duration::ZERO, RampShape::Sine, Duration::ZERO, Instant::now()), Some(Phase::Off));
+    }
+
+    #[test]
+    fn partial_fade_takes_proportionally_less_time() {
+        let start = Instant::now();
+        let mut ramp = ramp_to(LED_MAX_POWER_STAGE / 4);
+        ramp.advance(FADE, RampShape::Sine, Duration::ZERO, start);
+        assert_eq!(ramp.advance(FADE, RampShape::Sine, Duration::ZERO, start + FADE / 8), None);
+        assert_eq!(ramp.stage(), LED_MAX_POWER_STAGE / 8);
+        assert_eq!(ramp.advance(FADE, RampShape::Sine, Duration::ZERO, start + FADE / 4), Some(Phase::On));
+    }
+
+    #[test]
+    fn eased_fade_starts_and_ends_slowly() {
+        let start = Instant::now();
+        let mut ramp = ramp_to(LED_MAX_POWER_STAGE);
+        ramp.advance(FADE, RampShape::Exponential, Duration::ZERO, start);
+        assert_eq!(ramp.stage(), 1);
+        ramp.advance(FADE, RampShape::Exponential, Duration::ZERO, start + FADE / 10);
+        let early = ramp.stage();
+        ramp.advance(FADE, RampShape::Exponential, Duration::ZERO, start + FADE / 2);
+        assert_eq!(ramp.stage(), LED_MAX_POWER_STAGE / 2);
+        ramp.advance(FADE, RampShape::Exponential, Duration::ZERO, start + FADE * 9 / 10);
+        let late = ramp.stage();
+        assert!(early < LED_MAX_POWER_STAGE / 100, "{}", early);
+        assert!(late > LED_MAX_POWER_STAGE * 99 / 100, "{}", late);
+        assert_eq!(ramp.advance(FADE, RampShape::Exponential, Duration::ZERO, start + FADE), Some(Phase::On));
+    }
+
+    #[test]
+    fn takeover_reaches_the_target_without_passing_it() {
+        let start = Instant::now();
+        let takeover = Duration::from_millis(200);
+        let mut ramp = ramp_to(LED_MAX_POWER_STAGE);
+        let mut t = start;
+        let mut result = None;
+        while result.is_none() && t < start + FADE * 2 {
+            result = ramp.advance(FADE, RampShape::Linear, takeover, t);
+            assert!(ramp.stage() <= LED_MAX_POWER_STAGE);
+            t += Duration::from_millis(10);
+        }
+        assert_eq!(result, Some(Phase::On));
+        // about the fade duration plus the takeover time for accelerating and braking
+        assert!(t > start + FADE && t < start + FADE + takeover * 2, "{:?}", t - start);
+    }
+}
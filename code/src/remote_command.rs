@@ -0,0 +1,65 @@
+//! JSON commands of the remote control interfaces (MQTT, REST API)
+//!
+//! A command like `{"id": 1, "command": "max_brightness", "value": 80}` is translated into a console [Command],
+//! which the main loop applies like a command from the serial console.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use crate::config::Param;
+use crate::console::Command;
+use crate::mode::Mode;
+
+/// A received command: the `id` of the request (to be echoed) and the parsed command
+pub struct RemoteCommand {
+    pub id: Value,
+    pub command: Result<Command>,
+}
+
+pub fn parse_command(data: &[u8]) -> RemoteCommand {
+    let request = match serde_json::from_slice::<Value>(data) {
+        Ok(request) => request,
+        Err(e) => return RemoteCommand { id: Value::Null, command: Err(anyhow!("invalid JSON: {}", e)) }
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let value = || match request.get("value") {
+        Some(Value::Number(n)) => Ok(n.to_string()),
+        _ => Err(anyhow!("numeric 'value' expected"))
+    };
+    let command = match request.get("command").and_then(Value::as_str) {
+        Some("force_on") => Ok(Command::Mode(Some(Mode::ForcedOn))),
+        Some("force_off") => Ok(Command::Mode(Some(Mode::ForcedOff))),
+        Some("auto") => Ok(Command::Mode(Some(Mode::Auto))),
+        Some("max_brightness") => value().map(|v| Command::Set(Param::MaxBrightnessPct, v)),
+        Some("lux_threshold") => value().map(|v| Command::Set(Param::LuxThreshold, v)),
+        Some("power_cycle_sensor") => Ok(Command::PowerCyclePresenceSensor),
+        Some("ota") => match request.get("url").and_then(Value::as_str) {
+            Some(url) => Ok(Command::Ota(url.to_string())),
+            None => Err(anyhow!("'url' expected"))
+        },
+        Some("profile") => match request.get("name").and_then(Value::as_str) {
+            Some(name) => Ok(Command::Set(Param::Profile, name.to_string())),
+            None => Err(anyhow!("'name' expected"))
+        },
+        Some(other) => Err(anyhow!("unknown command '{}'", other)),
+        None => Err(anyhow!("'command' expected"))
+    };
+    RemoteCommand { id, command }
+}
+
+/// Parses a JSON object of parameter values like `{"lux_threshold": 20, "led_full_watts": 12.5}` into set commands
+pub fn parse_config(data: &[u8]) -> Result<Vec<Command>> {
+    let Value::Object(params) = serde_json::from_slice::<Value>(data)? else {
+        anyhow::bail!("JSON object of parameter values expected");
+    };
+    params.iter()
+        .map(|(name, value)| {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                Value::Number(_) | Value::Bool(_) => value.to_string(),
+                _ => anyhow::bail!("invalid value of {}", name)
+            };
+            Ok(Command::Set(name.parse()?, value))
+        })
+        .collect()
+}
@@ -0,0 +1,122 @@
+//! Remote diagnostics (opt-in)
+//!
+//! Periodically uploads a small JSON report (reset reason, metrics, recent events) to a configured
+//! HTTP endpoint - a self-hosted alternative to ESP Insights, which works the same for all installed units.
+//! Nothing leaves the device unless `diag_opt_in` is set.
+//! With `local_only_analytics` the report contains no occupancy related data (see [ExportFilter] and
+//! [ExportedStatus]) - neither the occupancy events nor the light phase and energy, but the number of presences since
+//! the last report.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use esp_idf_svc::sys;
+
+use crate::config::Config;
+use crate::event::{Event, ExportFilter, ExportedStatus};
+use crate::http_client::post_json;
+
+const UPLOAD_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// First upload after boot - earlier, so that crash reports arrive soon
+const FIRST_UPLOAD_DELAY: Duration = Duration::from_secs(60);
+const MAX_RECENT_EVENTS: usize = 20;
+
+pub struct RemoteDiagnostics {
+    boot_time: Instant,
+    reset_reason: &'static str,
+    recent_events: Vec<String>,
+    last_upload_attempt: Option<Instant>,
+}
+
+impl RemoteDiagnostics {
+    pub fn new() -> Self {
+        Self {
+            boot_time: Instant::now(),
+            reset_reason: reset_reason(),
+            recent_events: Vec::new(),
+            last_upload_attempt: None,
+        }
+    }
+
+    pub fn record_event(&mut self, event: &Event) {
+        if self.recent_events.len() == MAX_RECENT_EVENTS {
+            self.recent_events.remove(0);
+        }
+        self.recent_events.push(event.to_string());
+    }
+
+    /// Uploads a report, if enabled and due
+    pub fn update(&mut self, config: &Config, status: &ExportedStatus, export_filter: &mut ExportFilter, now: Instant) {
+        if !config.diag_opt_in || config.diag_url.is_empty() {
+            return;
+        }
+        let due = match self.last_upload_attempt {
+            Some(t) => now.duration_since(t) >= UPLOAD_INTERVAL,
+            None => now.duration_since(self.boot_time) >= FIRST_UPLOAD_DELAY
+        };
+        if !due {
+            return;
+        }
+        self.last_upload_attempt = Some(now);
+        let url = config.diag_url.clone();
+        let presences = export_filter.take_presence_count();
+        let report = self.report(status, presences, now);
+        self.recent_events.clear();
+        // upload in the background, so that the light control is not blocked
+        let result = thread::Builder::new()
+            .name("diagnostics".to_string())
+            .stack_size(8192)
+            .spawn(move || {
+                if let Err(e) = post_json(&url, &report) {
+                    log::warn!("diagnostics upload failed: {}", e);
+                }
+            });
+        if let Err(e) = result {
+            log::warn!("diagnostics upload not started: {}", e);
+        }
+    }
+
+    /// `presences`: aggregate count of the withheld presences - replaces the light phase with local-only analytics
+    fn report(&self, status: &ExportedStatus, presences: u32, now: Instant) -> String {
+        let events = self.recent_events.iter()
+            .map(|e| format!("\"{}\"", e))
+            .collect::<Vec<_>>()
+            .join(",");
+        let occupancy = match &status.occupancy {
+            Some(occupancy) => format!("\"phase\":\"{:?}\"", occupancy.phase),
+            None => format!("\"presences\":{}", presences)
+        };
+        let energy = match status.occupancy.as_ref().filter(|occupancy| occupancy.led_watts.is_some()) {
+            Some(occupancy) => format!("\"energy_today_wh\":{:.1},", occupancy.energy_today_wh),
+            None => String::new()
+        };
+        format!(
+            "{{\"firmware\":\"{}\",\"reset_reason\":\"{}\",\"uptime_s\":{},\"free_heap\":{},\
+             \"light_sensor_failures\":{},\"mode\":\"{}\",{},{}\"events\":[{}]}}",
+            env!("CARGO_PKG_VERSION"),
+            self.reset_reason,
+            now.duration_since(self.boot_time).as_secs(),
+            unsafe { sys::esp_get_free_heap_size() },
+            status.light_sensor_failures,
+            status.mode,
+            occupancy,
+            energy,
+            events
+        )
+    }
+}
+
+fn reset_reason() -> &'static str {
+    #[allow(non_upper_case_globals)]
+    match unsafe { sys::esp_reset_reason() } {
+        sys::esp_reset_reason_t_ESP_RST_POWERON => "power-on",
+        sys::esp_reset_reason_t_ESP_RST_SW => "software",
+        sys::esp_reset_reason_t_ESP_RST_PANIC => "panic",
+        sys::esp_reset_reason_t_ESP_RST_INT_WDT => "interrupt watchdog",
+        sys::esp_reset_reason_t_ESP_RST_TASK_WDT => "task watchdog",
+        sys::esp_reset_reason_t_ESP_RST_WDT => "watchdog",
+        sys::esp_reset_reason_t_ESP_RST_DEEPSLEEP => "deep-sleep",
+        sys::esp_reset_reason_t_ESP_RST_BROWNOUT => "brownout",
+        _ => "other"
+    }
+}
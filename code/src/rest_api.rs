@@ -0,0 +1,252 @@
+//! HTTP REST API (opt-in)
+//!
+//! - `GET /status`: the latest status snapshot as JSON - with `local_only_analytics` without the light phase, the duty,
+//!   the presence and the LED power (see [ExportedStatus])
+//! - `GET /config?params=<name>,...`: values of the given parameters as JSON object (strings like `get`) - except
+//!   the credentials in `api_token`, `mqtt_url`, `webhook_urls`, `influx_url` and `influx_token`
+//! - `POST /config`: sets parameters, e.g. `{"lux_threshold": 20, "max_brightness_pct": 80}` (stored like `set`)
+//! - `GET /config/export`: the configuration as JSON export (see [crate::config_transfer]) - without the credentials
+//! - `POST /config/import`: applies a JSON export as a whole (stored like `set`)
+//! - `POST /command`: a command like on the MQTT command topic, e.g. `{"command": "force_on"}`
+//!
+//! The handlers run in the task of the HTTP server. Changes are handed over to the main loop like console commands;
+//! the handler waits for the result. The POST requests need the header `Authorization: Bearer <api_token>` - they are
+//! refused while `api_token` is empty.
+
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use embedded_svc::http::Headers;
+use embedded_svc::io::{Read, Write};
+use esp_idf_svc::http::server::{Configuration, EspHttpConnection, EspHttpServer, Request};
+use esp_idf_svc::http::Method;
+use serde_json::{json, Map, Value};
+
+use crate::config::{Config, ConfigWatch, Param};
+use crate::console::Command;
+use crate::event::ExportedStatus;
+use crate::remote_command::{parse_command, parse_config};
+use crate::status::SharedStatus;
+
+/// Max. time a handler waits for the main loop to apply a command
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_BODY_LEN: usize = 1024;
+/// Max. size of a configuration import - all parameters
+#[cfg(feature = "config-transfer")]
+const MAX_IMPORT_LEN: usize = 8192;
+const SERVER_STACK_SIZE: usize = 10240;
+
+/// A command handed over to the main loop with the channel for its result
+pub struct ApiRequest {
+    pub command: Command,
+    reply: SyncSender<Result<(), String>>,
+}
+
+impl ApiRequest {
+    pub fn reply(self, result: &Result<()>) {
+        let _ = self.reply.send(result.as_ref().map(|_| ()).map_err(|e| e.to_string()));
+    }
+}
+
+pub struct RestApi {
+    _server: EspHttpServer<'static>,
+    receiver: Receiver<ApiRequest>,
+    /// copy of the current configuration for the handlers (e.g. `GET /config` and the `api_token` check)
+    config: Arc<Mutex<Config>>,
+    config_watch: ConfigWatch,
+}
+
+impl RestApi {
+    pub fn start(status: SharedStatus, config: &Config) -> Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let config = Arc::new(Mutex::new(config.clone()));
+        let mut server = EspHttpServer::new(&Configuration {
+            stack_size: SERVER_STACK_SIZE,
+            // for the path parameters of the Hue API
+            uri_match_wildcard: cfg!(feature = "hue"),
+            ..Default::default()
+        })?;
+
+        #[cfg(feature = "websocket")]
+        let status_for_telemetry = status.clone();
+        #[cfg(feature = "hue")]
+        let status_for_hue = status.clone();
+        let status_config = config.clone();
+        server.fn_handler("/status", Method::Get, move |request| {
+            let body = match status.get() {
+                Some(snapshot) => status_json(&ExportedStatus::new(&status_config.lock().unwrap(), &snapshot)).to_string(),
+                None => "null".to_string()
+            };
+            respond(request, 200, &body)
+        })?;
+
+        let shared_config = config.clone();
+        server.fn_handler("/config", Method::Get, move |request| {
+            let params = request.uri().split_once("?params=").map(|(_, params)| params.to_string()).unwrap_or_default();
+            let config = shared_config.lock().unwrap();
+            let mut values = Map::new();
+            for name in params.split(',').filter(|name| !name.is_empty()) {
+                match name.parse::<Param>() {
+                    Ok(param) if param.is_credential() => return respond_error(request, 403, &format!("{} is not readable", name)),
+                    Ok(param) => values.insert(name.to_string(), Value::from(config.get(param))),
+                    Err(e) => return respond_error(request, 400, &e.to_string())
+                };
+            }
+            respond(request, 200, &Value::Object(values).to_string())
+        })?;
+
+        let config_sender = sender.clone();
+        #[cfg(feature = "hue")]
+        let hue_sender = sender.clone();
+        let config_auth = config.clone();
+        server.fn_handler("/config", Method::Post, move |mut request| {
+            if !authorized(&request, &config_auth) {
+                return respond_error(request, 401, "unauthorized");
+            }
+            let commands = match read_body(&mut request).and_then(|body| parse_config(&body)) {
+                Ok(commands) => commands,
+                Err(e) => return respond_error(request, 400, &e.to_string())
+            };
+            for command in commands {
+                if let Err(e) = apply(&config_sender, command) {
+                    return respond_error(request, 422, &e);
+                }
+            }
+            respond(request, 200, &json!({"ok": true}).to_string())
+        })?;
+
+        #[cfg(feature = "config-transfer")]
+        {
+            let export_config = config.clone();
+            server.fn_handler("/config/export", Method::Get, move |request| {
+                let export = crate::config_transfer::export(&export_config.lock().unwrap(), false);
+                respond(request, 200, &export)
+            })?;
+
+            let import_sender = sender.clone();
+            let import_auth = config.clone();
+            server.fn_handler("/config/import", Method::Post, move |mut request| {
+                if !authorized(&request, &import_auth) {
+                    return respond_error(request, 401, "unauthorized");
+                }
+                let json = match read_body_max(&mut request, MAX_IMPORT_LEN).and_then(|body| Ok(String::from_utf8(body)?)) {
+                    Ok(json) => json,
+                    Err(e) => return respond_error(request, 400, &e.to_string())
+                };
+                match apply(&import_sender, Command::ImportConfig(json)) {
+                    Ok(()) => respond(request, 200, &json!({"ok": true}).to_string()),
+                    Err(e) => respond_error(request, 422, &e)
+                }
+            })?;
+        }
+
+        let command_auth = config.clone();
+        server.fn_handler("/command", Method::Post, move |mut request| {
+            if !authorized(&request, &command_auth) {
+                return respond_error(request, 401, "unauthorized");
+            }
+            let remote = match read_body(&mut request) {
+                Ok(body) => parse_command(&body),
+                Err(e) => return respond_error(request, 400, &e.to_string())
+            };
+            let command = match remote.command {
+                Ok(command) => command,
+                Err(e) => return respond_error(request, 400, &e.to_string())
+            };
+            match apply(&sender, command) {
+                Ok(()) => respond(request, 200, &json!({"id": remote.id, "ok": true}).to_string()),
+                Err(e) => respond_error(request, 422, &e)
+            }
+        })?;
+
+        #[cfg(feature = "web-ui")]
+        crate::web_ui::register(&mut server)?;
+        #[cfg(feature = "websocket")]
+        crate::telemetry::register(&mut server, status_for_telemetry, config.clone())?;
+        #[cfg(feature = "hue")]
+        crate::hue::register(&mut server, hue_sender, status_for_hue, config.clone())?;
+
+        log::info!("REST API started");
+        Ok(Self { _server: server, receiver, config, config_watch: ConfigWatch::new() })
+    }
+
+    /// Updates the configuration copy, if the configuration changed
+    pub fn update_config(&mut self, config: &Config) {
+        if self.config_watch.changed(config) {
+            *self.config.lock().unwrap() = config.clone();
+        }
+    }
+
+    /// Returns the next pending command (non-blocking)
+    pub fn poll(&self) -> Option<ApiRequest> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// The status as JSON object - without the occupancy related fields with `local_only_analytics`
+pub fn status_json(status: &ExportedStatus) -> Value {
+    let mut json = json!({
+        "mode": status.mode.to_string(),
+        "dark_enough": status.dark_enough,
+        "darkness_latched": status.darkness_latched,
+        "lux": status.lux,
+        "light_reading": status.light_reading.map(|r| json!({"lux": r.lux, "raw": r.raw, "white_raw": r.white_raw})),
+        "light_sensor_failures": status.light_sensor_failures,
+        "light_sensor_available": status.light_sensor_available,
+        "presence_sensor_enabled": status.presence_sensor_enabled,
+    });
+    if let Some(occupancy) = &status.occupancy {
+        json["phase"] = Value::from(format!("{:?}", occupancy.phase));
+        json["led_power_stage"] = Value::from(occupancy.led_power_stage);
+        json["target_stage"] = Value::from(occupancy.target_stage);
+        json["duty"] = Value::from(occupancy.duty);
+        json["max_duty"] = Value::from(occupancy.max_duty);
+        json["presence"] = Value::from(occupancy.presence);
+        json["reaction_latency_ms"] = Value::from(occupancy.reaction_latency_ms);
+        json["led_watts"] = Value::from(occupancy.led_watts);
+        json["energy_today_wh"] = Value::from(occupancy.energy_today_wh);
+    }
+    json
+}
+
+/// Hands the command over to the main loop and waits for the result
+pub fn apply(sender: &Sender<ApiRequest>, command: Command) -> Result<(), String> {
+    let (reply, result) = mpsc::sync_channel(1);
+    sender.send(ApiRequest { command, reply }).map_err(|_| "main loop not available".to_string())?;
+    result.recv_timeout(REPLY_TIMEOUT).map_err(|_| "no reply from the main loop".to_string())?
+}
+
+/// Checks the token against the current `api_token` - a changed token applies to the next request
+fn authorized(request: &Request<&mut EspHttpConnection>, config: &Mutex<Config>) -> bool {
+    let api_token = &config.lock().unwrap().api_token;
+    !api_token.is_empty() && request.header("Authorization")
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .is_some_and(|token| token == api_token)
+}
+
+pub fn read_body(request: &mut Request<&mut EspHttpConnection>) -> Result<Vec<u8>> {
+    read_body_max(request, MAX_BODY_LEN)
+}
+
+fn read_body_max(request: &mut Request<&mut EspHttpConnection>, max_len: usize) -> Result<Vec<u8>> {
+    let len = request.content_len().unwrap_or(0) as usize;
+    if len > max_len {
+        anyhow::bail!("request body too large");
+    }
+    let mut body = vec![0_u8; len];
+    request.read_exact(&mut body).map_err(|e| anyhow!("could not read the request body: {:?}", e))?;
+    Ok(body)
+}
+
+pub fn respond(request: Request<&mut EspHttpConnection>, status: u16, body: &str) -> Result<()> {
+    let mut response = request.into_response(status, None, &[("Content-Type", "application/json")])?;
+    response.write_all(body.as_bytes())?;
+    Ok(())
+}
+
+fn respond_error(request: Request<&mut EspHttpConnection>, status: u16, error: &str) -> Result<()> {
+    respond(request, status, &json!({"ok": false, "error": error}).to_string())
+}
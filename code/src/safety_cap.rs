@@ -0,0 +1,51 @@
+//! Hardware safety cap of the LED duty
+//!
+//! An upper bound of the duty set once at provisioning (e.g. for undersized wiring or power supply).
+//! It bounds every duty written to the LED driver - regardless of configuration, mode or commands.
+//! It is not a configuration parameter: it lives in its own NVS namespace and can only be changed
+//! on the serial console within the provisioning window after a power-up (physical access) - a restart, e.g. by a
+//! `reboot` over the network, doesn't open the window.
+
+use anyhow::Result;
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use esp_idf_svc::sys::{esp_reset_reason, esp_reset_reason_t_ESP_RST_POWERON};
+
+use crate::latency::now_ms;
+
+/// Time after power-up in which the safety cap may be changed
+const PROVISIONING_WINDOW_MS: u32 = 5 * 60 * 1000;
+
+const NVS_NAMESPACE: &str = "provisioning";
+const KEY_DUTY_CAP_PCT: &str = "duty_cap_pct";
+
+pub const NO_CAP_PCT: u32 = 100;
+/// Conservative cap, when the provisioned value can't be read
+pub const FALLBACK_CAP_PCT: u32 = 25;
+
+pub fn in_provisioning_window() -> bool {
+    // Safety: plain read of the reset reason stored at startup
+    let power_on = unsafe { esp_reset_reason() } == esp_reset_reason_t_ESP_RST_POWERON;
+    power_on && now_ms() < PROVISIONING_WINDOW_MS
+}
+
+pub struct SafetyCapStore {
+    nvs: EspNvs<NvsDefault>,
+}
+
+impl SafetyCapStore {
+    pub fn new(partition: EspDefaultNvsPartition) -> Result<Self> {
+        Ok(Self {
+            nvs: EspNvs::new(partition, NVS_NAMESPACE, true)?
+        })
+    }
+
+    /// Duty cap [%] of the max. duty
+    pub fn load(&self) -> Result<u32> {
+        Ok(self.nvs.get_u32(KEY_DUTY_CAP_PCT)?.unwrap_or(NO_CAP_PCT))
+    }
+
+    pub fn save(&mut self, percent: u32) -> Result<()> {
+        self.nvs.set_u32(KEY_DUTY_CAP_PCT, percent)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+//! Time of day and daily time windows for scheduled features
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+/// Minutes since midnight (local time)
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub struct TimeOfDay(u16);
+
+impl TimeOfDay {
+    pub const MIDNIGHT: TimeOfDay = TimeOfDay(0);
+
+    pub fn from_minutes(minutes: u16) -> Option<Self> {
+        if minutes < MINUTES_PER_DAY {
+            Some(TimeOfDay(minutes))
+        } else {
+            None
+        }
+    }
+
+    /// Full hour - `hour` must be below 24
+    pub const fn from_hour(hour: u16) -> Self {
+        assert!(hour < 24);
+        TimeOfDay(hour * 60)
+    }
+
+    pub fn minutes(self) -> u16 {
+        self.0
+    }
+}
+
+impl Display for TimeOfDay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.0 / 60, self.0 % 60)
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = anyhow::Error;
+
+    /// Format: `HH:MM`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hours, minutes) = s.split_once(':')
+            .ok_or_else(|| anyhow!("invalid time of day '{}' (expected HH:MM)", s))?;
+        let hours: u16 = hours.parse()?;
+        let minutes: u16 = minutes.parse()?;
+        if hours >= 24 || minutes >= 60 {
+            bail!("invalid time of day '{}'", s);
+        }
+        Ok(TimeOfDay(hours * 60 + minutes))
+    }
+}
+
+/// Daily time window `[from, until)`, which may wrap around midnight.
+/// An empty window (`from == until`) means disabled.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct TimeWindow {
+    pub from: TimeOfDay,
+    pub until: TimeOfDay,
+}
+
+impl TimeWindow {
+    pub fn new(from: TimeOfDay, until: TimeOfDay) -> Self {
+        Self { from, until }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.from == self.until
+    }
+
+    pub fn contains(&self, t: TimeOfDay) -> bool {
+        if self.from <= self.until {
+            self.from <= t && t < self.until
+        } else {
+            t >= self.from || t < self.until
+        }
+    }
+}
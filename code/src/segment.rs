@@ -0,0 +1,50 @@
+//! Second LED segment (two-zone output)
+//!
+//! The second segment (e.g. the upper part of a staircase) follows the power target of the first one
+//! with a configurable delay and its own brightness, so the segments light up and go dark one after the other.
+//! With the single presence sensor zone both segments share it.
+
+use std::time::{Duration, Instant};
+
+use crate::LED_MAX_POWER_STAGE;
+use crate::ramp::Ramp;
+
+#[derive(Debug)]
+pub struct FollowerSegment {
+    pub ramp: Ramp,
+    /// target of the first segment, which is applied after the delay
+    pending_target: Option<(u32, Instant)>,
+    /// logical (non-inverted) duty
+    pub duty: u32,
+}
+
+impl FollowerSegment {
+    pub fn new() -> Self {
+        Self {
+            ramp: Ramp::new(),
+            pending_target: None,
+            duty: 0,
+        }
+    }
+
+    /// Follows the target stage of the first segment after `delay`, scaled to `brightness_pct`
+    pub fn update(&mut self, leader_target: u32, brightness_pct: u32, delay: Duration, now: Instant) {
+        let target = leader_target * brightness_pct / 100;
+        match self.pending_target {
+            Some((pending, _)) if pending == target => {}
+            _ if self.ramp.target_stage() == target => self.pending_target = None,
+            _ => self.pending_target = Some((target, now + delay)),
+        }
+        if let Some((target, due)) = self.pending_target {
+            if now >= due {
+                self.ramp.set_target_stage(target.min(LED_MAX_POWER_STAGE));
+                self.pending_target = None;
+            }
+        }
+    }
+
+    /// A target change is still waiting for its delay
+    pub fn is_pending(&self) -> bool {
+        self.pending_target.is_some()
+    }
+}
@@ -0,0 +1,81 @@
+//! Energy statistics of the LED output
+//!
+//! Estimates the LED power from the output level and `led_full_watts` and integrates it over time:
+//! the energy since startup, of the current day and the daily totals of the last week.
+//! (Without a wall clock the days are relative to the start.)
+
+use std::time::{Duration, Instant};
+
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+const HISTORY_DAYS: usize = 7;
+
+#[derive(Debug)]
+pub struct EnergyStats {
+    last_update: Option<Instant>,
+    day_start: Option<Instant>,
+    /// estimated power since the last update [W]
+    watts: f32,
+    today_wh: f64,
+    total_wh: f64,
+    /// oldest first
+    daily_wh: AllocRingBuffer<f32>,
+}
+
+impl EnergyStats {
+    pub fn new() -> Self {
+        Self {
+            last_update: None,
+            day_start: None,
+            watts: 0.0,
+            today_wh: 0.0,
+            total_wh: 0.0,
+            daily_wh: AllocRingBuffer::new(HISTORY_DAYS),
+        }
+    }
+
+    /// Adds the energy since the last update and continues with the output `level` [0..=1] of the LEDs.
+    /// Returns the total [Wh] of a day, when it was completed with this update.
+    pub fn update(&mut self, level: f32, full_watts: f32, now: Instant) -> Option<f32> {
+        if let Some(last_update) = self.last_update {
+            let wh = self.watts as f64 * now.duration_since(last_update).as_secs_f64() / 3600.0;
+            self.today_wh += wh;
+            self.total_wh += wh;
+        }
+        self.last_update = Some(now);
+        self.watts = level.clamp(0.0, 1.0) * full_watts;
+
+        let day_start = *self.day_start.get_or_insert(now);
+        if now.duration_since(day_start) < DAY {
+            return None;
+        }
+        let day_wh = self.today_wh as f32;
+        self.daily_wh.push(day_wh);
+        self.today_wh = 0.0;
+        self.day_start = Some(now);
+        Some(day_wh)
+    }
+
+    /// Estimated current power [W]
+    pub fn watts(&self) -> f32 {
+        self.watts
+    }
+
+    pub fn today_wh(&self) -> f64 {
+        self.today_wh
+    }
+
+    pub fn total_wh(&self) -> f64 {
+        self.total_wh
+    }
+
+    /// The current day, one line per completed day (newest first) and the total
+    pub fn report(&self) -> Vec<String> {
+        let mut lines = vec![format!("now: {:.1} W, today: {:.1} Wh", self.watts, self.today_wh)];
+        lines.extend(self.daily_wh.iter().rev().enumerate()
+            .map(|(i, wh)| format!("day -{}: {:.1} Wh", i + 1, wh)));
+        lines.push(format!("since startup: {:.1} Wh", self.total_wh));
+        lines
+    }
+}
@@ -0,0 +1,100 @@
+//! Consistent status snapshot for all reporting paths
+//!
+//! The main loop publishes one snapshot per iteration, after all updates of the iteration are done.
+//! Readers (status log, diagnostics and later reporting threads) only see complete snapshots,
+//! never torn combinations like phase=Off with duty>0.
+
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+
+use crate::light_sensor::{LightReading, SaturationPolicy};
+use crate::mode::Mode;
+use crate::ramp::Phase;
+use crate::schedule::TimeWindow;
+
+#[derive(Clone, Debug)]
+pub struct StatusSnapshot {
+    pub mode: Mode,
+    pub dark_enough: bool,
+    pub darkness_latched: bool,
+    /// filtered lux level
+    pub lux: Option<f32>,
+    pub phase: Phase,
+    pub led_power_stage: u32,
+    pub target_stage: u32,
+    /// logical (non-inverted) duty
+    pub duty: u32,
+    pub max_duty: u32,
+    /// last (unfiltered) ambient light measurement
+    pub light_reading: Option<LightReading>,
+    pub saturation_policy: SaturationPolicy,
+    pub light_sensor_failures: u32,
+    pub light_sensor_available: bool,
+    pub fallback_dark_window: TimeWindow,
+    pub presence_sensor_enabled: bool,
+    pub presence: bool,
+    /// last measured presence-to-light latency
+    pub reaction_latency_ms: Option<u32>,
+    /// estimated LED power [W] - with `led_full_watts` only
+    pub led_watts: Option<f32>,
+    pub energy_today_wh: f64,
+}
+
+impl Display for StatusSnapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if !self.light_sensor_available {
+            if self.fallback_dark_window.is_empty() {
+                f.write_str("DEGRADED - no ambient light sensor, darkness gating skipped | ")?;
+            } else {
+                write!(f, "DEGRADED - no ambient light sensor, dark window {}-{} | ",
+                       self.fallback_dark_window.from, self.fallback_dark_window.until)?;
+            }
+        }
+        write!(f, "logic state: mode: {}, dark_enough: {}{}, lux: {:?}, phase: {:?}, led_power_stage: {} (target: {}), last reading: ",
+               self.mode,
+               self.dark_enough,
+               if self.darkness_latched { " (latched)" } else { "" },
+               self.lux,
+               self.phase,
+               self.led_power_stage,
+               self.target_stage
+        )?;
+        match self.light_reading {
+            Some(r) if r.is_saturated() => write!(f, "{} saturated ({})", r, self.saturation_policy)?,
+            Some(r) => write!(f, "{}", r)?,
+            None => f.write_str("-")?
+        }
+        if self.light_sensor_failures > 0 {
+            write!(f, ", light sensor failures: {}", self.light_sensor_failures)?;
+        }
+        if let Some(latency_ms) = self.reaction_latency_ms {
+            write!(f, ", reaction latency: {} ms", latency_ms)?;
+        }
+        if let Some(watts) = self.led_watts {
+            write!(f, ", LED power: {:.1} W ({:.1} Wh today)", watts, self.energy_today_wh)?;
+        }
+        write!(f, " , duty: {}/{} | Hardware: Presence sensor: enabled: {}, Sensor-signal: {}",
+               self.duty,
+               self.max_duty,
+               self.presence_sensor_enabled,
+               self.presence
+        )
+    }
+}
+
+/// The latest published status snapshot - cheap to clone and share with other threads
+#[derive(Clone, Default)]
+pub struct SharedStatus {
+    snapshot: Arc<Mutex<Option<StatusSnapshot>>>,
+}
+
+impl SharedStatus {
+    pub fn publish(&self, snapshot: StatusSnapshot) {
+        *self.snapshot.lock().unwrap() = Some(snapshot);
+    }
+
+    /// Copy of the latest snapshot; `None` before the first publication
+    pub fn get(&self) -> Option<StatusSnapshot> {
+        self.snapshot.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,155 @@
+//! Back-to-back phase transition stress test
+//!
+//! Drives synthetic presence on/off transitions in quick succession through the logic and the LED path
+//! (on a scratch state with simulated time) and checks every tick for state machine violations,
+//! duty glitches and heap growth - a regression net for changes of the arbitration and the ramp.
+
+use std::time::Duration;
+
+use esp_idf_svc::sys::esp_get_free_heap_size;
+
+use crate::ramp::Phase;
+use crate::LED_MAX_POWER_STAGE;
+
+/// Simulated time per tick
+pub const STRESS_TICK: Duration = Duration::from_millis(10);
+/// Upper bound for the number of transitions of one run
+pub const MAX_STRESS_TRANSITIONS: u32 = 100_000;
+/// Ticks the light gets to reach off after the last transition (10 minutes simulated)
+pub const MAX_SETTLE_TICKS: u32 = 60_000;
+/// Longest hold time of a presence level in ticks
+const MAX_HOLD_TICKS: u32 = 50;
+/// Tolerated heap decrease over a run (lazily allocated driver and log structures)
+const HEAP_TOLERANCE_BYTES: u32 = 1024;
+const MAX_REPORTED_VIOLATIONS: usize = 10;
+
+/// Deterministic pseudo random hold times (xorshift), so that a failing run can be repeated
+pub struct HoldTicks(u32);
+
+impl HoldTicks {
+    pub fn new(seed: u32) -> Self {
+        Self(seed.max(1))
+    }
+
+    /// Ticks in the range 1..=MAX_HOLD_TICKS
+    pub fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 % MAX_HOLD_TICKS + 1
+    }
+}
+
+/// Observed values after one tick
+#[derive(Copy, Clone, Debug)]
+pub struct Sample {
+    pub stage: u32,
+    pub target_stage: u32,
+    pub duty: u32,
+}
+
+#[derive(Debug)]
+pub struct StressMonitor {
+    ticks: u32,
+    transitions: u32,
+    violations: u32,
+    /// the first violations
+    examples: Vec<String>,
+    previous: Option<Sample>,
+    heap_before: u32,
+    min_heap: u32,
+    heap_after: Option<u32>,
+}
+
+impl StressMonitor {
+    pub fn new() -> Self {
+        let heap = free_heap();
+        Self {
+            ticks: 0,
+            transitions: 0,
+            violations: 0,
+            examples: vec![],
+            previous: None,
+            heap_before: heap,
+            min_heap: heap,
+            heap_after: None,
+        }
+    }
+
+    pub fn check(&mut self, sample: Sample) {
+        self.ticks += 1;
+        if sample.stage > LED_MAX_POWER_STAGE || sample.target_stage > LED_MAX_POWER_STAGE {
+            self.violation(format!("stage out of range: {:?}", sample));
+        }
+        if sample.stage == 0 && sample.duty > 0 {
+            self.violation(format!("duty without light: {:?}", sample));
+        }
+        if let Some(previous) = self.previous {
+            // the ramp never passes the (possibly new) target - it may still move away from it after a direction
+            // change with the smooth takeover
+            let overshoot = match previous.stage.cmp(&sample.target_stage) {
+                std::cmp::Ordering::Less => sample.stage > sample.target_stage,
+                std::cmp::Ordering::Greater => sample.stage < sample.target_stage,
+                std::cmp::Ordering::Equal => sample.stage != sample.target_stage,
+            };
+            if overshoot {
+                self.violation(format!("stage overshoot: {:?} -> {:?}", previous, sample));
+            }
+            // the duty follows the direction of the stage
+            let duty_glitch = match sample.stage.cmp(&previous.stage) {
+                std::cmp::Ordering::Greater => sample.duty < previous.duty,
+                std::cmp::Ordering::Less => sample.duty > previous.duty,
+                std::cmp::Ordering::Equal => sample.duty != previous.duty,
+            };
+            if duty_glitch {
+                self.violation(format!("duty glitch: {:?} -> {:?}", previous, sample));
+            }
+        }
+        self.previous = Some(sample);
+    }
+
+    pub fn transition_done(&mut self) {
+        self.transitions += 1;
+        self.min_heap = self.min_heap.min(free_heap());
+    }
+
+    /// Final checks after the settling time
+    pub fn finish(&mut self, phase: Phase) {
+        if phase != Phase::Off {
+            self.violation(format!("light not off after the settling time (phase {:?})", phase));
+        }
+        let heap_after = free_heap();
+        self.heap_after = Some(heap_after);
+        if heap_after + HEAP_TOLERANCE_BYTES < self.heap_before {
+            self.violation(format!("heap shrunk by {} bytes", self.heap_before - heap_after));
+        }
+    }
+
+    pub fn violations(&self) -> u32 {
+        self.violations
+    }
+
+    pub fn report(&self) -> Vec<String> {
+        let mut lines = vec![format!("{} transitions, {} ticks ({} s simulated), {} violations",
+                                     self.transitions, self.ticks, self.ticks * STRESS_TICK.as_millis() as u32 / 1000,
+                                     self.violations)];
+        lines.push(format!("free heap: before {} bytes, min {} bytes, after {} bytes",
+                           self.heap_before, self.min_heap, self.heap_after.unwrap_or(0)));
+        lines.extend(self.examples.iter().cloned());
+        if self.violations as usize > self.examples.len() {
+            lines.push(format!("... {} more", self.violations as usize - self.examples.len()));
+        }
+        lines
+    }
+
+    fn violation(&mut self, description: String) {
+        self.violations += 1;
+        if self.examples.len() < MAX_REPORTED_VIOLATIONS {
+            self.examples.push(format!("tick {}: {}", self.ticks, description));
+        }
+    }
+}
+
+fn free_heap() -> u32 {
+    unsafe { esp_get_free_heap_size() }
+}
@@ -0,0 +1,157 @@
+//! Push button on GPIO22 (to GND)
+//!
+//! - a short press toggles the always-on mode (`mode on` / `mode auto`)
+//! - held for 10 s from the startup or for 30 s at runtime: factory reset (see [crate::factory_reset])
+
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use esp_idf_hal::gpio::{AnyInputPin, Input, InterruptType, PinDriver, Pull};
+use esp_idf_hal::task::notification::Notifier;
+
+/// Hold time of a switch pressed since the startup
+const BOOT_HOLD_TIME: Duration = Duration::from_secs(10);
+/// Hold time of a switch pressed at runtime - long enough not to be triggered by accident
+const RUNTIME_HOLD_TIME: Duration = Duration::from_secs(30);
+/// Duration of a press toggling the always-on mode - shorter ones are contact bounce
+const MIN_TOGGLE_PRESS: Duration = Duration::from_millis(50);
+const MAX_TOGGLE_PRESS: Duration = Duration::from_secs(2);
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SwitchEvent {
+    /// short press (on release)
+    Toggle,
+    /// held long enough for a factory reset
+    FactoryReset,
+}
+
+pub struct Switch {
+    /// active low (pull-up)
+    switch: PinDriver<'static, AnyInputPin, Input>,
+    press: Press,
+}
+
+impl Switch {
+    /// Presses and releases wake up the main task through `notifier`
+    pub fn new(pin: AnyInputPin, notifier: Arc<Notifier>) -> Result<Self> {
+        let mut switch = PinDriver::input(pin)?;
+        switch.set_pull(Pull::Up)?;
+        switch.set_interrupt_type(InterruptType::AnyEdge)?;
+        // Safety: the callback only touches the (ISR-safe) notifier
+        unsafe {
+            switch.subscribe(move || {
+                notifier.notify_and_yield(NonZeroU32::MIN);
+            })?;
+        }
+        switch.enable_interrupt()?;
+        let at_boot = switch.is_low();
+        if at_boot {
+            log::warn!("switch pressed - factory reset, if held for {:?}", BOOT_HOLD_TIME);
+        }
+        Ok(Self {
+            switch,
+            press: Press::new(at_boot, Instant::now()),
+        })
+    }
+
+    /// Polls the switch
+    pub fn update(&mut self, now: Instant) -> Result<Option<SwitchEvent>> {
+        // the interrupt is disabled after each trigger
+        self.switch.enable_interrupt()?;
+        Ok(self.press.update(self.switch.is_low(), now))
+    }
+}
+
+/// Press and hold times of the switch
+#[derive(Debug)]
+struct Press {
+    pressed_since: Option<Instant>,
+    /// the switch is pressed since the startup
+    at_boot: bool,
+}
+
+impl Press {
+    fn new(at_boot: bool, now: Instant) -> Self {
+        Self {
+            pressed_since: at_boot.then_some(now),
+            at_boot,
+        }
+    }
+
+    fn update(&mut self, pressed: bool, now: Instant) -> Option<SwitchEvent> {
+        if !pressed {
+            let released = self.pressed_since.take();
+            let at_boot = std::mem::replace(&mut self.at_boot, false);
+            let toggle = released.is_some_and(|t| !at_boot && (MIN_TOGGLE_PRESS..MAX_TOGGLE_PRESS).contains(&now.duration_since(t)));
+            return toggle.then_some(SwitchEvent::Toggle);
+        }
+        let pressed_since = *self.pressed_since.get_or_insert(now);
+        let hold_time = if self.at_boot { BOOT_HOLD_TIME } else { RUNTIME_HOLD_TIME };
+        if now.duration_since(pressed_since) < hold_time {
+            return None;
+        }
+        // once per hold time - and no toggle on the release
+        self.pressed_since = Some(now - MAX_TOGGLE_PRESS);
+        self.at_boot = false;
+        Some(SwitchEvent::FactoryReset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn short_press_toggles_on_release() {
+        let start = Instant::now();
+        let mut press = Press::new(false, start);
+        assert_eq!(press.update(false, start), None);
+        assert_eq!(press.update(true, start + ms(10)), None);
+        assert_eq!(press.update(true, start + ms(200)), None);
+        assert_eq!(press.update(false, start + ms(300)), Some(SwitchEvent::Toggle));
+        assert_eq!(press.update(false, start + ms(400)), None);
+    }
+
+    #[test]
+    fn contact_bounce_and_long_presses_do_not_toggle() {
+        let start = Instant::now();
+        let mut press = Press::new(false, start);
+        press.update(true, start);
+        assert_eq!(press.update(false, start + ms(20)), None);
+        press.update(true, start + ms(100));
+        assert_eq!(press.update(false, start + ms(100) + MAX_TOGGLE_PRESS), None);
+    }
+
+    #[test]
+    fn held_at_runtime_resets_after_the_runtime_hold_time() {
+        let start = Instant::now();
+        let mut press = Press::new(false, start);
+        press.update(true, start);
+        assert_eq!(press.update(true, start + BOOT_HOLD_TIME), None);
+        assert_eq!(press.update(true, start + RUNTIME_HOLD_TIME), Some(SwitchEvent::FactoryReset));
+        assert_eq!(press.update(true, start + RUNTIME_HOLD_TIME + ms(100)), None);
+        // no toggle on the release after a reset
+        assert_eq!(press.update(false, start + RUNTIME_HOLD_TIME + ms(200)), None);
+    }
+
+    #[test]
+    fn held_since_the_startup_resets_after_the_boot_hold_time() {
+        let start = Instant::now();
+        let mut press = Press::new(true, start);
+        assert_eq!(press.update(true, start + BOOT_HOLD_TIME - ms(1)), None);
+        assert_eq!(press.update(true, start + BOOT_HOLD_TIME), Some(SwitchEvent::FactoryReset));
+    }
+
+    #[test]
+    fn release_of_a_press_from_the_startup_does_not_toggle() {
+        let start = Instant::now();
+        let mut press = Press::new(true, start);
+        assert_eq!(press.update(false, start + ms(500)), None);
+    }
+}
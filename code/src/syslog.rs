@@ -0,0 +1,126 @@
+//! Remote log streaming over UDP syslog (opt-in)
+//!
+//! Mirrors the log output to `syslog_target` (`<host>[:<port>]`, port 514 by default) as RFC 5424 messages of the
+//! facility `local0` - e.g. for debugging an installed unit without a serial connection.
+//! The lines logged while the target is not (yet) reachable - e.g. during the bring-up - are kept in a ring buffer
+//! and sent first. A lost line is not repeated (UDP).
+
+use std::collections::VecDeque;
+use std::net::{IpAddr, UdpSocket};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use log::{Level, Record};
+
+use crate::config::Config;
+
+const DEFAULT_PORT: u16 = 514;
+/// Lines kept while not connected - the oldest ones are dropped
+const MAX_BUFFERED_LINES: usize = 64;
+/// Interval of the connection attempts
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+/// facility local0
+const FACILITY: u8 = 16;
+
+static SYSLOG: Mutex<Syslog> = Mutex::new(Syslog {
+    buffer: VecDeque::new(),
+    connection: None,
+});
+
+struct Connection {
+    socket: UdpSocket,
+    target: String,
+    hostname: String,
+}
+
+struct Syslog {
+    /// severity and text of the lines logged while not connected
+    buffer: VecDeque<(u8, String)>,
+    connection: Option<Connection>,
+}
+
+impl Connection {
+    fn send(&self, severity: u8, text: &str) {
+        let message = format!("<{}>1 - {} sensor-light - - - {}", FACILITY * 8 + severity, self.hostname, text);
+        // never logs - this is called by the logger
+        let _ = self.socket.send(message.as_bytes());
+    }
+}
+
+/// Hands a log line over - called by the logger (see [crate::log_mirror])
+pub fn mirror(record: &Record) {
+    let severity = match record.level() {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    };
+    let text = format!("{}: {}", record.target(), record.args());
+    let mut syslog = SYSLOG.lock().unwrap();
+    match &syslog.connection {
+        Some(connection) => connection.send(severity, &text),
+        None => {
+            if syslog.buffer.len() == MAX_BUFFERED_LINES {
+                syslog.buffer.pop_front();
+            }
+            syslog.buffer.push_back((severity, text));
+        }
+    }
+}
+
+pub struct SyslogLink {
+    last_attempt: Option<Instant>,
+}
+
+impl SyslogLink {
+    pub fn new() -> Self {
+        Self { last_attempt: None }
+    }
+
+    /// Connects to the configured target (again, after a change) - a failed attempt is repeated later
+    pub fn update(&mut self, config: &Config, now: Instant) {
+        let connected = SYSLOG.lock().unwrap().connection.as_ref()
+            .is_some_and(|c| c.target == config.syslog_target && c.hostname == config.hostname);
+        if config.syslog_target.is_empty() {
+            SYSLOG.lock().unwrap().connection = None;
+            return;
+        }
+        if connected {
+            return;
+        }
+        if self.last_attempt.is_some_and(|t| now.duration_since(t) < RETRY_INTERVAL) {
+            return;
+        }
+        self.last_attempt = Some(now);
+        match connect(&config.syslog_target) {
+            Ok(socket) => {
+                let mut syslog = SYSLOG.lock().unwrap();
+                let connection = Connection {
+                    socket,
+                    target: config.syslog_target.clone(),
+                    hostname: config.hostname.clone(),
+                };
+                for (severity, text) in syslog.buffer.drain(..) {
+                    connection.send(severity, &text);
+                }
+                syslog.connection = Some(connection);
+                drop(syslog);
+                log::info!("log mirrored to syslog target {}", config.syslog_target);
+            }
+            Err(e) => log::warn!("syslog target {} not reachable: {}", config.syslog_target, e)
+        }
+    }
+}
+
+fn connect(target: &str) -> Result<UdpSocket> {
+    let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+    // `<host>:<port>` or `[<IPv6 address>]:<port>`
+    if target.contains(':') && target.parse::<IpAddr>().is_err() {
+        socket.connect(target)?;
+    } else {
+        socket.connect((target, DEFAULT_PORT))?;
+    }
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
@@ -0,0 +1,122 @@
+//! Remote console over TCP (opt-in)
+//!
+//! Accepts the commands of the serial console (see [crate::console]) on TCP port 23 - e.g. with
+//! `telnet <hostname>.local` or `nc <hostname>.local 23`. One client at a time; the first line has to be the
+//! `api_token` - without a token the console is not started.
+//! `get` shows no credentials and `config export` leaves them out, as the log goes to the network.
+//! The command output is logged, so the log is mirrored to the connected client.
+
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use log::Record;
+
+use crate::console::Command;
+
+const PORT: u16 = 23;
+/// Interval the client thread checks for log lines, while waiting for input
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+/// Log lines waiting for the client thread - more are dropped
+const MAX_PENDING_LINES: usize = 32;
+
+/// Log lines for the connected client (see [mirror])
+static MIRROR: Mutex<Option<SyncSender<String>>> = Mutex::new(None);
+
+/// Hands a log line over to the connected client - called by [crate::log_mirror]
+pub fn mirror(record: &Record) {
+    if let Some(mirror) = MIRROR.lock().unwrap().as_ref() {
+        // never blocks the logging task - a slow client misses lines
+        let _ = mirror.try_send(format!("{} {}: {}\r\n", record.level(), record.target(), record.args()));
+    }
+}
+
+pub struct TcpConsole {
+    receiver: Receiver<Command>,
+}
+
+impl TcpConsole {
+    pub fn start(api_token: &str) -> Result<Self> {
+        if api_token.is_empty() {
+            bail!("no api_token");
+        }
+        let (sender, receiver) = mpsc::channel();
+        let listener = TcpListener::bind(("0.0.0.0", PORT))?;
+        let api_token = api_token.to_string();
+        thread::Builder::new()
+            .name("tcp-console".to_string())
+            .stack_size(6144)
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            if let Err(e) = serve(stream, &sender, &api_token) {
+                                log::warn!("TCP console: {}", e);
+                            }
+                            *MIRROR.lock().unwrap() = None;
+                        }
+                        Err(e) => log::warn!("TCP console: connection not accepted: {}", e)
+                    }
+                }
+            })?;
+        log::info!("TCP console listening on port {}", PORT);
+        Ok(Self { receiver })
+    }
+
+    /// Returns the next pending command (non-blocking)
+    pub fn poll(&self) -> Option<Command> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Serves a client until it disconnects
+fn serve(stream: TcpStream, sender: &Sender<Command>, api_token: &str) -> Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let (mirror, log_lines) = mpsc::sync_channel(MAX_PENDING_LINES);
+    let mut authenticated = false;
+    writer.write_all(b"token: ")?;
+
+    let mut line = String::new();
+    loop {
+        match reader.read_line(&mut line) {
+            Ok(0) => return Ok(()),
+            Ok(_) => {
+                let text = line.trim().to_string();
+                line.clear();
+                if !authenticated {
+                    if text != api_token {
+                        writer.write_all(b"invalid token\r\n")?;
+                        return Ok(());
+                    }
+                    authenticate(&mut writer, mirror.clone())?;
+                    authenticated = true;
+                } else if !text.is_empty() {
+                    match Command::parse(&text) {
+                        Ok(command) => sender.send(command).map_err(|_| anyhow!("main loop not available"))?,
+                        Err(e) => writer.write_all(format!("{}\r\n", e).as_bytes())?
+                    }
+                }
+            }
+            // the partial line stays in the buffer
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => (),
+            Err(e) => return Err(e.into())
+        }
+        while let Ok(log_line) = log_lines.try_recv() {
+            writer.write_all(log_line.as_bytes())?;
+        }
+    }
+}
+
+/// Starts mirroring the log to the client
+fn authenticate(writer: &mut TcpStream, mirror: SyncSender<String>) -> Result<()> {
+    writer.write_all(b"sensor-light console\r\n")?;
+    *MIRROR.lock().unwrap() = Some(mirror);
+    Ok(())
+}
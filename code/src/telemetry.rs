@@ -0,0 +1,63 @@
+//! WebSocket live telemetry (opt-in)
+//!
+//! `/ws` streams the status snapshot (JSON like `GET /status`) at 2 Hz to every connected client - e.g. for watching
+//! lux, duty and presence live in a browser while adjusting the sensor placement.
+//! With `local_only_analytics` the stream has no occupancy related fields (like `GET /status`).
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use esp_idf_svc::http::server::ws::EspHttpWsDetachedSender;
+use esp_idf_svc::http::server::EspHttpServer;
+use esp_idf_svc::ws::FrameType;
+
+use crate::config::Config;
+use crate::event::ExportedStatus;
+use crate::rest_api::status_json;
+use crate::status::SharedStatus;
+
+const TELEMETRY_PERIOD: Duration = Duration::from_millis(500);
+const MAX_CLIENTS: usize = 4;
+
+/// Senders of the connected clients by their session id
+type Clients = Arc<Mutex<Vec<(i32, EspHttpWsDetachedSender)>>>;
+
+pub fn register(server: &mut EspHttpServer<'static>, status: SharedStatus, config: Arc<Mutex<Config>>) -> Result<()> {
+    let clients: Clients = Arc::default();
+
+    let handler_clients = clients.clone();
+    server.ws_handler("/ws", move |ws| {
+        let mut clients = handler_clients.lock().unwrap();
+        if ws.is_new() {
+            if clients.len() >= MAX_CLIENTS {
+                log::warn!("telemetry client rejected - {} clients connected", clients.len());
+                return Ok(());
+            }
+            clients.push((ws.session(), ws.create_detached_sender()?));
+        } else if ws.is_closed() {
+            clients.retain(|(session, _)| *session != ws.session());
+        }
+        // incoming frames are ignored
+        Ok::<(), esp_idf_svc::sys::EspError>(())
+    })?;
+
+    thread::Builder::new()
+        .name("telemetry".to_string())
+        .stack_size(6144)
+        .spawn(move || loop {
+            thread::sleep(TELEMETRY_PERIOD);
+            let mut clients = clients.lock().unwrap();
+            if clients.is_empty() {
+                continue;
+            }
+            let Some(snapshot) = status.get() else {
+                continue;
+            };
+            let frame = status_json(&ExportedStatus::new(&config.lock().unwrap(), &snapshot)).to_string();
+            // a client, which can't be reached anymore, is dropped
+            clients.retain_mut(|(_, sender)| sender.send(FrameType::Text(false), frame.as_bytes()).is_ok());
+        })?;
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! Presence test button on GPIO0 for installers (opt-in)
+//!
+//! A press simulates a presence signal for a while, which runs through the full pipeline
+//! (darkness gating, ramp, camera trigger). The reaction latency from the press to the first visible duty
+//! is recorded as an event, so each unit can be verified after mounting.
+
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use esp_idf_hal::gpio::{AnyInputPin, Input, InterruptType, PinDriver, Pull};
+use esp_idf_hal::task::notification::Notifier;
+
+use crate::event::{Event, EventQueue};
+
+/// Duration of the simulated presence signal
+const TEST_PRESENCE_DURATION: Duration = Duration::from_secs(10);
+
+pub struct TestTrigger {
+    /// active low (pull-up)
+    button: PinDriver<'static, AnyInputPin, Input>,
+    was_pressed: bool,
+    started: Option<Instant>,
+    reaction_pending: bool,
+}
+
+impl TestTrigger {
+    /// A button press wakes up the main task through `notifier`
+    pub fn new(pin: AnyInputPin, notifier: Arc<Notifier>) -> Result<Self> {
+        let mut button = PinDriver::input(pin)?;
+        button.set_pull(Pull::Up)?;
+        button.set_interrupt_type(InterruptType::NegEdge)?;
+        // Safety: the callback only touches the (ISR-safe) notifier
+        unsafe {
+            button.subscribe(move || {
+                notifier.notify_and_yield(NonZeroU32::MIN);
+            })?;
+        }
+        button.enable_interrupt()?;
+        Ok(Self {
+            button,
+            was_pressed: false,
+            started: None,
+            reaction_pending: false,
+        })
+    }
+
+    /// Polls the button; returns whether the simulated presence is active
+    pub fn update(&mut self, now: Instant, events: &mut EventQueue) -> Result<bool> {
+        // the interrupt is disabled after each trigger
+        self.button.enable_interrupt()?;
+        let pressed = self.button.is_low();
+        if pressed && !self.was_pressed && self.started.is_none() {
+            log::info!("test trigger: simulating presence for {:?}", TEST_PRESENCE_DURATION);
+            self.started = Some(now);
+            self.reaction_pending = true;
+            events.push(Event::TestTriggered);
+        }
+        self.was_pressed = pressed;
+
+        match self.started {
+            Some(t) if now.duration_since(t) < TEST_PRESENCE_DURATION => Ok(true),
+            Some(_) => {
+                if self.reaction_pending {
+                    // no light - e.g. not dark enough
+                    events.push(Event::TestTriggerReaction(None));
+                    self.reaction_pending = false;
+                }
+                self.started = None;
+                Ok(false)
+            }
+            None => Ok(false)
+        }
+    }
+
+    /// To be called after the duty was applied - records the reaction latency of a running test
+    pub fn check_reaction(&mut self, duty: u32, now: Instant, events: &mut EventQueue) {
+        if let Some(started) = self.started.filter(|_| self.reaction_pending && duty > 0) {
+            let latency_ms = now.duration_since(started).as_millis() as u32;
+            events.push(Event::TestTriggerReaction(Some(latency_ms)));
+            self.reaction_pending = false;
+        }
+    }
+}
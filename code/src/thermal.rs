@@ -0,0 +1,58 @@
+//! Thermal derating of the LED output
+//!
+//! An optional LM75 / TMP102 compatible temperature sensor on the LED profile (second I2C bus, instead of a
+//! second ambient light sensor) limits the maximum duty progressively above `derate_from_c`.
+//! There is no free ADC pin for an NTC on the ESP32-H2 board (GPIO1..GPIO5 are taken).
+
+use std::time::Duration;
+
+use anyhow::Result;
+use esp_idf_hal::delay::TickType;
+use esp_idf_hal::i2c::I2cDriver;
+
+use crate::config::Config;
+use crate::peripheral::i2c_probe;
+
+/// Poll period of the temperature sensor - the LED profile warms up slowly
+pub const TEMPERATURE_POLL_PERIOD: Duration = Duration::from_secs(5);
+/// Time budget of a temperature poll
+pub const TEMPERATURE_POLL_BUDGET: Duration = Duration::from_millis(20);
+
+/// LM75 / TMP102 with A0..A2 (ADD0) to GND
+const TEMPERATURE_SENSOR_ADDRESS: u8 = 0x48;
+const TEMPERATURE_REGISTER: u8 = 0x00;
+/// the left aligned 12 bit value of the TMP102 - the LM75 has fewer valid bits with zeros below
+const CELSIUS_PER_LSB: f32 = 0.0625;
+const I2C_TIMEOUT_MS: u64 = 50;
+
+pub struct TemperatureSensor {
+    i2c: I2cDriver<'static>,
+}
+
+impl TemperatureSensor {
+    pub fn new(mut i2c: I2cDriver<'static>) -> Result<Self> {
+        if !i2c_probe(&mut i2c, TEMPERATURE_SENSOR_ADDRESS) {
+            anyhow::bail!("no temperature sensor at {:#04x}", TEMPERATURE_SENSOR_ADDRESS);
+        }
+        let mut sensor = Self { i2c };
+        log::info!("temperature sensor: {:.1} °C", sensor.read_celsius()?);
+        Ok(sensor)
+    }
+
+    pub fn read_celsius(&mut self) -> Result<f32> {
+        let mut buf = [0_u8; 2];
+        self.i2c.write_read(TEMPERATURE_SENSOR_ADDRESS, &[TEMPERATURE_REGISTER], &mut buf,
+                            TickType::new_millis(I2C_TIMEOUT_MS).ticks())?;
+        Ok((i16::from_be_bytes(buf) >> 4) as f32 * CELSIUS_PER_LSB)
+    }
+}
+
+/// Max. duty [%] at the temperature: 100 up to `derate_from_c`, linearly down to `derate_min_pct` at `derate_to_c`
+pub fn derating_pct(celsius: f32, config: &Config) -> u32 {
+    if celsius <= config.derate_from_c {
+        return 100;
+    }
+    let span = (config.derate_to_c - config.derate_from_c).max(f32::EPSILON);
+    let x = ((celsius - config.derate_from_c) / span).min(1.0);
+    (100.0 - x * (100 - config.derate_min_pct) as f32).round() as u32
+}
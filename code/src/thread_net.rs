@@ -0,0 +1,43 @@
+//! Thread network interface (opt-in)
+//!
+//! The ESP32-H2 has no Wi-Fi - the network features (MQTT, REST API, syslog, ...) use the IPv6 interface of a Thread
+//! network instead, routed to the LAN by a Thread border router (e.g. an OpenThread border router or a smart home hub).
+//! The unit joins the network described by the active operational dataset in `thread_dataset` (as printed by
+//! `ot-ctl dataset active -x` on the border router) - there is no commissioning.
+//! The OpenThread stack runs in its own thread; the network features use the interface as soon as it is attached.
+
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::Result;
+use esp_idf_svc::eventloop::EspSystemEventLoop;
+use esp_idf_svc::hal::modem::Modem;
+use esp_idf_svc::io::vfs::MountedEventfs;
+use esp_idf_svc::nvs::EspDefaultNvsPartition;
+use esp_idf_svc::thread::EspThread;
+
+/// NVS namespace of the settings, which the OpenThread stack stores - the network keys and the datasets
+pub const SETTINGS_NVS_NAMESPACE: &str = "openthread";
+/// File descriptors of the eventfd VFS - the OpenThread stack needs one for its task queue
+const MAX_EVENT_FDS: usize = 4;
+const STACK_SIZE: usize = 8 * 1024;
+
+/// Brings up the Thread network interface and starts the OpenThread stack
+pub fn start(modem: Modem, nvs: EspDefaultNvsPartition, dataset: &str) -> Result<()> {
+    let sysloop = EspSystemEventLoop::take()?;
+    let eventfs = Arc::new(MountedEventfs::mount(MAX_EVENT_FDS)?);
+    let thread_net = EspThread::new(modem, sysloop, nvs, eventfs)?;
+    thread_net.set_tod_hexstr(dataset)?;
+    thread_net.enable_ipv6(true)?;
+    thread_net.enable_thread(true)?;
+    thread::Builder::new()
+        .name("thread-net".to_string())
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            if let Err(e) = thread_net.run() {
+                log::warn!("Thread network stopped: {}", e);
+            }
+        })?;
+    log::info!("Thread network interface up - attaching to the network");
+    Ok(())
+}
@@ -0,0 +1,21 @@
+//! On-device web configuration UI (opt-in)
+//!
+//! A single page served at `/` - shows the status and edits the most common parameters via the REST API
+//! (see [crate::rest_api]); changes are stored and applied right away like `set` on the console.
+//! There are no Wi-Fi settings - the ESP32-H2 has no Wi-Fi.
+
+use anyhow::Result;
+use embedded_svc::io::Write;
+use esp_idf_svc::http::server::EspHttpServer;
+use esp_idf_svc::http::Method;
+
+const INDEX_HTML: &str = include_str!("web_ui/index.html");
+
+pub fn register(server: &mut EspHttpServer<'static>) -> Result<()> {
+    server.fn_handler("/", Method::Get, |request| {
+        let mut response = request.into_response(200, None, &[("Content-Type", "text/html; charset=utf-8")])?;
+        response.write_all(INDEX_HTML.as_bytes())?;
+        Ok::<(), anyhow::Error>(())
+    })?;
+    Ok(())
+}
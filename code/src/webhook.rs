@@ -0,0 +1,79 @@
+//! Webhook notifications (opt-in)
+//!
+//! POSTs selected events as JSON to the endpoints in `webhook_urls` - e.g. to Node-RED or ntfy, without a broker:
+//! `{"event": "presence_start", "device": "<hostname>"}`.
+//! The requests are sent one after another by a background thread, so that the light control is not blocked.
+//! A failed request is logged and not repeated.
+
+use std::sync::mpsc;
+use std::sync::mpsc::SyncSender;
+use std::thread;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::config::Config;
+use crate::event::Event;
+use crate::http_client::post_json;
+
+/// Max. number of notifications waiting for the sender thread - more are dropped
+const MAX_PENDING: usize = 16;
+
+struct Notification {
+    urls: Vec<String>,
+    body: String,
+}
+
+pub struct Webhooks {
+    sender: SyncSender<Notification>,
+}
+
+impl Webhooks {
+    pub fn start() -> Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel::<Notification>(MAX_PENDING);
+        thread::Builder::new()
+            .name("webhooks".to_string())
+            .stack_size(8192)
+            .spawn(move || {
+                for notification in receiver {
+                    for url in &notification.urls {
+                        if let Err(e) = post_json(url, &notification.body) {
+                            log::warn!("webhook {} failed: {}", url, e);
+                        }
+                    }
+                }
+            })?;
+        Ok(Self { sender })
+    }
+
+    /// Sends the event to the configured endpoints, if it is one of the notified events.
+    /// The event has to pass the export filter before.
+    pub fn notify(&self, config: &Config, event: Event) {
+        if config.webhook_urls.is_empty() {
+            return;
+        }
+        let Some(payload) = payload(event, &config.hostname) else {
+            return;
+        };
+        let notification = Notification {
+            urls: config.webhook_urls.split(',').map(str::to_string).collect(),
+            body: payload.to_string(),
+        };
+        if self.sender.try_send(notification).is_err() {
+            log::warn!("webhook notification for {} dropped", event);
+        }
+    }
+}
+
+fn payload(event: Event, hostname: &str) -> Option<Value> {
+    let mut payload = match event {
+        Event::PresenceStart => json!({"event": "presence_start"}),
+        Event::PresenceEnd => json!({"event": "presence_end"}),
+        Event::MaxOnTimeReached => json!({"event": "max_on_time_reached"}),
+        Event::LightSensorLost => json!({"event": "light_sensor_lost"}),
+        Event::BringupFailed(component) => json!({"event": "bringup_failed", "component": component}),
+        _ => return None
+    };
+    payload["device"] = Value::from(hostname);
+    Some(payload)
+}
@@ -0,0 +1,49 @@
+//! Presence zones with occupancy age based brightness scaling
+//!
+//! Each zone remembers when a presence was seen there the last time.
+//! The brightness of a zone fades linearly with the age of its last presence:
+//! fresh = 100%, half the trail time = 50%, trail time = off.
+//! This gives a trailing-light effect with multiple zones.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct Zones {
+    last_presence: Vec<Option<Instant>>,
+}
+
+impl Zones {
+    pub fn new(count: usize) -> Self {
+        Self {
+            last_presence: vec![None; count],
+        }
+    }
+
+    pub fn update(&mut self, zone: usize, presence: bool, now: Instant) {
+        if presence {
+            self.last_presence[zone] = Some(now);
+        }
+    }
+
+    /// Brightness factor [0..1] of a single zone
+    pub fn zone_factor(&self, zone: usize, trail: Duration, now: Instant) -> f32 {
+        match self.last_presence[zone] {
+            Some(t) => occupancy_factor(now.duration_since(t), trail),
+            None => 0.0
+        }
+    }
+
+    /// Brightness factor [0..1] over all zones (the freshest zone wins)
+    pub fn factor(&self, trail: Duration, now: Instant) -> f32 {
+        (0..self.last_presence.len())
+            .map(|zone| self.zone_factor(zone, trail, now))
+            .fold(0.0, f32::max)
+    }
+}
+
+fn occupancy_factor(age: Duration, trail: Duration) -> f32 {
+    if trail.is_zero() {
+        return if age.is_zero() { 1.0 } else { 0.0 };
+    }
+    (1.0 - age.as_secs_f32() / trail.as_secs_f32()).clamp(0.0, 1.0)
+}
@@ -0,0 +1,145 @@
+//! Provisioning profile generator
+//!
+//! Generates ready-to-flash NVS partition images from site descriptions (see [site]), so that new units can be
+//! provisioned on the bench: `profiles [--out <dir>] [--size <bytes>] <site.toml>...`
+//!
+//! Per site it writes `<name>.csv` in the format of ESP-IDF's NVS partition generator and runs the generator
+//! (`pip install esp-idf-nvs-partition-gen`) to build `<name>.bin`.
+//! Flash the image to the NVS partition, e.g. `espflash write-bin 0x9000 <name>.bin`.
+//!
+//! The values are checked for their type and format only - range limits are checked by the firmware console.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+use std::{env, fs};
+
+use crate::params::{Encoding, ParamSpec, CONFIG_VERSION, CONFIG_VERSION_KEY, NVS_NAMESPACE};
+use crate::site::{Site, Value};
+
+mod params;
+mod site;
+
+/// Size of the NVS partition in the default partition table
+const DEFAULT_PARTITION_SIZE: &str = "0x6000";
+
+fn main() -> ExitCode {
+    match run(env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut out_dir = PathBuf::from(".");
+    let mut size = DEFAULT_PARTITION_SIZE.to_string();
+    let mut sites = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => out_dir = args.next().ok_or("--out needs a directory")?.into(),
+            "--size" => size = args.next().ok_or("--size needs a partition size")?,
+            "-h" | "--help" => {
+                println!("usage: profiles [--out <dir>] [--size <bytes>] <site.toml>...");
+                return Ok(());
+            }
+            _ => sites.push(PathBuf::from(arg))
+        }
+    }
+    if sites.is_empty() {
+        return Err("no site description given (usage: profiles [--out <dir>] [--size <bytes>] <site.toml>...)".to_string());
+    }
+    for path in sites {
+        generate(&path, &out_dir, &size).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+fn generate(path: &Path, out_dir: &Path, size: &str) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let site = site::parse(&text)?;
+    let csv_path = out_dir.join(format!("{}.csv", site.name));
+    let bin_path = out_dir.join(format!("{}.bin", site.name));
+    fs::write(&csv_path, to_csv(&site)?).map_err(|e| format!("{}: {}", csv_path.display(), e))?;
+    println!("{}: {} parameters -> {}", site.name, site.config.len(), csv_path.display());
+
+    let status = Command::new("python3")
+        .args(["-m", "esp_idf_nvs_partition_gen", "generate"])
+        .arg(&csv_path)
+        .arg(&bin_path)
+        .arg(size)
+        .status();
+    match status {
+        Ok(status) if status.success() => {
+            println!("{}: image -> {}", site.name, bin_path.display());
+            Ok(())
+        }
+        Ok(status) => Err(format!("NVS partition generator failed ({}) - is it installed (`pip install esp-idf-nvs-partition-gen`)?", status)),
+        Err(e) => Err(format!("NVS partition generator not available ({}) - install it with `pip install esp-idf-nvs-partition-gen`", e))
+    }
+}
+
+/// CSV input of the NVS partition generator
+fn to_csv(site: &Site) -> Result<String, String> {
+    let mut csv = format!("key,type,encoding,value\n{},namespace,,\n", NVS_NAMESPACE);
+    csv.push_str(&format!("{},data,u32,{}\n", CONFIG_VERSION_KEY, CONFIG_VERSION));
+    for entry in &site.config {
+        let spec = params::find(&entry.key)
+            .ok_or_else(|| format!("line {}: unknown parameter '{}'", entry.line, entry.key))?;
+        let (encoding, value) = encode(spec, &entry.value)
+            .map_err(|e| format!("line {}: {}: {}", entry.line, entry.key, e))?;
+        csv.push_str(&format!("{},data,{},{}\n", spec.key, encoding, value));
+    }
+    Ok(csv)
+}
+
+/// NVS encoding and value as expected by the firmware's `ConfigStore::load()`
+fn encode(spec: &ParamSpec, value: &Value) -> Result<(&'static str, String), String> {
+    match (&spec.encoding, value) {
+        (Encoding::U32, Value::Int(i)) => u32::try_from(*i)
+            .map(|v| ("u32", v.to_string()))
+            .map_err(|_| format!("{} is out of range", i)),
+        (Encoding::F32, Value::Int(i)) => Ok(("u32", (*i as f32).to_bits().to_string())),
+        (Encoding::F32, Value::Float(x)) => Ok(("u32", (*x as f32).to_bits().to_string())),
+        (Encoding::Bool, Value::Bool(b)) => Ok(("u8", (*b as u8).to_string())),
+        (Encoding::TimeOfDay, Value::Str(s)) => parse_time_of_day(s).map(|minutes| ("u16", minutes.to_string())),
+        (Encoding::Enum(names), Value::Str(s)) => names.iter().position(|name| name == s)
+            .map(|index| ("u8", index.to_string()))
+            .ok_or_else(|| format!("unknown value \"{}\" (expected one of {})", s, names.join(", "))),
+        (Encoding::String, Value::Str(s)) => Ok(("string", csv_quoted(s))),
+        (_, value) => Err(format!("unexpected value {} (expected {})", value, expected(&spec.encoding)))
+    }
+}
+
+fn expected(encoding: &Encoding) -> &'static str {
+    match encoding {
+        Encoding::U32 => "an unsigned integer",
+        Encoding::F32 => "a number",
+        Encoding::Bool => "true or false",
+        Encoding::TimeOfDay => "\"HH:MM\"",
+        Encoding::Enum(_) => "a name in quotes",
+        Encoding::String => "a string",
+    }
+}
+
+/// `HH:MM` as minutes since midnight
+fn parse_time_of_day(s: &str) -> Result<u16, String> {
+    let invalid = || format!("invalid time of day \"{}\" (expected HH:MM)", s);
+    let (hours, minutes) = s.split_once(':').ok_or_else(invalid)?;
+    let hours: u16 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u16 = minutes.parse().map_err(|_| invalid())?;
+    if hours >= 24 || minutes >= 60 {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+fn csv_quoted(s: &str) -> String {
+    if s.contains([',', '"']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
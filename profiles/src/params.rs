@@ -0,0 +1,149 @@
+//! Configuration parameters of the firmware and their NVS representation
+//!
+//! Mirrors `ConfigStore::save()` in `code/src/config.rs` - keep both in sync.
+
+/// NVS namespace of the firmware configuration
+pub const NVS_NAMESPACE: &str = "sensor-light";
+/// Layout version of the stored values (`CONFIG_VERSION`) and its key
+pub const CONFIG_VERSION: u32 = 1;
+pub const CONFIG_VERSION_KEY: &str = "cfg_version";
+
+/// How a parameter value is stored in NVS
+pub enum Encoding {
+    U32,
+    /// bit representation as u32
+    F32,
+    /// u8 0 / 1
+    Bool,
+    /// `HH:MM` as minutes since midnight (u16)
+    TimeOfDay,
+    /// index of the name (u8)
+    Enum(&'static [&'static str]),
+    String,
+}
+
+pub struct ParamSpec {
+    /// name as used on the console
+    pub name: &'static str,
+    pub key: &'static str,
+    pub encoding: Encoding,
+}
+
+const fn param(name: &'static str, key: &'static str, encoding: Encoding) -> ParamSpec {
+    ParamSpec { name, key, encoding }
+}
+
+const LUX_FILTERS: &[&str] = &["median", "ema", "trimmed_mean", "weighted_median"];
+const VEML_GAINS: &[&str] = &["1/8", "1/4", "1", "2"];
+const SATURATION_POLICIES: &[&str] = &["clamp", "invalid", "auto_gain"];
+const LUX_COMBINATIONS: &[&str] = &["min", "average"];
+const DIMMING_CURVES: &[&str] = &["log", "cie1931", "gamma2.2"];
+const STRIP_EFFECTS: &[&str] = &["uniform", "chase"];
+const MIN_DUTY_POLICIES: &[&str] = &["clamp", "snap"];
+const RAMP_SHAPES: &[&str] = &["linear", "sine", "exponential"];
+const GROUP_ROLES: &[&str] = &["standalone", "coordinator", "follower"];
+
+/// All parameters, which can be provisioned (not `led_crosstalk`, which is measured on the device)
+pub const PARAMS: &[ParamSpec] = &[
+    param("lux_threshold", "lux_threshold", Encoding::F32),
+    param("lux_filter", "lux_filter", Encoding::Enum(LUX_FILTERS)),
+    param("lux_window", "lux_window", Encoding::U32),
+    param("retrigger_guard_ms", "retrig_guard", Encoding::U32),
+    param("retrigger_confirm_ms", "retrig_confirm", Encoding::U32),
+    param("sampling_gap_interval_s", "gap_interval", Encoding::U32),
+    param("sampling_gap_stage", "gap_stage", Encoding::U32),
+    param("led_lux_contribution", "led_lux", Encoding::F32),
+    param("fade_up_s", "fade_up_s", Encoding::F32),
+    param("fade_down_s", "fade_down_s", Encoding::F32),
+    param("zone_trail_s", "zone_trail", Encoding::U32),
+    param("lux_calibration", "lux_calib", Encoding::F32),
+    param("dark_latch_min", "dark_latch", Encoding::U32),
+    param("dark_release_lux", "dark_rel_lux", Encoding::F32),
+    param("dark_release_min", "dark_rel_min", Encoding::U32),
+    param("diag_opt_in", "diag_opt_in", Encoding::Bool),
+    param("diag_url", "diag_url", Encoding::String),
+    param("day_sleep_after_min", "day_sleep_after", Encoding::U32),
+    param("day_sleep_lux", "day_sleep_lux", Encoding::F32),
+    param("day_sleep_wake_min", "day_sleep_wake", Encoding::U32),
+    param("veml_gain", "veml_gain", Encoding::Enum(VEML_GAINS)),
+    param("veml_it_ms", "veml_it", Encoding::U32),
+    param("veml_psm", "veml_psm", Encoding::U32),
+    param("saturation_policy", "saturation", Encoding::Enum(SATURATION_POLICIES)),
+    param("lux_stats_persist", "lux_stats_nvs", Encoding::Bool),
+    param("lux_half_life_ms", "lux_half_life", Encoding::U32),
+    param("second_light_sensor", "light_sensor_2", Encoding::Bool),
+    param("lux_combine", "lux_combine", Encoding::Enum(LUX_COMBINATIONS)),
+    param("fallback_dark_from", "fb_dark_from", Encoding::TimeOfDay),
+    param("fallback_dark_until", "fb_dark_until", Encoding::TimeOfDay),
+    param("latency_budget_ms", "latency_budget", Encoding::U32),
+    param("crosstalk_compensation", "crosstalk_comp", Encoding::Bool),
+    param("max_brightness_pct", "max_bright_pct", Encoding::U32),
+    param("adaptive_lux_factor", "adaptive_lux", Encoding::F32),
+    param("dimming_curve", "dimming_curve", Encoding::Enum(DIMMING_CURVES)),
+    param("led_hw_fade", "led_hw_fade", Encoding::Bool),
+    param("second_led", "second_led", Encoding::Bool),
+    param("second_led_delay_ms", "second_led_dly", Encoding::U32),
+    param("second_led_pct", "second_led_pct", Encoding::U32),
+    param("tunable_white", "tunable_white", Encoding::Bool),
+    param("cct_warm_k", "cct_warm_k", Encoding::U32),
+    param("cct_cold_k", "cct_cold_k", Encoding::U32),
+    param("cct_k", "cct_k", Encoding::U32),
+    param("cct_night_k", "cct_night_k", Encoding::U32),
+    param("cct_night_from", "cct_night_from", Encoding::TimeOfDay),
+    param("cct_night_until", "cct_night_until", Encoding::TimeOfDay),
+    param("local_only_analytics", "local_only", Encoding::Bool),
+    param("rgbw", "rgbw", Encoding::Bool),
+    param("led_color", "led_color", Encoding::String),
+    param("led_strip_pixels", "led_strip_px", Encoding::U32),
+    param("led_strip_rgbw", "led_strip_rgbw", Encoding::Bool),
+    param("strip_effect", "strip_effect", Encoding::Enum(STRIP_EFFECTS)),
+    param("chase_reverse", "chase_reverse", Encoding::Bool),
+    param("night_light_pct", "night_light_pct", Encoding::U32),
+    param("night_light_from", "night_from", Encoding::TimeOfDay),
+    param("night_light_until", "night_until", Encoding::TimeOfDay),
+    param("ambient_brightness_pct", "ambient_bright", Encoding::U32),
+    param("pwm_freq_hz", "pwm_freq_hz", Encoding::U32),
+    param("pwm_resolution_bits", "pwm_res_bits", Encoding::U32),
+    param("invert_pwm", "invert_pwm", Encoding::Bool),
+    param("min_duty_pct", "min_duty_pct", Encoding::F32),
+    param("min_duty_policy", "min_duty_pol", Encoding::Enum(MIN_DUTY_POLICIES)),
+    param("max_on_time_min", "max_on_time", Encoding::U32),
+    param("presence_stage_pct", "presence_stage", Encoding::U32),
+    param("full_on_after_ms", "full_on_after", Encoding::U32),
+    param("ramp_shape", "ramp_shape", Encoding::Enum(RAMP_SHAPES)),
+    param("led_self_test", "led_self_test", Encoding::Bool),
+    param("standby_glow_pct", "standby_glow", Encoding::F32),
+    param("temperature_sensor", "temp_sensor", Encoding::Bool),
+    param("derate_from_c", "derate_from", Encoding::F32),
+    param("derate_to_c", "derate_to", Encoding::F32),
+    param("derate_min_pct", "derate_min_pct", Encoding::U32),
+    param("led_dither", "led_dither", Encoding::Bool),
+    param("led_full_watts", "led_full_watts", Encoding::F32),
+    param("fade_takeover_ms", "fade_takeover", Encoding::U32),
+    param("mqtt_url", "mqtt_url", Encoding::String),
+    param("mqtt_topic", "mqtt_topic", Encoding::String),
+    param("mqtt_heartbeat_s", "mqtt_heartbeat", Encoding::U32),
+    param("ha_discovery", "ha_discovery", Encoding::Bool),
+    param("api_token", "api_token", Encoding::String),
+    param("hostname", "hostname", Encoding::String),
+    param("timezone", "timezone", Encoding::String),
+    param("ble_pin", "ble_pin", Encoding::U32),
+    param("syslog_target", "syslog_target", Encoding::String),
+    param("webhook_urls", "webhook_urls", Encoding::String),
+    param("influx_url", "influx_url", Encoding::String),
+    param("influx_token", "influx_token", Encoding::String),
+    param("influx_interval_s", "influx_int_s", Encoding::U32),
+    param("group_role", "group_role", Encoding::Enum(GROUP_ROLES)),
+    param("group_topic", "group_topic", Encoding::String),
+    param("profile", "profile", Encoding::String),
+    param("persist_always_on", "persist_on", Encoding::Bool),
+    param("camera_pre_delay_ms", "cam_pre_delay", Encoding::U32),
+    param("camera_pulse_ms", "cam_pulse", Encoding::U32),
+    param("camera_hold_s", "cam_hold", Encoding::U32),
+    param("thread_dataset", "thread_dataset", Encoding::String),
+    param("hue_user", "hue_user", Encoding::String),
+];
+
+pub fn find(name: &str) -> Option<&'static ParamSpec> {
+    PARAMS.iter().find(|p| p.name == name)
+}
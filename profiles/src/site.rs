@@ -0,0 +1,178 @@
+//! Site description of a unit - a small subset of TOML
+//!
+//! ```toml
+//! [device]
+//! name = "stairs-top"
+//!
+//! # configuration parameters by their console name
+//! [config]
+//! lux_threshold = 2.5
+//! fade_up_s = 3
+//! dimming_curve = "cie1931"
+//! cct_night_from = "22:30"
+//! ```
+//!
+//! Supported are `[section]` headers, `key = value` lines with strings, integers, floats and booleans, and `#` comments.
+
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Str(s) => write!(f, "\"{}\"", s),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Entry {
+    pub key: String,
+    pub value: Value,
+    pub line: usize,
+}
+
+#[derive(Debug)]
+pub struct Site {
+    /// device name - names the generated files
+    pub name: String,
+    pub config: Vec<Entry>,
+}
+
+pub fn parse(text: &str) -> Result<Site, String> {
+    let mut name = None;
+    let mut config = Vec::new();
+    let mut section = String::new();
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let header = strip_comment(header).strip_suffix(']')
+                .ok_or_else(|| format!("line {}: invalid section header", line_no))?;
+            section = header.trim().to_string();
+            match section.as_str() {
+                "device" | "config" => {}
+                "pins" => return Err(format!("line {}: pins are fixed in the firmware (see hardware/hardware-notes.md)", line_no)),
+                "wifi" => return Err(format!("line {}: the ESP32-H2 has no Wi-Fi", line_no)),
+                _ => return Err(format!("line {}: unknown section [{}] (expected [device] or [config])", line_no, section))
+            }
+            continue;
+        }
+        let (key, value) = line.split_once('=')
+            .ok_or_else(|| format!("line {}: expected key = value", line_no))?;
+        let key = key.trim().to_string();
+        let value = parse_value(value.trim()).map_err(|e| format!("line {}: {}", line_no, e))?;
+        match (section.as_str(), key.as_str(), value) {
+            ("device", "name", Value::Str(s)) => name = Some(s),
+            ("device", _, _) => return Err(format!("line {}: unknown device entry '{}' (expected name = \"...\")", line_no, key)),
+            ("config", _, value) => config.push(Entry { key, value, line: line_no }),
+            _ => return Err(format!("line {}: entry outside of a section", line_no))
+        }
+    }
+    let name = name.ok_or("missing device name ([device] name = \"...\")")?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("invalid device name '{}' (letters, digits, '-' and '_' only)", name));
+    }
+    Ok(Site { name, config })
+}
+
+fn parse_value(s: &str) -> Result<Value, String> {
+    if let Some(rest) = s.strip_prefix('"') {
+        let (content, tail) = rest.split_once('"').ok_or("unterminated string")?;
+        if !strip_comment(tail).trim().is_empty() {
+            return Err("unexpected characters after the string".to_string());
+        }
+        return Ok(Value::Str(content.to_string()));
+    }
+    let s = strip_comment(s).trim();
+    match s {
+        "true" => Ok(Value::Bool(true)),
+        "false" => Ok(Value::Bool(false)),
+        _ => {
+            let number = s.replace('_', "");
+            if let Ok(i) = number.parse() {
+                Ok(Value::Int(i))
+            } else if let Ok(x) = number.parse() {
+                Ok(Value::Float(x))
+            } else {
+                Err(format!("invalid value '{}'", s))
+            }
+        }
+    }
+}
+
+fn strip_comment(s: &str) -> &str {
+    s.split_once('#').map_or(s, |(before, _)| before)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_device_name_and_config_entries() {
+        let site = parse(r#"
+            # stairs, upper floor
+            [device]
+            name = "stairs-top"
+
+            [config]
+            lux_threshold = 2.5  # darker than the default
+            lux_window = 1_000
+            dimming_curve = "cie1931"
+            led_hw_fade = true
+        "#).unwrap();
+        assert_eq!(site.name, "stairs-top");
+        let entries = site.config.iter().map(|e| (e.key.as_str(), e.value.to_string(), e.line)).collect::<Vec<_>>();
+        assert_eq!(entries, [
+            ("lux_threshold", "2.5".to_string(), 7),
+            ("lux_window", "1000".to_string(), 8),
+            ("dimming_curve", "\"cie1931\"".to_string(), 9),
+            ("led_hw_fade", "true".to_string(), 10),
+        ]);
+        assert!(matches!(site.config[1].value, Value::Int(1000)));
+        assert!(matches!(site.config[0].value, Value::Float(x) if x == 2.5));
+    }
+
+    #[test]
+    fn keeps_a_hash_within_a_string() {
+        let site = parse("[device]\nname = \"hall\"\n[config]\nmqtt_topic = \"home#1\" # comment\n").unwrap();
+        assert!(matches!(&site.config[0].value, Value::Str(s) if s == "home#1"));
+    }
+
+    #[test]
+    fn rejects_a_missing_or_invalid_device_name() {
+        assert!(parse("[config]\nlux_threshold = 2\n").unwrap_err().contains("missing device name"));
+        assert!(parse("[device]\nname = \"stairs top\"\n").unwrap_err().contains("invalid device name"));
+        assert!(parse("[device]\nname = 1\n").unwrap_err().contains("unknown device entry"));
+    }
+
+    #[test]
+    fn reports_the_line_of_an_error() {
+        assert_eq!(parse("[device]\nname = \"a\"\n[config]\nfade_up_s\n").unwrap_err(), "line 4: expected key = value");
+        assert_eq!(parse("[device]\nname = \"a\"\n[config]\nx = \"open\n").unwrap_err(), "line 4: unterminated string");
+        assert_eq!(parse("[device]\nname = \"a\"\n[config]\nx = 1 2\n").unwrap_err(), "line 4: invalid value '1 2'");
+        assert_eq!(parse("x = 1\n").unwrap_err(), "line 1: entry outside of a section");
+    }
+
+    #[test]
+    fn rejects_unsupported_sections() {
+        assert!(parse("[wifi]\n").unwrap_err().contains("no Wi-Fi"));
+        assert!(parse("[pins]\n").unwrap_err().contains("pins are fixed"));
+        assert!(parse("[other]\n").unwrap_err().contains("unknown section [other]"));
+        assert!(parse("[device\n").unwrap_err().contains("invalid section header"));
+    }
+}